@@ -1,32 +1,9 @@
+use pixtra::blur::{BlurOptions, EdgeMode};
 use pixtra::canvas::{Canvas, Island};
 use pixtra::pixels::{Pixel, PixelBuilder};
 use pixtra::utility::{to_grey_lumiosity, count_colors, counted_colors_to_html};
 use std::path::Path;
 
-fn gaussian_filter(canvas: &Canvas, x: u32, y: u32) -> Pixel {
-    // NOTE: The kernel needs to sum to 1.0. Below 1.0 your image will appear darker
-    // and above 1.0 it will appear lighter.
-    let kernel = vec![0.06, 0.13, 0.06, 0.13, 0.24, 0.13, 0.06, 0.13, 0.06];
-    let coords = vec![
-        (-1, -1),
-        (0, -1),
-        (1, -1),
-        (-1, 0),
-        (0, 0),
-        (1, 0),
-        (-1, 1),
-        (0, 1),
-        (1, 1),
-    ];
-    // For simplicity, we will leave out the edges of the picture.
-    let canvas_size = canvas.dimensions();
-    if x > 0 && y > 0 && x < canvas_size.width - 1 && y < canvas_size.height - 1 {
-        let pixel = apply_filter(canvas, (x, y), &kernel, &coords);
-        return pixel;
-    }
-    canvas.get_pixel(x, y)
-}
-
 fn lap_of_gaussian_filter(canvas: &Canvas, x: u32, y: u32) -> Pixel {
     // NOTE: The kernel needs to sum to 1.0. Below 1.0 your image will appear darker
     // and above 1.0 it will appear lighter.
@@ -171,20 +148,47 @@ fn main() {
     // Gaussian blur
     let canvas = Canvas::load(Path::new("assets/lena.png")).unwrap();
     let test_image = Canvas::load(Path::new("assets/IMG_0771.JPG")).unwrap();
-    let gaussian_canvas = canvas.filter(gaussian_filter);
+    let gaussian_canvas = canvas.clone().gaussian_blur(1.0);
     let _ = gaussian_canvas
         .save(Path::new("gaussian_canvas.png"))
         .unwrap();
-    let gaussian_canvas = gaussian_canvas.filter(gaussian_filter);
-    let gaussian_canvas = gaussian_canvas.filter(gaussian_filter);
-    let gaussian_canvas = gaussian_canvas.filter(gaussian_filter);
-    let gaussian_canvas = gaussian_canvas.filter(gaussian_filter);
+    // Large sigmas are as fast as small ones
+    let gaussian_canvas = canvas.clone().gaussian_blur(8.0);
     let _ = gaussian_canvas
         .save(Path::new("very_gaussian_canvas.png"))
         .unwrap();
+    // Blending in linear light keeps bright details from turning muddy
+    let options = BlurOptions::new().set_linear_light(true).set_edge_mode(EdgeMode::Mirror);
+    let _ = canvas
+        .clone()
+        .gaussian_blur_with(8.0, &options)
+        .save(Path::new("linear_gaussian_canvas.png"))
+        .unwrap();
+    let _ = canvas
+        .clone()
+        .box_blur(6)
+        .save(Path::new("box_blur_canvas.png"))
+        .unwrap();
+    let _ = canvas
+        .clone()
+        .motion_blur(20.0, 30.0)
+        .save(Path::new("motion_blur_canvas.png"))
+        .unwrap();
+
+    // Sharpening saturates instead of overflowing, and the threshold leaves noise alone
+    let _ = canvas
+        .clone()
+        .unsharp_mask(2.0, 1.5, 8)
+        .save(Path::new("unsharp_mask_canvas.png"))
+        .unwrap();
+    let _ = canvas
+        .clone()
+        .clarity(20.0, 0.6)
+        .save(Path::new("clarity_canvas.png"))
+        .unwrap();
 
     // Chaining filters
-    let inverse_gaussian_canvas = canvas.filter(inverse_filter).filter(gaussian_filter);
+    let inverse_gaussian_canvas = canvas.filter(inverse_filter).gaussian_blur(1.0);
     let _ = inverse_gaussian_canvas
         .save(Path::new("inverse_gaussian_canvas.png"))
         .unwrap();
@@ -0,0 +1,61 @@
+use pixtra::canvas::{Canvas, Island, Point};
+use pixtra::pixels::{ColorTrait, Colors};
+use pixtra::text::{BitmapFont, TextAlign, TextStyle};
+use std::path::Path;
+
+fn top_left(island: &Island) -> Point {
+    let x = island.points.iter().map(|p| p.x).min().unwrap_or(0);
+    let y = island.points.iter().map(|p| p.y).min().unwrap_or(0);
+    Point { x, y }
+}
+
+fn annotate(canvas: &Canvas) -> Canvas {
+    let font = BitmapFont::default();
+    let style = TextStyle::new(&Colors::BLACK).set_scale(2);
+    let islands = canvas.find_islands(&Colors::GREEN);
+
+    let mut result = canvas.clone();
+    for (i, island) in islands.iter().enumerate() {
+        result.draw_text_mut(&format!("{}", i + 1), &top_left(island), &font, &style);
+    }
+
+    let title = format!("{} islands", islands.len());
+    let size = font.measure_text(&title, 1);
+    let width = canvas.dimensions().width;
+    result.draw_square_mut(
+        width.saturating_sub(size.width + 4),
+        0,
+        size.width + 4,
+        size.height + 2,
+        &Colors::WHITE,
+    );
+    let style = TextStyle::new(&Colors::RED).set_align(TextAlign::Right);
+    result.draw_text(
+        &title,
+        &Point {
+            x: width.saturating_sub(2),
+            y: 1,
+        },
+        &font,
+        &style,
+    )
+}
+
+fn main() {
+    let canvas = Canvas::load(Path::new("assets/green_islands.png")).unwrap();
+    let annotated = annotate(&canvas);
+    annotated.save(Path::new("annotated-islands.png")).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotations_are_drawn() {
+        let canvas = Canvas::load(Path::new("assets/green_islands.png")).unwrap();
+        let annotated = annotate(&canvas);
+        assert!(annotated.count_pixels(&Colors::RED) > 0);
+        assert!(annotated.count_pixels(&Colors::BLACK) > canvas.count_pixels(&Colors::BLACK));
+    }
+}
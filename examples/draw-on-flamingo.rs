@@ -1,6 +1,6 @@
 use pixtra::canvas::Canvas;
 use pixtra::pixels::{ColorTrait, Colors, Pixel};
-use pixtra::utility::{diff_debug, error};
+use pixtra::utility::diff_debug;
 use std::path::Path;
 
 fn find_non_zero(p: &Pixel, _x: u32, _y: u32) -> bool {
@@ -15,7 +15,7 @@ fn main() {
     let result = background.draw_subimage(0, 0, &foreground);
 
     let compare_image = Canvas::load(Path::new("assets/draw-on-example-result.png")).unwrap();
-    let diff = diff_debug(&result, &compare_image);
+    let diff = diff_debug(&result, &compare_image).unwrap();
     diff.save(Path::new("diff_debug_flamingo.out.png")).unwrap();
 
     result.save(Path::new("flamingo.out.png")).unwrap();
@@ -33,6 +33,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pixtra::assert_canvas_eq;
 
     #[test]
     fn test_blending_on_known_image() {
@@ -41,9 +42,6 @@ mod tests {
 
         let result = background.draw_subimage(0, 0, &foreground);
 
-        let compared_image = Canvas::load(Path::new("assets/draw-on-example-result.png")).unwrap();
-        let diff = diff_debug(&result, &compared_image);
-        let error_count = diff.find_with_predicate(find_non_zero).len();
-        assert_eq!(error_count, 0);
+        assert_canvas_eq!(result, "assets/draw-on-example-result.png");
     }
 }
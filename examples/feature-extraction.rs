@@ -1,4 +1,5 @@
 use pixtra::canvas::{Canvas, Island};
+use pixtra::features::CornerOptions;
 use pixtra::pixels::{Pixel, PixelBuilder};
 use pixtra::utility::{to_grey_lumiosity, count_colors, counted_colors_to_html};
 use std::path::Path;
@@ -82,9 +83,22 @@ fn main() {
     }
     fix_image_with_map_grapes(grapes);
     fix_image_with_map_donut(donut);
+    draw_corners(donut);
 
 
 }
+fn draw_corners(image_name: &str) {
+    let test_image = Canvas::load(Path::new(&format!("assets/{}.JPG", image_name))).unwrap();
+    let options = CornerOptions::new().set_max_corners(200).set_min_distance(20.0);
+    let corners = test_image.shi_tomasi_corners(&options);
+    let fast_corners = test_image.fast_corners(40);
+    test_image
+        .draw_keypoints(&fast_corners, &Pixel::new(0, 255, 0, 255))
+        .draw_keypoints(&corners, &Pixel::new(255, 0, 0, 255))
+        .save(&Path::new(&format!("{}-corners.png", image_name)))
+        .unwrap();
+}
+
 fn fix_image_with_map(image_name: &str) {
 
     let test_image = Canvas::load(Path::new(&format!("assets/{}.JPG", image_name))).unwrap();
@@ -1,4 +1,5 @@
 use pixtra::canvas::Canvas;
+use pixtra::inpaint::InpaintMethod;
 use std::collections::HashMap;
 use pixtra::pixels::{ColorTrait, Colors};
 use pixtra::utility::count_colors;
@@ -34,5 +35,11 @@ fn main() {
     }
     println!("Island 2 hashmap: {}", hashmap.len());
 
-
+    // Fill the islands from their surroundings instead of painting over them
+    let mut cleaned = canvas.clone();
+    for island in islands.iter() {
+        cleaned.inpaint_island_mut(island, InpaintMethod::Telea, 3);
+    }
+    println!("Islands after inpainting: {}", cleaned.find_islands(&Colors::GREEN).len());
+    cleaned.save(Path::new("inpainted-islands.png")).unwrap();
 }
@@ -0,0 +1,61 @@
+use pixtra::canvas::{Canvas, Point, Rect, Size};
+use pixtra::gradient::{Gradient, Interpolation};
+use pixtra::path::{FillRule, Path};
+use pixtra::pixels::{ColorTrait, Colors, Pixel};
+use std::f32::consts::PI;
+use std::path::Path as FilePath;
+
+fn heatmap(x0: f32, x1: f32) -> Gradient {
+    Gradient::linear(x0, 0.0, x1, 0.0)
+        .add_stop(0.0, &Pixel::new(0, 0, 128, 255))
+        .add_stop(0.35, &Colors::BLUE)
+        .add_stop(0.5, &Colors::GREEN)
+        .add_stop(0.75, &Pixel::new(255, 255, 0, 255))
+        .add_stop(1.0, &Colors::RED)
+        .set_interpolation(Interpolation::Oklab)
+}
+
+fn main() {
+    // Placeholder art: a radial glow on a linear background
+    let background = Gradient::linear(0.0, 0.0, 0.0, 200.0)
+        .add_stop(0.0, &Pixel::new(30, 30, 60, 255))
+        .add_stop(1.0, &Pixel::new(200, 120, 160, 255))
+        .set_interpolation(Interpolation::LinearRgb);
+    let glow = Gradient::radial(100.0, 80.0, 60.0)
+        .add_stop(0.0, &Pixel::new(255, 255, 200, 255))
+        .add_stop(1.0, &Pixel::new(255, 255, 200, 0));
+    let canvas = Canvas::new_with_gradient(200, 200, &background).fill_gradient(&glow);
+
+    // A color wheel drawn with a conic gradient inside a circle
+    let wheel = Gradient::conic(160.0, 40.0, -PI / 2.0)
+        .add_stop(0.0, &Colors::RED)
+        .add_stop(1.0 / 3.0, &Colors::GREEN)
+        .add_stop(2.0 / 3.0, &Colors::BLUE)
+        .add_stop(1.0, &Colors::RED);
+    let canvas =
+        canvas.fill_path_gradient(&Path::circle(160.0, 40.0, 30.0), &wheel, FillRule::NonZero);
+
+    // A heatmap legend at the bottom
+    let legend = Rect {
+        start: Point { x: 20, y: 170 },
+        size: Size {
+            width: 160,
+            height: 15,
+        },
+    };
+    let canvas = canvas.fill_gradient_rect(&legend, &heatmap(20.0, 180.0));
+
+    canvas.save(FilePath::new("gradients.png")).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legend_runs_from_blue_to_red() {
+        let canvas = Canvas::new_with_gradient(100, 2, &heatmap(0.5, 99.5));
+        assert_eq!(canvas.get_pixel(0, 0), Pixel::new(0, 0, 128, 255));
+        assert_eq!(canvas.get_pixel(99, 0), Colors::RED);
+    }
+}
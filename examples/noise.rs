@@ -0,0 +1,55 @@
+use pixtra::canvas::Canvas;
+use pixtra::gradient::{Gradient, Interpolation};
+use pixtra::noise::Noise;
+use pixtra::pixels::Pixel;
+use pixtra::utility::merge_chunks_horizontally;
+use std::path::Path;
+
+fn terrain() -> Gradient {
+    Gradient::linear(0.0, 0.0, 1.0, 0.0)
+        .add_stop(0.0, &Pixel::new(10, 30, 120, 255))
+        .add_stop(0.45, &Pixel::new(40, 110, 200, 255))
+        .add_stop(0.5, &Pixel::new(230, 210, 150, 255))
+        .add_stop(0.6, &Pixel::new(60, 150, 60, 255))
+        .add_stop(0.8, &Pixel::new(110, 90, 70, 255))
+        .add_stop(1.0, &Pixel::new(250, 250, 250, 255))
+        .set_interpolation(Interpolation::Oklab)
+}
+
+fn main() {
+    let size = 128;
+    let samples = vec![
+        Canvas::new_with_noise(size, size, &Noise::white(1)),
+        Canvas::new_with_noise(size, size, &Noise::perlin(1).set_scale(24.0)),
+        Canvas::new_with_noise(size, size, &Noise::simplex(1).set_scale(24.0)),
+        Canvas::new_with_noise(size, size, &Noise::worley(1).set_scale(16.0)),
+        Canvas::new_with_noise(size, size, &Noise::perlin(1).set_octaves(5)),
+    ];
+    merge_chunks_horizontally(&samples)
+        .save(Path::new("noise.png"))
+        .unwrap();
+
+    // A fractal island map that can be repeated without seams
+    let map = Noise::simplex(42).set_scale(64.0).set_octaves(6);
+    let tile = Canvas::new_with_tileable_noise_gradient(size, size, &map, &terrain());
+    merge_chunks_horizontally(&vec![tile.clone(), tile])
+        .save(Path::new("noise-tiled.png"))
+        .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_have_matching_edges() {
+        let map = Noise::perlin(42).set_scale(64.0).set_octaves(4);
+        let tile = Canvas::new_with_tileable_noise_gradient(64, 64, &map, &terrain());
+        // The left column continues the right column, so neighbours are close in color
+        for y in 0..64 {
+            let left = tile.get_pixel(0, y);
+            let right = tile.get_pixel(63, y);
+            assert!(left.distance(&right) < 40.0);
+        }
+    }
+}
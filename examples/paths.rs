@@ -0,0 +1,56 @@
+use pixtra::canvas::Canvas;
+use pixtra::path::{FillRule, LineCap, LineJoin, Path, Stroke};
+use pixtra::pixels::{ColorTrait, Colors, Pixel};
+use std::path::Path as FilePath;
+
+fn heart() -> Path {
+    Path::new()
+        .move_to(100.0, 60.0)
+        .cubic_to(100.0, 30.0, 50.0, 20.0, 40.0, 60.0)
+        .cubic_to(30.0, 100.0, 90.0, 130.0, 100.0, 160.0)
+        .cubic_to(110.0, 130.0, 170.0, 100.0, 160.0, 60.0)
+        .cubic_to(150.0, 20.0, 100.0, 30.0, 100.0, 60.0)
+        .close()
+}
+
+fn main() {
+    let canvas = Canvas::new(200, 200);
+
+    // Fill a heart and outline it with a rounded stroke
+    let canvas = canvas.fill_path(&heart(), &Colors::RED, FillRule::NonZero);
+    let stroke = Stroke::new(4.0)
+        .set_join(LineJoin::Round)
+        .set_cap(LineCap::Round);
+    let canvas = canvas.stroke_path(&heart(), &stroke, &Colors::BLACK);
+
+    // Two overlapping squares show the difference between the fill rules
+    let squares = Path::rect(10.0, 150.0, 40.0, 40.0)
+        .move_to(20.0, 160.0)
+        .line_to(40.0, 160.0)
+        .line_to(40.0, 180.0)
+        .line_to(20.0, 180.0)
+        .close();
+    let canvas = canvas.fill_path(&squares, &Pixel::new(0, 0, 255, 160), FillRule::EvenOdd);
+
+    // A zig-zag with a quadratic tail
+    let zig_zag = Path::new()
+        .move_to(120.0, 180.0)
+        .line_to(140.0, 160.0)
+        .line_to(160.0, 180.0)
+        .quad_to(180.0, 200.0, 190.0, 150.0);
+    let canvas = canvas.stroke_path(&zig_zag, &Stroke::new(3.0), &Colors::GREEN);
+
+    canvas.save(FilePath::new("paths.png")).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heart_is_symmetric() {
+        let canvas = Canvas::new(200, 200).fill_path(&heart(), &Colors::RED, FillRule::NonZero);
+        assert_eq!(canvas, canvas.flip());
+        assert!(canvas.count_pixels(&Colors::RED) > 5000);
+    }
+}
@@ -1,6 +1,6 @@
 use pixtra::canvas::Canvas;
 use pixtra::pixels::Pixel;
-use pixtra::utility::error;
+use pixtra::metrics::mse;
 use std::path::Path;
 
 fn draw_filter(_: &Canvas, x: u32, y: u32) -> Pixel {
@@ -18,7 +18,7 @@ fn main() {
     let canvas = canvas.rotate90();
     let canvas = canvas.rotate90();
     let canvas = canvas.rotate90();
-    let rotate_error = error(&canvas_old, &canvas);
+    let rotate_error = mse(&canvas_old, &canvas).unwrap();
     println!("ERROR: {}", rotate_error);
 
     let canvas = Canvas::new(200, 100);
@@ -0,0 +1,32 @@
+use pixtra::canvas::Canvas;
+use pixtra::mask::Mask;
+use pixtra::pixels::{ColorTrait, Colors};
+use pixtra::seam::SeamCarveOptions;
+use std::path::Path;
+
+fn main() {
+    // Narrower without squeezing the face
+    let canvas = Canvas::load(Path::new("assets/lena.png")).unwrap();
+    let _ = canvas
+        .clone()
+        .seam_carve(448, 512)
+        .save(Path::new("seam-carved-lena.png"))
+        .unwrap();
+
+    // Keep the first island as it is and erase the second one
+    let canvas = Canvas::load(Path::new("assets/small_green_islands.png")).unwrap();
+    let size = canvas.dimensions();
+    let islands = canvas.find_islands(&Colors::GREEN);
+    println!("Found {} islands", islands.len());
+    if islands.len() >= 2 {
+        let options = SeamCarveOptions::new()
+            .set_protect(Mask::from_island(size.width, size.height, &islands[0]))
+            .set_remove(Mask::from_island(size.width, size.height, &islands[1]));
+        let carved = canvas.seam_carve_with(size.width - 4, size.height, &options);
+        println!(
+            "Islands left after carving: {}",
+            carved.find_islands(&Colors::GREEN).len()
+        );
+        let _ = carved.save(Path::new("seam-carved-islands.png")).unwrap();
+    }
+}
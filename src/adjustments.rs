@@ -0,0 +1,457 @@
+use crate::canvas::Canvas;
+use crate::colorspace::{linear_to_pixel, oklab_to_pixel, pixel_to_linear, pixel_to_oklab};
+use crate::pixels::Pixel;
+
+/// Chroma of the most saturated sRGB colors in Oklab. Used to weigh `vibrance`.
+const MAX_CHROMA: f32 = 0.33;
+
+/// Input and output black and white points for a levels adjustment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Levels {
+    pub in_black: u8,
+    pub in_white: u8,
+    pub gamma: f32,
+    pub out_black: u8,
+    pub out_white: u8,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels::new()
+    }
+}
+
+impl Levels {
+    /// Creates levels that leave every value unchanged
+    pub fn new() -> Levels {
+        Levels {
+            in_black: 0,
+            in_white: 255,
+            gamma: 1.0,
+            out_black: 0,
+            out_white: 255,
+        }
+    }
+
+    /// Values at or below `black` become the output black point and values at or above `white`
+    /// become the output white point.
+    pub fn set_input(mut self, black: u8, white: u8) -> Levels {
+        self.in_black = black;
+        self.in_white = white;
+        self
+    }
+
+    /// Sets the gamma applied between the input and output range. Above `1.0` brightens midtones.
+    pub fn set_gamma(mut self, gamma: f32) -> Levels {
+        self.gamma = gamma;
+        self
+    }
+
+    /// Sets the range the output is compressed into.
+    pub fn set_output(mut self, black: u8, white: u8) -> Levels {
+        self.out_black = black;
+        self.out_white = white;
+        self
+    }
+
+    /// Maps a single channel value through the levels
+    pub fn apply(&self, value: u8) -> u8 {
+        let range = (self.in_white as f32 - self.in_black as f32).max(1.0);
+        let t = ((value as f32 - self.in_black as f32) / range).clamp(0.0, 1.0);
+        let t = t.powf(1.0 / self.gamma.max(f32::EPSILON));
+        let out = self.out_black as f32 + t * (self.out_white as f32 - self.out_black as f32);
+        out.round().clamp(0.0, 255.0) as u8
+    }
+}
+
+/// A tone curve through control points. Points are `(input, output)` pairs between `0.0` and
+/// `1.0` and the curve is a monotone cubic spline, so it never overshoots between points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve {
+    points: Vec<(f32, f32)>,
+    tangents: Vec<f32>,
+}
+
+impl Curve {
+    /// Creates a curve through `points`. Points are sorted by input and inputs appearing twice
+    /// keep only the first point. With fewer than two points the curve is the identity.
+    pub fn new(points: &[(f32, f32)]) -> Curve {
+        let mut points: Vec<(f32, f32)> = points
+            .iter()
+            .map(|&(x, y)| (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+            .collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|a, b| a.0 == b.0);
+        if points.len() < 2 {
+            points = vec![(0.0, 0.0), (1.0, 1.0)];
+        }
+
+        // Fritsch-Carlson tangents
+        let n = points.len();
+        let slopes: Vec<f32> = points
+            .windows(2)
+            .map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0))
+            .collect();
+        let mut tangents = vec![0.0; n];
+        tangents[0] = slopes[0];
+        tangents[n - 1] = slopes[n - 2];
+        for i in 1..n - 1 {
+            tangents[i] = if slopes[i - 1] * slopes[i] <= 0.0 {
+                0.0
+            } else {
+                (slopes[i - 1] + slopes[i]) / 2.0
+            };
+        }
+        for i in 0..n - 1 {
+            if slopes[i] == 0.0 {
+                tangents[i] = 0.0;
+                tangents[i + 1] = 0.0;
+                continue;
+            }
+            let alpha = tangents[i] / slopes[i];
+            let beta = tangents[i + 1] / slopes[i];
+            let length = alpha.hypot(beta);
+            if length > 3.0 {
+                let tau = 3.0 / length;
+                tangents[i] = tau * alpha * slopes[i];
+                tangents[i + 1] = tau * beta * slopes[i];
+            }
+        }
+        Curve { points, tangents }
+    }
+
+    /// Returns the control points of the curve
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// Evaluates the curve at `x`. Inputs outside the first and last point are held constant.
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let last = self.points.len() - 1;
+        if x <= self.points[0].0 {
+            return self.points[0].1;
+        }
+        if x >= self.points[last].0 {
+            return self.points[last].1;
+        }
+        let i = self.points.partition_point(|p| p.0 <= x) - 1;
+        let (x0, y0) = self.points[i];
+        let (x1, y1) = self.points[i + 1];
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        let value = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * h * self.tangents[i]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * h * self.tangents[i + 1];
+        value.clamp(0.0, 1.0)
+    }
+
+    /// Samples the curve for every channel value
+    pub fn to_table(&self) -> [u8; 256] {
+        let mut table = [0u8; 256];
+        for (value, entry) in table.iter_mut().enumerate() {
+            *entry = (self.evaluate(value as f32 / 255.0) * 255.0).round() as u8;
+        }
+        table
+    }
+}
+
+/// Maps the color channels of `p` through `table`, keeping alpha.
+fn map_table(p: &Pixel, table: &[u8; 256]) -> Pixel {
+    Pixel::new(
+        table[p.r as usize],
+        table[p.g as usize],
+        table[p.b as usize],
+        p.a,
+    )
+}
+
+/// Maps every normalized channel value through `f`.
+fn table_from(f: impl Fn(f32) -> f32) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (value, entry) in table.iter_mut().enumerate() {
+        *entry = (f(value as f32 / 255.0) * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+    table
+}
+
+impl Canvas {
+    /// Replaces every pixel with the result of `f`.
+    fn map_pixels_mut(&mut self, f: impl Fn(&Pixel) -> Pixel) {
+        let dimensions = self.dimensions();
+        let pixels = self.pixels().map(f).collect();
+        *self = Canvas::new_with_data(dimensions.width, dimensions.height, pixels);
+    }
+
+    /// Applies `table` to the red, green and blue channels of every pixel.
+    fn map_table_mut(&mut self, table: &[u8; 256]) {
+        self.map_pixels_mut(|p| map_table(p, table));
+    }
+
+    /// Adds `amount` to every color channel. `amount` is between `-1.0` and `1.0`.
+    pub fn brightness_mut(&mut self, amount: f32) {
+        self.map_table_mut(&table_from(|v| v + amount));
+    }
+
+    /// Adds `amount` to every color channel. `amount` is between `-1.0` and `1.0`.
+    pub fn brightness(mut self, amount: f32) -> Canvas {
+        self.brightness_mut(amount);
+        self
+    }
+
+    /// Scales the distance of every color channel from mid grey by `factor`. `1.0` leaves the
+    /// canvas unchanged and `0.0` makes it flat grey.
+    pub fn contrast_mut(&mut self, factor: f32) {
+        self.map_table_mut(&table_from(|v| (v - 0.5) * factor + 0.5));
+    }
+
+    /// Scales the distance of every color channel from mid grey by `factor`. `1.0` leaves the
+    /// canvas unchanged and `0.0` makes it flat grey.
+    pub fn contrast(mut self, factor: f32) -> Canvas {
+        self.contrast_mut(factor);
+        self
+    }
+
+    /// Applies gamma correction. Values of `gamma` above `1.0` brighten the midtones.
+    pub fn gamma_mut(&mut self, gamma: f32) {
+        let exponent = 1.0 / gamma.max(f32::EPSILON);
+        self.map_table_mut(&table_from(|v| v.powf(exponent)));
+    }
+
+    /// Applies gamma correction. Values of `gamma` above `1.0` brighten the midtones.
+    pub fn gamma(mut self, gamma: f32) -> Canvas {
+        self.gamma_mut(gamma);
+        self
+    }
+
+    /// Changes the exposure by `stops`. Every stop doubles or halves the light in linear RGB.
+    pub fn exposure_mut(&mut self, stops: f32) {
+        let factor = 2f32.powf(stops);
+        self.map_pixels_mut(|p| {
+            let (r, g, b) = pixel_to_linear(p);
+            linear_to_pixel(r * factor, g * factor, b * factor, p.a)
+        });
+    }
+
+    /// Changes the exposure by `stops`. Every stop doubles or halves the light in linear RGB.
+    pub fn exposure(mut self, stops: f32) -> Canvas {
+        self.exposure_mut(stops);
+        self
+    }
+
+    /// Applies `levels` to the red, green and blue channels.
+    pub fn levels_mut(&mut self, levels: &Levels) {
+        let mut table = [0u8; 256];
+        for (value, entry) in table.iter_mut().enumerate() {
+            *entry = levels.apply(value as u8);
+        }
+        self.map_table_mut(&table);
+    }
+
+    /// Applies `levels` to the red, green and blue channels.
+    pub fn levels(mut self, levels: &Levels) -> Canvas {
+        self.levels_mut(levels);
+        self
+    }
+
+    /// Maps the red, green and blue channels through `curve`.
+    pub fn curves_mut(&mut self, curve: &Curve) {
+        self.map_table_mut(&curve.to_table());
+    }
+
+    /// Maps the red, green and blue channels through `curve`.
+    pub fn curves(mut self, curve: &Curve) -> Canvas {
+        self.curves_mut(curve);
+        self
+    }
+
+    /// Maps each color channel through its own curve.
+    pub fn curves_rgb_mut(&mut self, red: &Curve, green: &Curve, blue: &Curve) {
+        let (red, green, blue) = (red.to_table(), green.to_table(), blue.to_table());
+        self.map_pixels_mut(|p| {
+            Pixel::new(
+                red[p.r as usize],
+                green[p.g as usize],
+                blue[p.b as usize],
+                p.a,
+            )
+        });
+    }
+
+    /// Maps each color channel through its own curve.
+    pub fn curves_rgb(mut self, red: &Curve, green: &Curve, blue: &Curve) -> Canvas {
+        self.curves_rgb_mut(red, green, blue);
+        self
+    }
+
+    /// Rotates the hue of every pixel by `degrees` in Oklab. Lightness and chroma are kept.
+    pub fn hue_rotate_mut(&mut self, degrees: f32) {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        self.map_pixels_mut(|p| {
+            let (l, a, b) = pixel_to_oklab(p);
+            oklab_to_pixel(l, a * cos - b * sin, a * sin + b * cos, p.a)
+        });
+    }
+
+    /// Rotates the hue of every pixel by `degrees` in Oklab. Lightness and chroma are kept.
+    pub fn hue_rotate(mut self, degrees: f32) -> Canvas {
+        self.hue_rotate_mut(degrees);
+        self
+    }
+
+    /// Scales the chroma of every pixel by `factor` in Oklab. `0.0` gives a grey image with
+    /// the same perceived lightness.
+    pub fn saturation_mut(&mut self, factor: f32) {
+        let factor = factor.max(0.0);
+        self.map_pixels_mut(|p| {
+            let (l, a, b) = pixel_to_oklab(p);
+            oklab_to_pixel(l, a * factor, b * factor, p.a)
+        });
+    }
+
+    /// Scales the chroma of every pixel by `factor` in Oklab. `0.0` gives a grey image with
+    /// the same perceived lightness.
+    pub fn saturation(mut self, factor: f32) -> Canvas {
+        self.saturation_mut(factor);
+        self
+    }
+
+    /// Increases the chroma of muted colors more than that of already saturated colors.
+    /// `amount` is between `-1.0` and `1.0`.
+    pub fn vibrance_mut(&mut self, amount: f32) {
+        self.map_pixels_mut(|p| {
+            let (l, a, b) = pixel_to_oklab(p);
+            let chroma = a.hypot(b);
+            let weight = (1.0 - chroma / MAX_CHROMA).clamp(0.0, 1.0);
+            let factor = (1.0 + amount * weight).max(0.0);
+            oklab_to_pixel(l, a * factor, b * factor, p.a)
+        });
+    }
+
+    /// Increases the chroma of muted colors more than that of already saturated colors.
+    /// `amount` is between `-1.0` and `1.0`.
+    pub fn vibrance(mut self, amount: f32) -> Canvas {
+        self.vibrance_mut(amount);
+        self
+    }
+
+    /// Shifts the white balance. Positive `temperature` warms the image towards yellow and
+    /// negative cools it towards blue. Positive `tint` shifts towards magenta and negative
+    /// towards green. Both are between `-1.0` and `1.0`.
+    pub fn temperature_tint_mut(&mut self, temperature: f32, tint: f32) {
+        let (shift_a, shift_b) = (tint * 0.1, temperature * 0.1);
+        self.map_pixels_mut(|p| {
+            let (l, a, b) = pixel_to_oklab(p);
+            oklab_to_pixel(l, a + shift_a, b + shift_b, p.a)
+        });
+    }
+
+    /// Shifts the white balance. Positive `temperature` warms the image towards yellow and
+    /// negative cools it towards blue. Positive `tint` shifts towards magenta and negative
+    /// towards green. Both are between `-1.0` and `1.0`.
+    pub fn temperature_tint(mut self, temperature: f32, tint: f32) -> Canvas {
+        self.temperature_tint_mut(temperature, tint);
+        self
+    }
+
+    /// Inverts the red, green and blue channels. Alpha is kept.
+    pub fn invert_mut(&mut self) {
+        self.map_pixels_mut(|p| Pixel::new(255 - p.r, 255 - p.g, 255 - p.b, p.a));
+    }
+
+    /// Inverts the red, green and blue channels. Alpha is kept.
+    pub fn invert(mut self) -> Canvas {
+        self.invert_mut();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+
+    fn single(p: Pixel) -> Canvas {
+        Canvas::new_with_background(1, 1, p)
+    }
+
+    #[test]
+    fn simple_tone_adjustments() {
+        let grey = single(Pixel::new(100, 100, 100, 200));
+        assert_eq!(
+            grey.clone().brightness(0.2).get_pixel(0, 0),
+            Pixel::new(151, 151, 151, 200)
+        );
+        assert_eq!(
+            grey.clone().contrast(0.0).get_pixel(0, 0),
+            Pixel::new(128, 128, 128, 200)
+        );
+        assert_eq!(
+            grey.clone().invert().invert().get_pixel(0, 0),
+            grey.get_pixel(0, 0)
+        );
+        assert!(grey.clone().gamma(2.2).get_pixel(0, 0).r > 100);
+        // One stop doubles the light in linear RGB
+        let exposed = single(Pixel::new(128, 0, 0, 255)).exposure(1.0);
+        assert_eq!(exposed.get_pixel(0, 0).r, 176);
+    }
+
+    #[test]
+    fn levels_remap_range() {
+        let levels = Levels::new().set_input(50, 150).set_output(10, 210);
+        assert_eq!(levels.apply(0), 10);
+        assert_eq!(levels.apply(100), 110);
+        assert_eq!(levels.apply(200), 210);
+        assert_eq!(Levels::default().set_gamma(2.0).apply(64), 128);
+        let canvas = single(Pixel::new(50, 100, 150, 255)).levels(&levels);
+        assert_eq!(canvas.get_pixel(0, 0), Pixel::new(10, 110, 210, 255));
+    }
+
+    #[test]
+    fn curve_passes_through_points() {
+        let curve = Curve::new(&[(1.0, 1.0), (0.0, 0.0), (0.25, 0.4), (0.75, 0.8)]);
+        assert_eq!(curve.points()[1], (0.25, 0.4));
+        for &(x, y) in curve.points() {
+            assert!((curve.evaluate(x) - y).abs() < 1e-6);
+        }
+        // Monotone: increasing points give an increasing table
+        let table = curve.to_table();
+        assert!(table.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(Curve::new(&[]).to_table()[77], 77);
+
+        let flat = Curve::new(&[(0.0, 0.5), (1.0, 0.5)]);
+        let red = Curve::new(&[(0.0, 1.0), (1.0, 0.0)]);
+        let canvas = single(Colors::BLACK).curves_rgb(&red, &flat, &flat);
+        assert_eq!(canvas.get_pixel(0, 0), Pixel::new(255, 128, 128, 255));
+    }
+
+    #[test]
+    fn color_adjustments() {
+        let red = single(Colors::RED);
+        let grey = red.clone().saturation(0.0).get_pixel(0, 0);
+        assert!(grey.r == grey.g && grey.g == grey.b);
+        // Colors that stay inside sRGB survive a round trip
+        let muted = single(Pixel::new(140, 120, 110, 255));
+        let back = muted
+            .clone()
+            .hue_rotate(120.0)
+            .hue_rotate(-120.0)
+            .get_pixel(0, 0);
+        assert!(back.distance(&muted.get_pixel(0, 0)) < 2.0);
+        let rotated = red.clone().hue_rotate(180.0).get_pixel(0, 0);
+        assert!(rotated.r < rotated.g && rotated.r < rotated.b);
+
+        // Muted colors gain more chroma than saturated ones
+        let boosted = muted.clone().vibrance(1.0).get_pixel(0, 0);
+        assert!(boosted.r - boosted.b > 30);
+        assert!(red.vibrance(1.0).get_pixel(0, 0).distance(&Colors::RED) < 4.0);
+
+        let warm = single(Pixel::new(128, 128, 128, 255)).temperature_tint(0.5, 0.0);
+        let p = warm.get_pixel(0, 0);
+        assert!(p.r > p.b);
+        let green = single(Pixel::new(128, 128, 128, 255)).temperature_tint(0.0, -0.5);
+        let p = green.get_pixel(0, 0);
+        assert!(p.g > p.r && p.g > p.b);
+    }
+}
@@ -10,7 +10,6 @@ use std::path::Path;
 // Sub-images
 // Flip
 // Resize?
-// Inverse colors
 
 fn main() {
     let canvas = Canvas::load(&Path::new("testing.png")).unwrap();
@@ -0,0 +1,560 @@
+use crate::canvas::Canvas;
+use crate::pixels::Pixel;
+
+/// Photoshop style blend modes as defined by the W3C compositing specification. The blended
+/// color is composited onto the destination with source-over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+impl BlendMode {
+    /// All blend modes in the order they are declared
+    pub const ALL: [BlendMode; 16] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::ColorDodge,
+        BlendMode::ColorBurn,
+        BlendMode::HardLight,
+        BlendMode::SoftLight,
+        BlendMode::Difference,
+        BlendMode::Exclusion,
+        BlendMode::Hue,
+        BlendMode::Saturation,
+        BlendMode::Color,
+        BlendMode::Luminosity,
+    ];
+
+    /// Returns the name of the blend mode in lower case with dashes, e.g. `"color-dodge"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Darken => "darken",
+            BlendMode::Lighten => "lighten",
+            BlendMode::ColorDodge => "color-dodge",
+            BlendMode::ColorBurn => "color-burn",
+            BlendMode::HardLight => "hard-light",
+            BlendMode::SoftLight => "soft-light",
+            BlendMode::Difference => "difference",
+            BlendMode::Exclusion => "exclusion",
+            BlendMode::Hue => "hue",
+            BlendMode::Saturation => "saturation",
+            BlendMode::Color => "color",
+            BlendMode::Luminosity => "luminosity",
+        }
+    }
+
+    /// Finds the blend mode with name `name` as returned by `BlendMode::name`
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        BlendMode::ALL
+            .iter()
+            .find(|mode| mode.name() == name)
+            .copied()
+    }
+
+    /// Blends backdrop color `backdrop` with source color `source`. All channels are normalized
+    /// and alpha is not taken into account.
+    pub fn blend(&self, backdrop: (f32, f32, f32), source: (f32, f32, f32)) -> (f32, f32, f32) {
+        let separable = |f: fn(f32, f32) -> f32| {
+            (
+                f(backdrop.0, source.0),
+                f(backdrop.1, source.1),
+                f(backdrop.2, source.2),
+            )
+        };
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => separable(|b, s| b * s),
+            BlendMode::Screen => separable(screen),
+            BlendMode::Overlay => separable(|b, s| hard_light(s, b)),
+            BlendMode::Darken => separable(f32::min),
+            BlendMode::Lighten => separable(f32::max),
+            BlendMode::ColorDodge => separable(|b, s| {
+                if b == 0.0 {
+                    0.0
+                } else if s >= 1.0 {
+                    1.0
+                } else {
+                    (b / (1.0 - s)).min(1.0)
+                }
+            }),
+            BlendMode::ColorBurn => separable(|b, s| {
+                if b >= 1.0 {
+                    1.0
+                } else if s <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - ((1.0 - b) / s).min(1.0)
+                }
+            }),
+            BlendMode::HardLight => separable(hard_light),
+            BlendMode::SoftLight => separable(|b, s| {
+                if s <= 0.5 {
+                    b - (1.0 - 2.0 * s) * b * (1.0 - b)
+                } else {
+                    let d = if b <= 0.25 {
+                        ((16.0 * b - 12.0) * b + 4.0) * b
+                    } else {
+                        b.sqrt()
+                    };
+                    b + (2.0 * s - 1.0) * (d - b)
+                }
+            }),
+            BlendMode::Difference => separable(|b, s| (b - s).abs()),
+            BlendMode::Exclusion => separable(|b, s| b + s - 2.0 * b * s),
+            BlendMode::Hue => set_lum(set_sat(source, sat(backdrop)), lum(backdrop)),
+            BlendMode::Saturation => set_lum(set_sat(backdrop, sat(source)), lum(backdrop)),
+            BlendMode::Color => set_lum(source, lum(backdrop)),
+            BlendMode::Luminosity => set_lum(backdrop, lum(source)),
+        }
+    }
+}
+
+fn screen(b: f32, s: f32) -> f32 {
+    b + s - b * s
+}
+
+fn hard_light(b: f32, s: f32) -> f32 {
+    if s <= 0.5 {
+        b * 2.0 * s
+    } else {
+        screen(b, 2.0 * s - 1.0)
+    }
+}
+
+fn lum(c: (f32, f32, f32)) -> f32 {
+    0.3 * c.0 + 0.59 * c.1 + 0.11 * c.2
+}
+
+fn clip_color(c: (f32, f32, f32)) -> (f32, f32, f32) {
+    let l = lum(c);
+    let n = c.0.min(c.1).min(c.2);
+    let x = c.0.max(c.1).max(c.2);
+    let mut channels = [c.0, c.1, c.2];
+    for channel in channels.iter_mut() {
+        if n < 0.0 && l - n != 0.0 {
+            *channel = l + (*channel - l) * l / (l - n);
+        }
+        if x > 1.0 && x - l != 0.0 {
+            *channel = l + (*channel - l) * (1.0 - l) / (x - l);
+        }
+    }
+    (channels[0], channels[1], channels[2])
+}
+
+fn set_lum(c: (f32, f32, f32), l: f32) -> (f32, f32, f32) {
+    let d = l - lum(c);
+    clip_color((c.0 + d, c.1 + d, c.2 + d))
+}
+
+fn sat(c: (f32, f32, f32)) -> f32 {
+    c.0.max(c.1).max(c.2) - c.0.min(c.1).min(c.2)
+}
+
+fn set_sat(c: (f32, f32, f32), s: f32) -> (f32, f32, f32) {
+    let mut channels = [c.0, c.1, c.2];
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| channels[a].total_cmp(&channels[b]));
+    let (min, mid, max) = (order[0], order[1], order[2]);
+    if channels[max] > channels[min] {
+        channels[mid] = (channels[mid] - channels[min]) * s / (channels[max] - channels[min]);
+        channels[max] = s;
+    } else {
+        channels[mid] = 0.0;
+        channels[max] = 0.0;
+    }
+    channels[min] = 0.0;
+    (channels[0], channels[1], channels[2])
+}
+
+/// Draws `source` onto `destination` using blend mode `mode`. The source alpha is scaled by
+/// `opacity` before compositing. Uses the W3C formula
+/// `co = cs * (1 - ab) + B(cb, cs) * ab` followed by Porter-Duff source-over.
+pub fn blend_pixels(destination: &Pixel, source: &Pixel, mode: BlendMode, opacity: f32) -> Pixel {
+    let (sr, sg, sb, sa) = source.normalize();
+    let (dr, dg, db, da) = destination.normalize();
+    let sa = sa * opacity.clamp(0.0, 1.0);
+    let alpha = sa + da * (1.0 - sa);
+    if alpha <= 0.0 {
+        return Pixel::new(0, 0, 0, 0);
+    }
+    let blended = mode.blend((dr, dg, db), (sr, sg, sb));
+    let composite = |s: f32, d: f32, b: f32| {
+        let s = (1.0 - da) * s + da * b.clamp(0.0, 1.0);
+        (sa * s + (1.0 - sa) * da * d) / alpha
+    };
+    Pixel::from(
+        (composite(sr, dr, blended.0) * 255.0).round(),
+        (composite(sg, dg, blended.1) * 255.0).round(),
+        (composite(sb, db, blended.2) * 255.0).round(),
+        (alpha * 255.0).round(),
+    )
+}
+
+/// Porter-Duff compositing operators. The source is drawn onto the destination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompositeOperator {
+    Source,
+    Destination,
+    SourceOver,
+    DestinationOver,
+    SourceIn,
+    DestinationIn,
+    SourceOut,
+    DestinationOut,
+    SourceAtop,
+    DestinationAtop,
+    Xor,
+}
+
+impl CompositeOperator {
+    /// Returns the factors `(fa, fb)` the premultiplied source and destination are multiplied by
+    /// for source alpha `sa` and destination alpha `da`.
+    pub fn factors(&self, sa: f32, da: f32) -> (f32, f32) {
+        match self {
+            CompositeOperator::Source => (1.0, 0.0),
+            CompositeOperator::Destination => (0.0, 1.0),
+            CompositeOperator::SourceOver => (1.0, 1.0 - sa),
+            CompositeOperator::DestinationOver => (1.0 - da, 1.0),
+            CompositeOperator::SourceIn => (da, 0.0),
+            CompositeOperator::DestinationIn => (0.0, sa),
+            CompositeOperator::SourceOut => (1.0 - da, 0.0),
+            CompositeOperator::DestinationOut => (0.0, 1.0 - sa),
+            CompositeOperator::SourceAtop => (da, 1.0 - sa),
+            CompositeOperator::DestinationAtop => (1.0 - da, sa),
+            CompositeOperator::Xor => (1.0 - da, 1.0 - sa),
+        }
+    }
+}
+
+/// Composites `source` onto `destination` with Porter-Duff operator `operator`. Both pixels
+/// are straight (not premultiplied) alpha.
+pub fn composite_pixels(destination: &Pixel, source: &Pixel, operator: CompositeOperator) -> Pixel {
+    let (sr, sg, sb, sa) = source.normalize();
+    let (dr, dg, db, da) = destination.normalize();
+    let (fa, fb) = operator.factors(sa, da);
+    let alpha = sa * fa + da * fb;
+    if alpha <= 0.0 {
+        return Pixel::new(0, 0, 0, 0);
+    }
+    let channel = |s: f32, d: f32| ((s * sa * fa + d * da * fb) / alpha * 255.0).round();
+    Pixel::from(
+        channel(sr, dr),
+        channel(sg, dg),
+        channel(sb, db),
+        (alpha * 255.0).round(),
+    )
+}
+
+impl Canvas {
+    /// Draws canvas `canvas` as a subimage at `(x, y)` using blend mode `mode`. The alpha of
+    /// `canvas` is multiplied by `opacity`.
+    pub fn draw_subimage_with_mut(
+        &mut self,
+        x: u32,
+        y: u32,
+        canvas: &Canvas,
+        mode: BlendMode,
+        opacity: f32,
+    ) {
+        let dimensions = self.dimensions();
+        let source_dimensions = canvas.dimensions();
+        let width = source_dimensions
+            .width
+            .min(dimensions.width.saturating_sub(x));
+        let height = source_dimensions
+            .height
+            .min(dimensions.height.saturating_sub(y));
+        for j in 0..height {
+            for i in 0..width {
+                let destination = self.get_pixel(x + i, y + j);
+                let source = canvas.get_pixel(i, j);
+                let new_color = blend_pixels(&destination, &source, mode, opacity);
+                self.set_pixel_mut(x + i, y + j, &new_color);
+            }
+        }
+    }
+
+    /// Draws canvas `canvas` as a subimage at `(x, y)` using blend mode `mode`. The alpha of
+    /// `canvas` is multiplied by `opacity`.
+    pub fn draw_subimage_with(
+        mut self,
+        x: u32,
+        y: u32,
+        canvas: &Canvas,
+        mode: BlendMode,
+        opacity: f32,
+    ) -> Canvas {
+        self.draw_subimage_with_mut(x, y, canvas, mode, opacity);
+        self
+    }
+
+    /// Composites canvas `canvas` as a subimage at `(x, y)` using Porter-Duff operator
+    /// `operator`. Only the area covered by `canvas` is affected.
+    pub fn composite_subimage_mut(
+        &mut self,
+        x: u32,
+        y: u32,
+        canvas: &Canvas,
+        operator: CompositeOperator,
+    ) {
+        let dimensions = self.dimensions();
+        let source_dimensions = canvas.dimensions();
+        let width = source_dimensions
+            .width
+            .min(dimensions.width.saturating_sub(x));
+        let height = source_dimensions
+            .height
+            .min(dimensions.height.saturating_sub(y));
+        for j in 0..height {
+            for i in 0..width {
+                let destination = self.get_pixel(x + i, y + j);
+                let source = canvas.get_pixel(i, j);
+                let new_color = composite_pixels(&destination, &source, operator);
+                self.set_pixel_mut(x + i, y + j, &new_color);
+            }
+        }
+    }
+
+    /// Composites canvas `canvas` as a subimage at `(x, y)` using Porter-Duff operator
+    /// `operator`. Only the area covered by `canvas` is affected.
+    pub fn composite_subimage(
+        mut self,
+        x: u32,
+        y: u32,
+        canvas: &Canvas,
+        operator: CompositeOperator,
+    ) -> Canvas {
+        self.composite_subimage_mut(x, y, canvas, operator);
+        self
+    }
+
+    /// Multiplies the color channels of every pixel with its alpha.
+    pub fn premultiply_mut(&mut self) {
+        let dimensions = self.dimensions();
+        for y in 0..dimensions.height {
+            for x in 0..dimensions.width {
+                let p = self.get_pixel(x, y);
+                let alpha = p.a as f32 / 255.0;
+                let premultiplied = Pixel::from(
+                    (p.r as f32 * alpha).round(),
+                    (p.g as f32 * alpha).round(),
+                    (p.b as f32 * alpha).round(),
+                    p.a as f32,
+                );
+                self.set_pixel_mut(x, y, &premultiplied);
+            }
+        }
+    }
+
+    /// Multiplies the color channels of every pixel with its alpha.
+    pub fn premultiply(mut self) -> Canvas {
+        self.premultiply_mut();
+        self
+    }
+
+    /// Divides the color channels of every pixel by its alpha. This is the inverse of
+    /// `premultiply` up to rounding. Fully transparent pixels become `(0, 0, 0, 0)`.
+    pub fn unpremultiply_mut(&mut self) {
+        let dimensions = self.dimensions();
+        for y in 0..dimensions.height {
+            for x in 0..dimensions.width {
+                let p = self.get_pixel(x, y);
+                let straight = if p.a == 0 {
+                    Pixel::new(0, 0, 0, 0)
+                } else {
+                    let alpha = p.a as f32 / 255.0;
+                    Pixel::from(
+                        (p.r as f32 / alpha).round(),
+                        (p.g as f32 / alpha).round(),
+                        (p.b as f32 / alpha).round(),
+                        p.a as f32,
+                    )
+                };
+                self.set_pixel_mut(x, y, &straight);
+            }
+        }
+    }
+
+    /// Divides the color channels of every pixel by its alpha. This is the inverse of
+    /// `premultiply` up to rounding. Fully transparent pixels become `(0, 0, 0, 0)`.
+    pub fn unpremultiply(mut self) -> Canvas {
+        self.unpremultiply_mut();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+    use crate::utility::overlap_colors;
+
+    #[test]
+    fn normal_over_opaque_matches_source() {
+        let source = Pixel::new(10, 20, 30, 255);
+        for mode in [BlendMode::Normal, BlendMode::Darken, BlendMode::Multiply] {
+            let result = blend_pixels(&Colors::WHITE, &source, mode, 1.0);
+            assert_eq!(result, source);
+        }
+    }
+
+    #[test]
+    fn transparent_destination_shows_source() {
+        // Without a backdrop the blend mode has no effect
+        let source = Pixel::new(200, 100, 50, 128);
+        for mode in BlendMode::ALL {
+            let result = blend_pixels(&Pixel::new(0, 0, 0, 0), &source, mode, 1.0);
+            assert_eq!(result, source);
+        }
+    }
+
+    #[test]
+    fn semi_transparent_layers_keep_alpha() {
+        let half_red = Pixel::new(255, 0, 0, 128);
+        let result = blend_pixels(&half_red, &half_red, BlendMode::Normal, 1.0);
+        // 0.5 + 0.5 * (1 - 0.5) = 0.75
+        assert_eq!(result, Pixel::new(255, 0, 0, 192));
+    }
+
+    #[test]
+    fn separable_modes() {
+        let grey = Pixel::new(128, 128, 128, 255);
+        assert_eq!(
+            blend_pixels(&grey, &grey, BlendMode::Screen, 1.0),
+            Pixel::new(192, 192, 192, 255)
+        );
+        assert_eq!(
+            blend_pixels(&Colors::WHITE, &Colors::RED, BlendMode::Difference, 1.0),
+            Pixel::new(0, 255, 255, 255)
+        );
+        assert_eq!(
+            blend_pixels(&Colors::BLUE, &Colors::WHITE, BlendMode::Multiply, 0.0),
+            Colors::BLUE
+        );
+    }
+
+    #[test]
+    fn luminosity_keeps_hue() {
+        let result = blend_pixels(&Colors::RED, &Colors::WHITE, BlendMode::Luminosity, 1.0);
+        assert_eq!(result, Colors::WHITE);
+        let grey = Pixel::new(128, 128, 128, 255);
+        let result = blend_pixels(&grey, &Colors::RED, BlendMode::Color, 1.0);
+        assert!(result.r > result.g && result.g == result.b);
+    }
+
+    #[test]
+    fn draw_subimage_with_clips_to_canvas() {
+        let source = Canvas::new_with_background(4, 4, Colors::BLACK);
+        let canvas = Canvas::new(5, 5).draw_subimage_with(3, 3, &source, BlendMode::Multiply, 0.5);
+        assert_eq!(canvas.count_pixels(&Pixel::new(128, 128, 128, 255)), 4);
+        let canvas = Canvas::new(5, 5).draw_subimage_with(
+            0,
+            0,
+            &Canvas::new_with_background(1, 1, Colors::BLACK),
+            BlendMode::Normal,
+            1.0,
+        );
+        assert_eq!(canvas.get_pixel(0, 0), Colors::BLACK);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for mode in BlendMode::ALL {
+            assert_eq!(BlendMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(BlendMode::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn source_over_matches_overlap_colors() {
+        let colors = [
+            Pixel::new(255, 0, 0, 128),
+            Pixel::new(0, 40, 200, 77),
+            Pixel::new(10, 10, 10, 0),
+            Colors::WHITE,
+        ];
+        for destination in colors.iter() {
+            for source in colors.iter() {
+                let expected = overlap_colors(destination, source);
+                let normal = blend_pixels(destination, source, BlendMode::Normal, 1.0);
+                let over = composite_pixels(destination, source, CompositeOperator::SourceOver);
+                assert_eq!(over, expected);
+                assert_eq!(normal, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn porter_duff_operators() {
+        let source = Pixel::new(255, 0, 0, 255);
+        let destination = Pixel::new(0, 0, 255, 128);
+        let transparent = Pixel::new(0, 0, 0, 0);
+        let op = |operator| composite_pixels(&destination, &source, operator);
+        assert_eq!(op(CompositeOperator::Source), source);
+        assert_eq!(op(CompositeOperator::Destination), destination);
+        assert_eq!(op(CompositeOperator::SourceIn), Pixel::new(255, 0, 0, 128));
+        assert_eq!(op(CompositeOperator::SourceOut), Pixel::new(255, 0, 0, 127));
+        assert_eq!(op(CompositeOperator::DestinationOut), transparent);
+        assert_eq!(
+            op(CompositeOperator::SourceAtop),
+            Pixel::new(255, 0, 0, 128)
+        );
+        assert_eq!(
+            op(CompositeOperator::DestinationAtop),
+            Pixel::new(127, 0, 128, 255)
+        );
+        assert_eq!(op(CompositeOperator::Xor), Pixel::new(255, 0, 0, 127));
+        assert_eq!(
+            composite_pixels(&transparent, &source, CompositeOperator::DestinationOver),
+            source
+        );
+    }
+
+    #[test]
+    fn stacking_semi_transparent_layers() {
+        // Two 50% layers over nothing should give 75% coverage
+        let layer = Canvas::new_with_background(2, 2, Pixel::new(0, 0, 255, 128));
+        let canvas = Canvas::new_with_background(2, 2, Pixel::new(0, 0, 0, 0))
+            .draw_subimage(0, 0, &layer)
+            .draw_subimage(0, 0, &layer);
+        assert_eq!(canvas.get_pixel(1, 1), Pixel::new(0, 0, 255, 192));
+    }
+
+    #[test]
+    fn premultiply_round_trip() {
+        let canvas = Canvas::new_with_background(2, 2, Pixel::new(200, 100, 50, 128));
+        let premultiplied = canvas.clone().premultiply();
+        assert_eq!(premultiplied.get_pixel(0, 0), Pixel::new(100, 50, 25, 128));
+        // Premultiplying loses precision, so the round trip is only close
+        let restored = premultiplied.unpremultiply();
+        assert!(restored.get_pixel(1, 1).distance(&canvas.get_pixel(1, 1)) <= 1.0);
+        let transparent = Canvas::new_with_background(2, 2, Pixel::new(1, 2, 3, 0));
+        assert_eq!(
+            transparent.unpremultiply().get_pixel(0, 0),
+            Pixel::new(0, 0, 0, 0)
+        );
+    }
+}
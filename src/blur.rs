@@ -0,0 +1,442 @@
+use crate::canvas::Canvas;
+use crate::denoise::Planes;
+
+/// Sigma from which `gaussian_blur` switches from a sampled kernel to three box blurs.
+const BOX_APPROXIMATION_SIGMA: f32 = 4.0;
+
+/// How pixels outside of the canvas are sampled when blurring near the border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// Repeats the nearest border pixel
+    Clamp,
+    /// Mirrors the canvas at its border
+    Mirror,
+    /// Continues at the opposite side, for tileable images
+    Wrap,
+    /// Treats everything outside as transparent, so the border fades out
+    Transparent,
+}
+
+/// Options shared by the blur filters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlurOptions {
+    /// How the border is handled. Defaults to `EdgeMode::Clamp`.
+    pub edge_mode: EdgeMode,
+    /// Averages colors in linear light instead of sRGB, which keeps bright details from
+    /// darkening. Defaults to `false`.
+    pub linear_light: bool,
+}
+
+impl Default for BlurOptions {
+    fn default() -> Self {
+        BlurOptions::new()
+    }
+}
+
+impl BlurOptions {
+    pub fn new() -> BlurOptions {
+        BlurOptions {
+            edge_mode: EdgeMode::Clamp,
+            linear_light: false,
+        }
+    }
+
+    pub fn set_edge_mode(mut self, edge_mode: EdgeMode) -> BlurOptions {
+        self.edge_mode = edge_mode;
+        self
+    }
+
+    pub fn set_linear_light(mut self, linear_light: bool) -> BlurOptions {
+        self.linear_light = linear_light;
+        self
+    }
+}
+
+impl EdgeMode {
+    /// Index inside a line of `length` values that `index` maps to, or `None` if it is
+    /// transparent.
+    fn index(&self, index: i64, length: usize) -> Option<usize> {
+        let n = length as i64;
+        if (0..n).contains(&index) {
+            return Some(index as usize);
+        }
+        match self {
+            EdgeMode::Clamp => Some(index.clamp(0, n - 1) as usize),
+            EdgeMode::Wrap => Some(index.rem_euclid(n) as usize),
+            EdgeMode::Mirror => {
+                let m = index.rem_euclid(2 * n);
+                Some(if m < n { m } else { 2 * n - 1 - m } as usize)
+            }
+            EdgeMode::Transparent => None,
+        }
+    }
+
+    fn sample(&self, line: &[f32], index: i64) -> f32 {
+        self.index(index, line.len())
+            .map(|i| line[i])
+            .unwrap_or(0.0)
+    }
+}
+
+/// Applies `blur` to every row and then to every column of `values`.
+fn separable<F>(values: &mut [f32], width: usize, height: usize, blur: F)
+where
+    F: Fn(&[f32]) -> Vec<f32>,
+{
+    for row in values.chunks_mut(width) {
+        let blurred = blur(row);
+        row.copy_from_slice(&blurred);
+    }
+    let mut column = vec![0f32; height];
+    for x in 0..width {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = values[y * width + x];
+        }
+        for (y, value) in blur(&column).into_iter().enumerate() {
+            values[y * width + x] = value;
+        }
+    }
+}
+
+/// Mean of every `(2 * radius + 1)` window of `line` using a running sum, so the cost does not
+/// depend on `radius`.
+fn box_line(line: &[f32], radius: usize, edge_mode: EdgeMode) -> Vec<f32> {
+    let r = radius as i64;
+    let size = (2 * radius + 1) as f64;
+    let mut sum: f64 = (-r..=r).map(|i| edge_mode.sample(line, i) as f64).sum();
+    let mut result = Vec::with_capacity(line.len());
+    for x in 0..line.len() as i64 {
+        result.push((sum / size) as f32);
+        sum += edge_mode.sample(line, x + r + 1) as f64 - edge_mode.sample(line, x - r) as f64;
+    }
+    result
+}
+
+/// Convolution of `line` with a normalized gaussian kernel cut off at three sigma.
+fn gaussian_line(line: &[f32], kernel: &[f32], edge_mode: EdgeMode) -> Vec<f32> {
+    let radius = (kernel.len() / 2) as i64;
+    (0..line.len() as i64)
+        .map(|x| {
+            kernel
+                .iter()
+                .enumerate()
+                .map(|(i, k)| k * edge_mode.sample(line, x + i as i64 - radius))
+                .sum()
+        })
+        .collect()
+}
+
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil() as i64;
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter().map(|k| k / sum).collect()
+}
+
+/// Radii of three successive box blurs that together approximate a gaussian with `sigma`.
+fn box_radii(sigma: f32) -> [usize; 3] {
+    let n = 3.0;
+    let ideal = (12.0 * sigma * sigma / n + 1.0).sqrt();
+    let mut lower = ideal.floor() as i64;
+    if lower % 2 == 0 {
+        lower -= 1;
+    }
+    let l = lower as f32;
+    let lower_count =
+        ((12.0 * sigma * sigma - n * l * l - 4.0 * n * l - 3.0 * n) / (-4.0 * l - 4.0)).round();
+    let mut radii = [0; 3];
+    for (i, radius) in radii.iter_mut().enumerate() {
+        let size = if (i as f32) < lower_count {
+            lower
+        } else {
+            lower + 2
+        };
+        *radius = ((size - 1) / 2) as usize;
+    }
+    radii
+}
+
+/// Gaussian blur of a single channel. Small sigmas use a sampled kernel, larger ones three box
+/// blurs whose cost does not grow with `sigma`.
+pub(crate) fn gaussian_values(
+    values: &[f32],
+    width: usize,
+    height: usize,
+    sigma: f32,
+    edge_mode: EdgeMode,
+) -> Vec<f32> {
+    let mut result = values.to_vec();
+    if sigma <= 0.0 || width == 0 || height == 0 {
+        return result;
+    }
+    if sigma < BOX_APPROXIMATION_SIGMA {
+        let kernel = gaussian_kernel(sigma);
+        separable(&mut result, width, height, |line| {
+            gaussian_line(line, &kernel, edge_mode)
+        });
+    } else {
+        for radius in box_radii(sigma) {
+            separable(&mut result, width, height, |line| {
+                box_line(line, radius, edge_mode)
+            });
+        }
+    }
+    result
+}
+
+/// Bilinear sample of a single channel at `(x, y)`.
+fn bilinear(values: &[f32], width: usize, height: usize, x: f32, y: f32, edge: EdgeMode) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let at = |x: i64, y: i64| match (edge.index(x, width), edge.index(y, height)) {
+        (Some(x), Some(y)) => values[y * width + x],
+        _ => 0.0,
+    };
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1, y0) * fx;
+    let bottom = at(x0, y0 + 1) * (1.0 - fx) + at(x0 + 1, y0 + 1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+impl Canvas {
+    /// Applies `blur` to every channel of the premultiplied, optionally linear canvas.
+    fn blur_channels<F>(&self, options: &BlurOptions, blur: F) -> Canvas
+    where
+        F: Fn(&[f32], usize, usize) -> Vec<f32>,
+    {
+        let mut planes = Planes::from_canvas_with(self, options.linear_light);
+        let (width, height) = (planes.width, planes.height);
+        for channel in planes.channels.iter_mut() {
+            *channel = blur(channel, width, height);
+        }
+        planes.to_canvas()
+    }
+
+    /// Gaussian blur with standard deviation `sigma` in pixels. See `gaussian_blur_with`.
+    pub fn gaussian_blur(mut self, sigma: f32) -> Canvas {
+        self.gaussian_blur_mut(sigma);
+        self
+    }
+
+    /// Gaussian blur with standard deviation `sigma` in pixels. See `gaussian_blur_with`.
+    pub fn gaussian_blur_mut(&mut self, sigma: f32) {
+        self.gaussian_blur_with_mut(sigma, &BlurOptions::new());
+    }
+
+    /// Gaussian blur with standard deviation `sigma` in pixels. The blur is separable, and
+    /// sigmas of four and more are approximated by three box blurs so they stay fast.
+    pub fn gaussian_blur_with(mut self, sigma: f32, options: &BlurOptions) -> Canvas {
+        self.gaussian_blur_with_mut(sigma, options);
+        self
+    }
+
+    /// Gaussian blur with standard deviation `sigma` in pixels. See `gaussian_blur_with`.
+    pub fn gaussian_blur_with_mut(&mut self, sigma: f32, options: &BlurOptions) {
+        if sigma > 0.0 {
+            *self = self.blur_channels(options, |values, width, height| {
+                gaussian_values(values, width, height, sigma, options.edge_mode)
+            });
+        }
+    }
+
+    /// Replaces every pixel by the mean of the `(2 * radius + 1)` square around it.
+    pub fn box_blur(mut self, radius: u32) -> Canvas {
+        self.box_blur_mut(radius);
+        self
+    }
+
+    /// Replaces every pixel by the mean of the `(2 * radius + 1)` square around it.
+    pub fn box_blur_mut(&mut self, radius: u32) {
+        self.box_blur_with_mut(radius, &BlurOptions::new());
+    }
+
+    /// Box blur using running sums, so large radii are as fast as small ones.
+    pub fn box_blur_with(mut self, radius: u32, options: &BlurOptions) -> Canvas {
+        self.box_blur_with_mut(radius, options);
+        self
+    }
+
+    /// Box blur using running sums. See `box_blur_with`.
+    pub fn box_blur_with_mut(&mut self, radius: u32, options: &BlurOptions) {
+        if radius > 0 {
+            *self = self.blur_channels(options, |values, width, height| {
+                let mut result = values.to_vec();
+                separable(&mut result, width, height, |line| {
+                    box_line(line, radius as usize, options.edge_mode)
+                });
+                result
+            });
+        }
+    }
+
+    /// Smears the canvas over `length` pixels along `angle` degrees, as if it moved while
+    /// captured. An angle of `0` blurs horizontally and `90` vertically.
+    pub fn motion_blur(mut self, length: f32, angle: f32) -> Canvas {
+        self.motion_blur_mut(length, angle);
+        self
+    }
+
+    /// Smears the canvas over `length` pixels along `angle` degrees. See `motion_blur`.
+    pub fn motion_blur_mut(&mut self, length: f32, angle: f32) {
+        self.motion_blur_with_mut(length, angle, &BlurOptions::new());
+    }
+
+    /// Motion blur with custom border handling and blending. See `motion_blur`.
+    pub fn motion_blur_with(mut self, length: f32, angle: f32, options: &BlurOptions) -> Canvas {
+        self.motion_blur_with_mut(length, angle, options);
+        self
+    }
+
+    /// Motion blur with custom border handling and blending. See `motion_blur`.
+    pub fn motion_blur_with_mut(&mut self, length: f32, angle: f32, options: &BlurOptions) {
+        if length <= 0.0 {
+            return;
+        }
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let steps = length.ceil() as usize;
+        let offsets: Vec<(f32, f32)> = (0..=steps)
+            .map(|i| {
+                let t = (i as f32 / steps as f32 - 0.5) * length;
+                (t * cos, t * sin)
+            })
+            .collect();
+        let edge_mode = options.edge_mode;
+        *self = self.blur_channels(options, |values, width, height| {
+            let mut result = Vec::with_capacity(values.len());
+            for y in 0..height {
+                for x in 0..width {
+                    let sum: f32 = offsets
+                        .iter()
+                        .map(|(dx, dy)| {
+                            let (sx, sy) = (x as f32 + dx, y as f32 + dy);
+                            bilinear(values, width, height, sx, sy, edge_mode)
+                        })
+                        .sum();
+                    result.push(sum / offsets.len() as f32);
+                }
+            }
+            result
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors, Pixel};
+
+    fn brute_force_box(values: &[f32], radius: i64, x: i64, edge_mode: EdgeMode) -> f32 {
+        let sum: f32 = (x - radius..=x + radius)
+            .map(|i| edge_mode.sample(values, i))
+            .sum();
+        sum / (2 * radius + 1) as f32
+    }
+
+    #[test]
+    fn edge_modes_map_indices() {
+        let indices =
+            |mode: EdgeMode| -> Vec<Option<usize>> { (-3..7).map(|i| mode.index(i, 4)).collect() };
+        let some = |v: &[usize]| v.iter().map(|&i| Some(i)).collect::<Vec<_>>();
+        assert_eq!(
+            indices(EdgeMode::Clamp),
+            some(&[0, 0, 0, 0, 1, 2, 3, 3, 3, 3])
+        );
+        assert_eq!(
+            indices(EdgeMode::Wrap),
+            some(&[1, 2, 3, 0, 1, 2, 3, 0, 1, 2])
+        );
+        assert_eq!(
+            indices(EdgeMode::Mirror),
+            some(&[2, 1, 0, 0, 1, 2, 3, 3, 2, 1])
+        );
+        assert_eq!(indices(EdgeMode::Transparent)[2], None);
+        assert_eq!(indices(EdgeMode::Transparent)[3], Some(0));
+    }
+
+    #[test]
+    fn box_line_matches_brute_force() {
+        let values: Vec<f32> = (0..11).map(|i| ((i * 37) % 17) as f32).collect();
+        for mode in [
+            EdgeMode::Clamp,
+            EdgeMode::Mirror,
+            EdgeMode::Wrap,
+            EdgeMode::Transparent,
+        ] {
+            for radius in [1, 3, 12] {
+                let fast = box_line(&values, radius, mode);
+                for (x, value) in fast.iter().enumerate() {
+                    let expected = brute_force_box(&values, radius as i64, x as i64, mode);
+                    assert!((value - expected).abs() < 1e-4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn box_approximation_is_close_to_gaussian() {
+        // A single bright pixel spreads out the same with both implementations
+        let mut values = vec![0f32; 61 * 61];
+        values[30 * 61 + 30] = 1000.0;
+        let sigma = 5.0;
+        let exact = {
+            let kernel = gaussian_kernel(sigma);
+            let mut result = values.clone();
+            separable(&mut result, 61, 61, |line| {
+                gaussian_line(line, &kernel, EdgeMode::Clamp)
+            });
+            result
+        };
+        let approximated = gaussian_values(&values, 61, 61, sigma, EdgeMode::Clamp);
+        let largest = exact
+            .iter()
+            .zip(&approximated)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0f32, f32::max);
+        assert!(largest < exact[30 * 61 + 30] * 0.1);
+        let total: f32 = approximated.iter().sum();
+        assert!((total - 1000.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn blurs_keep_flat_canvases_flat() {
+        let canvas = Canvas::new_with_background(9, 7, Pixel::new(10, 120, 230, 255));
+        let options = BlurOptions::new().set_linear_light(true);
+        assert_eq!(canvas.clone().gaussian_blur(1.5), canvas);
+        assert_eq!(canvas.clone().gaussian_blur_with(6.0, &options), canvas);
+        assert_eq!(canvas.clone().box_blur(3), canvas);
+        assert_eq!(canvas.clone().motion_blur(5.0, 30.0), canvas);
+    }
+
+    #[test]
+    fn transparent_edges_fade_out() {
+        let canvas = Canvas::new_with_background(9, 9, Colors::RED);
+        let options = BlurOptions::new().set_edge_mode(EdgeMode::Transparent);
+        let blurred = canvas.box_blur_with(1, &options);
+        assert_eq!(blurred.get_pixel(0, 0), Pixel::new(255, 0, 0, 113));
+        assert_eq!(blurred.get_pixel(0, 4), Pixel::new(255, 0, 0, 170));
+        assert_eq!(blurred.get_pixel(4, 4), Colors::RED);
+    }
+
+    #[test]
+    fn motion_blur_follows_the_angle() {
+        let canvas =
+            Canvas::new_with_background(15, 15, Colors::BLACK).set_pixel(7, 7, &Colors::WHITE);
+        let horizontal = canvas.clone().motion_blur(6.0, 0.0);
+        let vertical = canvas.motion_blur(6.0, 90.0);
+        assert!(horizontal.get_pixel(10, 7).r > 0);
+        assert_eq!(horizontal.get_pixel(7, 10).r, 0);
+        assert!(vertical.get_pixel(7, 10).r > 0);
+        assert_eq!(vertical.get_pixel(10, 7).r, 0);
+    }
+
+    #[test]
+    fn linear_light_keeps_highlights_brighter() {
+        let canvas = Canvas::new(2, 1).set_pixel(1, 0, &Colors::BLACK);
+        let srgb = canvas.clone().box_blur(1).get_pixel(0, 0);
+        let options = BlurOptions::new().set_linear_light(true);
+        let linear = canvas.box_blur_with(1, &options).get_pixel(0, 0);
+        assert!(linear.r > srgb.r);
+    }
+}
@@ -1,6 +1,8 @@
 use crate::pixels::{ColorTrait, Colors, Pixel};
 use crate::utility::{clamp, overlap_colors, to_grey_lumiosity};
 use image::{GenericImageView, ImageFormat, RgbaImage};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::cmp::{max, min};
 use std::fmt;
 use std::path::Path;
@@ -42,7 +44,7 @@ pub struct Size {
     pub height: u32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Rect {
     pub start: Point,
     pub size: Size,
@@ -195,6 +197,23 @@ impl Canvas {
         }
     }
 
+    /// Creates a new `Canvas` of size `width` and `height` filled with random opaque colors
+    /// drawn from `rng`.
+    pub fn new_random_with<R: Rng + ?Sized>(width: u32, height: u32, rng: &mut R) -> Canvas {
+        let pixels = (0..width * height).map(|_| Pixel::random_with(rng)).collect();
+        Canvas {
+            pixels,
+            height,
+            width,
+        }
+    }
+
+    /// Creates a new `Canvas` of size `width` and `height` filled with random opaque colors.
+    /// The same `seed` always gives the same canvas.
+    pub fn new_random(width: u32, height: u32, seed: u64) -> Canvas {
+        Canvas::new_random_with(width, height, &mut StdRng::seed_from_u64(seed))
+    }
+
     /// Saves the canvas as an image at the path given by `filename`
     pub fn save(&self, filename: &Path) -> Result<(), ImageError> {
         println!("Saving to: {}", filename.display());
@@ -372,7 +391,7 @@ impl Canvas {
             height: dimensions.height - 1,
         };
         for x in 0..dimensions.width / 2 {
-            for y in 0..dimensions.height / 2 {
+            for y in 0..dimensions.height.div_ceil(2) {
                 let cloned = self.get_pixel(x, y);
                 self.set_pixel_mut(x, y, &self.get_pixel(y, innerdim.height - x));
                 self.set_pixel_mut(
@@ -427,7 +446,7 @@ impl Canvas {
             height: dimensions.height - 1,
         };
         for x in 0..dimensions.width / 2 {
-            for y in 0..dimensions.height / 2 {
+            for y in 0..dimensions.height.div_ceil(2) {
                 let cloned = self.get_pixel(x, y);
                 self.set_pixel_mut(x, y, &self.get_pixel(y, innerdim.height - x));
                 self.set_pixel_mut(
@@ -997,9 +1016,23 @@ mod tests {
         assert_eq!(correct_color, true);
     }
 
+    #[test]
+    fn random_canvas_is_seeded() {
+        let canvas = Canvas::new_random(8, 8, 42);
+        assert_eq!(canvas, Canvas::new_random(8, 8, 42));
+        assert_ne!(canvas, Canvas::new_random(8, 8, 43));
+        assert!(canvas.pixels().all(|p| p.a == 255));
+        assert!(count_colors(&canvas).len() > 1);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        assert_eq!(Canvas::new_random_with(8, 8, &mut rng), canvas);
+        let first = Pixel::random_with(&mut StdRng::seed_from_u64(7));
+        assert_eq!(first, Pixel::random_with(&mut StdRng::seed_from_u64(7)));
+    }
+
     #[test]
     fn clean_canvas_with_background() {
-        let color = Pixel::random();
+        let color = Pixel::random_with(&mut StdRng::seed_from_u64(3));
         let canvas = Canvas::new_with_background(20, 20, color.clone());
         let dimensions = canvas.dimensions();
         assert_eq!(
@@ -1014,4 +1047,20 @@ mod tests {
         assert_eq!(counts.keys().len(), 1);
         assert_eq!(counts.get(&color), Some(&400));
     }
+
+    #[test]
+    fn rotate90_turns_odd_sizes_clockwise() {
+        for (width, height) in [(5, 5), (4, 5), (5, 4), (7, 4)] {
+            let canvas = Canvas::new_random(width, height, 11);
+            let rotated = canvas.clone().rotate90();
+            let mut rotated_mut = canvas.clone();
+            rotated_mut.rotate90_mut();
+            assert_eq!(rotated, rotated_mut);
+            for y in 0..width {
+                for x in 0..height {
+                    assert_eq!(rotated.get_pixel(x, y), canvas.get_pixel(y, height - 1 - x));
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,135 @@
+use crate::pixels::Pixel;
+
+/// Converts a normalized sRGB channel value to linear light.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a normalized linear light channel value to sRGB.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Returns the color channels of `p` in linear light, normalized to `0.0..=1.0`.
+pub fn pixel_to_linear(p: &Pixel) -> (f32, f32, f32) {
+    let (r, g, b, _) = p.normalize();
+    (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+}
+
+/// Creates a pixel from normalized linear light channels and alpha `alpha`.
+pub fn linear_to_pixel(r: f32, g: f32, b: f32, alpha: u8) -> Pixel {
+    Pixel::from(
+        (linear_to_srgb(r) * 255.0).round(),
+        (linear_to_srgb(g) * 255.0).round(),
+        (linear_to_srgb(b) * 255.0).round(),
+        alpha as f32,
+    )
+}
+
+/// Converts normalized linear light channels to Oklab `(L, a, b)`.
+pub fn linear_to_oklab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let l = 0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b;
+    let m = 0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b;
+    let s = 0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b;
+    let (l, m, s) = (l.cbrt(), m.cbrt(), s.cbrt());
+    (
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    )
+}
+
+/// Converts Oklab `(L, a, b)` to normalized linear light channels.
+pub fn oklab_to_linear(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let l_ = l + 0.396_337_78 * a + 0.215_803_76 * b;
+    let m_ = l - 0.105_561_346 * a - 0.063_854_17 * b;
+    let s_ = l - 0.089_484_18 * a - 1.291_485_5 * b;
+    let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+    (
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+        -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    )
+}
+
+/// Returns the color of `p` in Oklab `(L, a, b)`. `L` is between `0.0` and `1.0`.
+pub fn pixel_to_oklab(p: &Pixel) -> (f32, f32, f32) {
+    let (r, g, b) = pixel_to_linear(p);
+    linear_to_oklab(r, g, b)
+}
+
+/// Creates a pixel from Oklab `(L, a, b)` and alpha `alpha`. Colors outside sRGB are clamped.
+pub fn oklab_to_pixel(l: f32, a: f32, b: f32, alpha: u8) -> Pixel {
+    let (r, g, b) = oklab_to_linear(l, a, b);
+    linear_to_pixel(r, g, b, alpha)
+}
+
+/// Converts normalized linear light channels to CIELAB `(L, a, b)` with a D65 white point.
+/// `L` is between `0.0` and `100.0`.
+pub fn linear_to_lab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.088_83;
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// Returns the color of `p` in CIELAB `(L, a, b)` with a D65 white point.
+pub fn pixel_to_lab(p: &Pixel) -> (f32, f32, f32) {
+    let (r, g, b) = pixel_to_linear(p);
+    linear_to_lab(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+
+    #[test]
+    fn linear_round_trip() {
+        for value in 0..=255u8 {
+            let p = Pixel::new(value, value / 2, 255 - value, 200);
+            let (r, g, b) = pixel_to_linear(&p);
+            assert_eq!(linear_to_pixel(r, g, b, p.a), p);
+        }
+    }
+
+    #[test]
+    fn oklab_round_trip() {
+        for p in [
+            Colors::WHITE,
+            Colors::BLACK,
+            Colors::RED,
+            Pixel::new(12, 200, 99, 255),
+        ] {
+            let (l, a, b) = pixel_to_oklab(&p);
+            assert_eq!(oklab_to_pixel(l, a, b, p.a), p);
+        }
+        let (l, a, b) = pixel_to_oklab(&Colors::WHITE);
+        assert!((l - 1.0).abs() < 1e-3 && a.abs() < 1e-3 && b.abs() < 1e-3);
+    }
+
+    #[test]
+    fn lab_of_known_colors() {
+        let (l, a, b) = pixel_to_lab(&Colors::WHITE);
+        assert!((l - 100.0).abs() < 1e-2 && a.abs() < 1e-2 && b.abs() < 1e-2);
+        let (l, a, b) = pixel_to_lab(&Colors::RED);
+        assert!((l - 53.24).abs() < 0.05 && (a - 80.09).abs() < 0.05 && (b - 67.2).abs() < 0.05);
+    }
+}
@@ -0,0 +1,547 @@
+use crate::canvas::Canvas;
+use crate::colorspace::{linear_to_srgb, srgb_to_linear};
+use crate::pixels::Pixel;
+
+/// The channels of a canvas as premultiplied floats between `0.0` and `255.0`, so transparent
+/// pixels do not bleed their color into their neighbours.
+#[derive(Clone)]
+pub(crate) struct Planes {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) channels: [Vec<f32>; 4],
+    linear: bool,
+}
+
+impl Planes {
+    pub(crate) fn from_canvas(canvas: &Canvas) -> Planes {
+        Planes::from_canvas_with(canvas, false)
+    }
+
+    /// Like `from_canvas`, but with the colors in linear light when `linear` is set.
+    pub(crate) fn from_canvas_with(canvas: &Canvas, linear: bool) -> Planes {
+        let dimensions = canvas.dimensions();
+        let count = (dimensions.width * dimensions.height) as usize;
+        let mut channels: [Vec<f32>; 4] = Default::default();
+        for channel in channels.iter_mut() {
+            channel.reserve(count);
+        }
+        let decode = |c: u8| {
+            if linear {
+                srgb_to_linear(c as f32 / 255.0) * 255.0
+            } else {
+                c as f32
+            }
+        };
+        for p in canvas.pixels() {
+            let alpha = p.a as f32 / 255.0;
+            channels[0].push(decode(p.r) * alpha);
+            channels[1].push(decode(p.g) * alpha);
+            channels[2].push(decode(p.b) * alpha);
+            channels[3].push(p.a as f32);
+        }
+        Planes {
+            width: dimensions.width as usize,
+            height: dimensions.height as usize,
+            channels,
+            linear,
+        }
+    }
+
+    /// Empty planes of the same size and color encoding.
+    pub(crate) fn like(&self) -> Planes {
+        self.with_size(self.width, self.height)
+    }
+
+    /// Empty planes of size `width` and `height` with the same color encoding.
+    pub(crate) fn with_size(&self, width: usize, height: usize) -> Planes {
+        let count = width * height;
+        Planes {
+            width,
+            height,
+            channels: [
+                vec![0.0; count],
+                vec![0.0; count],
+                vec![0.0; count],
+                vec![0.0; count],
+            ],
+            linear: self.linear,
+        }
+    }
+
+    pub(crate) fn to_canvas(&self) -> Canvas {
+        let pixels = (0..self.width * self.height)
+            .map(|i| {
+                let alpha = self.channels[3][i].clamp(0.0, 255.0);
+                if alpha.round() <= 0.0 {
+                    return Pixel::new(0, 0, 0, 0);
+                }
+                let straight = |c: f32| {
+                    let c = c / alpha;
+                    if self.linear {
+                        (linear_to_srgb(c) * 255.0).round()
+                    } else {
+                        (c * 255.0).round()
+                    }
+                };
+                Pixel::from(
+                    straight(self.channels[0][i]),
+                    straight(self.channels[1][i]),
+                    straight(self.channels[2][i]),
+                    alpha.round(),
+                )
+            })
+            .collect();
+        Canvas::new_with_data(self.width as u32, self.height as u32, pixels)
+    }
+
+    /// Squared distance of the premultiplied colors at indices `i` and `j`, alpha included.
+    fn distance_squared(&self, i: usize, j: usize) -> f32 {
+        self.channels
+            .iter()
+            .map(|c| (c[i] - c[j]) * (c[i] - c[j]))
+            .sum()
+    }
+}
+
+/// Summed-area table of `values` with one extra row and column of zeros.
+pub(crate) fn integral(values: &[f32], width: usize, height: usize) -> Vec<f64> {
+    let mut table = vec![0f64; (width + 1) * (height + 1)];
+    for y in 0..height {
+        let mut row = 0f64;
+        for x in 0..width {
+            row += values[y * width + x] as f64;
+            table[(y + 1) * (width + 1) + x + 1] = table[y * (width + 1) + x + 1] + row;
+        }
+    }
+    table
+}
+
+/// Sum of the `integral` table over the inclusive rectangle `(x0, y0)..=(x1, y1)`.
+pub(crate) fn area_sum(
+    table: &[f64],
+    width: usize,
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+) -> f64 {
+    let w = width + 1;
+    table[(y1 + 1) * w + x1 + 1] - table[y0 * w + x1 + 1] - table[(y1 + 1) * w + x0]
+        + table[y0 * w + x0]
+}
+
+/// Mean of `values` over a `(2 * radius + 1)` square window. Near the border only the pixels
+/// inside the canvas are averaged.
+fn box_mean(values: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let table = integral(values, width, height);
+    let mut result = Vec::with_capacity(values.len());
+    for y in 0..height {
+        let (y0, y1) = (y.saturating_sub(radius), (y + radius).min(height - 1));
+        for x in 0..width {
+            let (x0, x1) = (x.saturating_sub(radius), (x + radius).min(width - 1));
+            let count = ((x1 - x0 + 1) * (y1 - y0 + 1)) as f64;
+            result.push((area_sum(&table, width, x0, y0, x1, y1) / count) as f32);
+        }
+    }
+    result
+}
+
+/// Median of every `(2 * radius + 1)` square window of one channel with edges clamped, using
+/// the constant time algorithm of Perreault and Hébert. Every pixel costs the same regardless
+/// of `radius`.
+fn median_channel(values: &[u8], width: usize, height: usize, radius: usize) -> Vec<u8> {
+    let clamp_y = |y: i64| y.clamp(0, height as i64 - 1) as usize;
+    let clamp_x = |x: i64| x.clamp(0, width as i64 - 1) as usize;
+    let r = radius as i64;
+    let target = ((2 * radius + 1) * (2 * radius + 1)).div_ceil(2) as u32;
+
+    // One histogram per column covering the rows of the current window
+    let mut columns = vec![[0u32; 256]; width];
+    for (x, column) in columns.iter_mut().enumerate() {
+        for y in -r..=r {
+            column[values[clamp_y(y) * width + x] as usize] += 1;
+        }
+    }
+
+    let mut result = vec![0u8; values.len()];
+    for y in 0..height {
+        if y > 0 {
+            let (old, new) = (clamp_y(y as i64 - r - 1), clamp_y(y as i64 + r));
+            for (x, column) in columns.iter_mut().enumerate() {
+                column[values[old * width + x] as usize] -= 1;
+                column[values[new * width + x] as usize] += 1;
+            }
+        }
+
+        let mut kernel = [0u32; 256];
+        for x in -r..=r {
+            let column = &columns[clamp_x(x)];
+            for (bin, count) in kernel.iter_mut().zip(column.iter()) {
+                *bin += count;
+            }
+        }
+        for x in 0..width {
+            let mut seen = 0;
+            for (value, count) in kernel.iter().enumerate() {
+                seen += count;
+                if seen >= target {
+                    result[y * width + x] = value as u8;
+                    break;
+                }
+            }
+            if x + 1 < width {
+                let old = &columns[clamp_x(x as i64 - r)];
+                let new = &columns[clamp_x(x as i64 + r + 1)];
+                for bin in 0..256 {
+                    kernel[bin] = kernel[bin] + new[bin] - old[bin];
+                }
+            }
+        }
+    }
+    result
+}
+
+impl Canvas {
+    /// Median filtered copy of the canvas, see `median_filter`.
+    fn median_filtered(&self, radius: u32) -> Canvas {
+        let dimensions = self.dimensions();
+        let (width, height) = (dimensions.width as usize, dimensions.height as usize);
+        if width == 0 || height == 0 || radius == 0 {
+            return self.clone();
+        }
+        let premultiplied = self.clone().premultiply();
+        let channel = |f: fn(&Pixel) -> u8| -> Vec<u8> {
+            let values: Vec<u8> = premultiplied.pixels().map(f).collect();
+            median_channel(&values, width, height, radius as usize)
+        };
+        let (r, g, b, a) = (
+            channel(|p| p.r),
+            channel(|p| p.g),
+            channel(|p| p.b),
+            channel(|p| p.a),
+        );
+        let pixels = (0..width * height)
+            .map(|i| Pixel::new(r[i], g[i], b[i], a[i]))
+            .collect();
+        Canvas::new_with_data(dimensions.width, dimensions.height, pixels).unpremultiply()
+    }
+
+    /// Bilateral filtered copy of the canvas, see `bilateral_filter`.
+    fn bilateral_filtered(&self, spatial_sigma: f32, range_sigma: f32) -> Canvas {
+        let planes = Planes::from_canvas(self);
+        let (width, height) = (planes.width as i64, planes.height as i64);
+        let radius = (2.0 * spatial_sigma).ceil().max(1.0) as i64;
+        let spatial = -0.5 / (spatial_sigma * spatial_sigma).max(f32::EPSILON);
+        let range = -0.5 / (range_sigma * range_sigma).max(f32::EPSILON);
+
+        let mut result = planes.like();
+        for y in 0..height {
+            for x in 0..width {
+                let center = (y * width + x) as usize;
+                let mut sums = [0f32; 4];
+                let mut total = 0f32;
+                for ny in (y - radius).max(0)..=(y + radius).min(height - 1) {
+                    for nx in (x - radius).max(0)..=(x + radius).min(width - 1) {
+                        let neighbour = (ny * width + nx) as usize;
+                        let d2 = ((nx - x) * (nx - x) + (ny - y) * (ny - y)) as f32;
+                        let weight = (d2 * spatial
+                            + planes.distance_squared(center, neighbour) * range)
+                            .exp();
+                        for (sum, channel) in sums.iter_mut().zip(planes.channels.iter()) {
+                            *sum += weight * channel[neighbour];
+                        }
+                        total += weight;
+                    }
+                }
+                for (channel, sum) in result.channels.iter_mut().zip(sums) {
+                    channel[center] = sum / total;
+                }
+            }
+        }
+        result.to_canvas()
+    }
+
+    /// Guided filtered copy of the canvas, see `guided_filter_with`.
+    fn guided_filtered(&self, guide: &Canvas, radius: u32, epsilon: f32) -> Canvas {
+        let (d1, d2) = (self.dimensions(), guide.dimensions());
+        if d1.width != d2.width || d1.height != d2.height || d1.width == 0 || d1.height == 0 {
+            return self.clone();
+        }
+        let planes = Planes::from_canvas(self);
+        let (width, height) = (planes.width, planes.height);
+        let radius = radius as usize;
+        let mean = |values: &[f32]| box_mean(values, width, height, radius);
+        let multiply =
+            |a: &[f32], b: &[f32]| -> Vec<f32> { a.iter().zip(b).map(|(a, b)| a * b).collect() };
+
+        let guide: Vec<f32> = guide
+            .pixels()
+            .map(|p| (0.299 * p.r as f32 + 0.587 * p.g as f32 + 0.114 * p.b as f32) / 255.0)
+            .collect();
+        let mean_guide = mean(&guide);
+        let variance: Vec<f32> = mean(&multiply(&guide, &guide))
+            .iter()
+            .zip(&mean_guide)
+            .map(|(squares, m)| squares - m * m)
+            .collect();
+
+        let mut result = planes.like();
+        for (out, channel) in result.channels.iter_mut().zip(planes.channels.iter()) {
+            let input: Vec<f32> = channel.iter().map(|v| v / 255.0).collect();
+            let mean_input = mean(&input);
+            let mean_product = mean(&multiply(&guide, &input));
+            let mut a = vec![0f32; input.len()];
+            let mut b = vec![0f32; input.len()];
+            for i in 0..input.len() {
+                let covariance = mean_product[i] - mean_guide[i] * mean_input[i];
+                a[i] = covariance / (variance[i] + epsilon);
+                b[i] = mean_input[i] - a[i] * mean_guide[i];
+            }
+            let (mean_a, mean_b) = (mean(&a), mean(&b));
+            *out = (0..input.len())
+                .map(|i| (mean_a[i] * guide[i] + mean_b[i]) * 255.0)
+                .collect();
+        }
+        result.to_canvas()
+    }
+
+    /// Non-local means filtered copy of the canvas, see `non_local_means`.
+    fn non_local_means_filtered(&self, h: f32, patch_radius: u32, search_radius: u32) -> Canvas {
+        let planes = Planes::from_canvas(self);
+        let (width, height) = (planes.width, planes.height);
+        if width == 0 || height == 0 {
+            return self.clone();
+        }
+        let count = width * height;
+        let (patch, search) = (patch_radius as i64, search_radius as i64);
+        let h2 = (h * h).max(f32::EPSILON) as f64;
+        let mut sums = [
+            vec![0f64; count],
+            vec![0f64; count],
+            vec![0f64; count],
+            vec![0f64; count],
+        ];
+        let mut totals = vec![0f64; count];
+
+        // For every offset the patch distances of all pixels come from one summed-area table
+        for dy in -search..=search {
+            for dx in -search..=search {
+                let shifted = |x: usize, y: usize| {
+                    let sx = (x as i64 + dx).clamp(0, width as i64 - 1) as usize;
+                    let sy = (y as i64 + dy).clamp(0, height as i64 - 1) as usize;
+                    sy * width + sx
+                };
+                let mut differences = vec![0f32; count];
+                for y in 0..height {
+                    for x in 0..width {
+                        let i = y * width + x;
+                        differences[i] = planes.distance_squared(i, shifted(x, y));
+                    }
+                }
+                let table = integral(&differences, width, height);
+                for y in 0..height {
+                    let y0 = (y as i64 - patch).max(0) as usize;
+                    let y1 = (y as i64 + patch).min(height as i64 - 1) as usize;
+                    for x in 0..width {
+                        let x0 = (x as i64 - patch).max(0) as usize;
+                        let x1 = (x as i64 + patch).min(width as i64 - 1) as usize;
+                        let area = ((x1 - x0 + 1) * (y1 - y0 + 1) * 4) as f64;
+                        let distance = area_sum(&table, width, x0, y0, x1, y1) / area;
+                        let weight = (-distance / h2).exp();
+                        let i = y * width + x;
+                        let source = shifted(x, y);
+                        for (sum, channel) in sums.iter_mut().zip(planes.channels.iter()) {
+                            sum[i] += weight * channel[source] as f64;
+                        }
+                        totals[i] += weight;
+                    }
+                }
+            }
+        }
+
+        let mut result = planes.like();
+        for (out, sum) in result.channels.iter_mut().zip(sums.iter()) {
+            *out = sum
+                .iter()
+                .zip(&totals)
+                .map(|(s, t)| (s / t) as f32)
+                .collect();
+        }
+        result.to_canvas()
+    }
+}
+
+impl Canvas {
+    /// Replaces every channel of every pixel by the median of the `(2 * radius + 1)` square
+    /// around it. Runs in constant time per pixel, so large radii are as fast as small ones.
+    /// Colors are filtered premultiplied by alpha.
+    pub fn median_filter(mut self, radius: u32) -> Canvas {
+        self.median_filter_mut(radius);
+        self
+    }
+
+    /// Replaces every channel of every pixel by the median of the `(2 * radius + 1)` square
+    /// around it. See `median_filter`.
+    pub fn median_filter_mut(&mut self, radius: u32) {
+        *self = self.median_filtered(radius);
+    }
+
+    /// Smooths the canvas while keeping edges. Neighbours are weighted by their distance with
+    /// `spatial_sigma` in pixels and by their color difference with `range_sigma` in channel
+    /// values between `0` and `255`. Colors are filtered premultiplied by alpha.
+    pub fn bilateral_filter(mut self, spatial_sigma: f32, range_sigma: f32) -> Canvas {
+        self.bilateral_filter_mut(spatial_sigma, range_sigma);
+        self
+    }
+
+    /// Smooths the canvas while keeping edges. See `bilateral_filter`.
+    pub fn bilateral_filter_mut(&mut self, spatial_sigma: f32, range_sigma: f32) {
+        *self = self.bilateral_filtered(spatial_sigma, range_sigma);
+    }
+
+    /// Edge preserving smoothing guided by the luma of the canvas itself. See
+    /// `guided_filter_with`.
+    pub fn guided_filter(mut self, radius: u32, epsilon: f32) -> Canvas {
+        self.guided_filter_mut(radius, epsilon);
+        self
+    }
+
+    /// Edge preserving smoothing guided by the luma of the canvas itself. See
+    /// `guided_filter_with`.
+    pub fn guided_filter_mut(&mut self, radius: u32, epsilon: f32) {
+        *self = self.guided_filtered(&self.clone(), radius, epsilon);
+    }
+
+    /// Guided filter of He, Sun and Tang. The result follows the edges of the luma of `guide`
+    /// within every `(2 * radius + 1)` window. `epsilon` is the regularization on normalized
+    /// values; larger values smooth more. `guide` must have the same dimensions as the canvas,
+    /// otherwise the canvas is returned unchanged.
+    pub fn guided_filter_with(mut self, guide: &Canvas, radius: u32, epsilon: f32) -> Canvas {
+        self.guided_filter_with_mut(guide, radius, epsilon);
+        self
+    }
+
+    /// Guided filter with the luma of `guide`. See `guided_filter_with`.
+    pub fn guided_filter_with_mut(&mut self, guide: &Canvas, radius: u32, epsilon: f32) {
+        *self = self.guided_filtered(guide, radius, epsilon);
+    }
+
+    /// Non-local means denoising. Every pixel becomes the average of the pixels within
+    /// `search_radius` whose surrounding `(2 * patch_radius + 1)` patch looks alike. `h` controls
+    /// how alike patches must be, in channel values between `0` and `255`.
+    pub fn non_local_means(mut self, h: f32, patch_radius: u32, search_radius: u32) -> Canvas {
+        self.non_local_means_mut(h, patch_radius, search_radius);
+        self
+    }
+
+    /// Non-local means denoising. See `non_local_means`.
+    pub fn non_local_means_mut(&mut self, h: f32, patch_radius: u32, search_radius: u32) {
+        *self = self.non_local_means_filtered(h, patch_radius, search_radius);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::mse;
+    use crate::pixels::{ColorTrait, Colors};
+    use rand::rngs::StdRng;
+    use rand::{RngExt, SeedableRng};
+
+    /// A black and white edge with seeded noise added.
+    fn noisy_edge(seed: u64) -> (Canvas, Canvas) {
+        let clean = Canvas::new_with_background(24, 24, Pixel::new(200, 200, 200, 255))
+            .draw_square(0, 0, 12, 24, &Pixel::new(40, 40, 40, 255));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let pixels: Vec<Pixel> = clean
+            .pixels()
+            .map(|p| {
+                let n: i32 = rng.random_range(-30..=30);
+                let v = (p.r as i32 + n).clamp(0, 255) as u8;
+                Pixel::new(v, v, v, 255)
+            })
+            .collect();
+        (clean, Canvas::new_with_data(24, 24, pixels))
+    }
+
+    fn naive_median(canvas: &Canvas, radius: i64, x: i64, y: i64) -> u8 {
+        let d = canvas.dimensions();
+        let mut values = vec![];
+        for ny in y - radius..=y + radius {
+            for nx in x - radius..=x + radius {
+                let (cx, cy) = (
+                    nx.clamp(0, d.width as i64 - 1),
+                    ny.clamp(0, d.height as i64 - 1),
+                );
+                values.push(canvas.get_pixel(cx as u32, cy as u32).g);
+            }
+        }
+        values.sort();
+        values[values.len() / 2]
+    }
+
+    #[test]
+    fn median_matches_naive_median() {
+        let canvas = Canvas::new_random(13, 9, 4);
+        for radius in [1, 2, 5] {
+            let filtered = canvas.clone().median_filter(radius);
+            for y in 0..9 {
+                for x in 0..13 {
+                    let expected = naive_median(&canvas, radius as i64, x, y);
+                    assert_eq!(filtered.get_pixel(x as u32, y as u32).g, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn median_removes_impulses() {
+        let canvas =
+            Canvas::new(9, 9)
+                .set_pixel(4, 4, &Colors::BLACK)
+                .set_pixel(0, 0, &Colors::RED);
+        assert_eq!(canvas.clone().median_filter(1), Canvas::new(9, 9));
+        assert_eq!(canvas.clone().median_filter(0), canvas);
+    }
+
+    #[test]
+    fn denoisers_reduce_noise_and_keep_edges() {
+        let (clean, noisy) = noisy_edge(9);
+        let before = mse(&clean, &noisy).unwrap();
+        let results = [
+            noisy.clone().median_filter(2),
+            noisy.clone().bilateral_filter(2.0, 60.0),
+            noisy.clone().guided_filter(2, 0.01),
+            noisy.clone().non_local_means(25.0, 1, 4),
+        ];
+        for result in results {
+            assert!(mse(&clean, &result).unwrap() < before / 2.0);
+            // The edge stays sharp
+            assert!(result.get_pixel(10, 12).r < 90);
+            assert!(result.get_pixel(13, 12).r > 150);
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_bleed() {
+        // Red that is fully transparent must not tint the opaque blue next to it
+        let canvas = Canvas::new_with_background(8, 8, Pixel::new(255, 0, 0, 0)).draw_square(
+            0,
+            0,
+            4,
+            8,
+            &Colors::BLUE,
+        );
+        let results = [
+            canvas.clone().median_filter(1),
+            canvas.clone().bilateral_filter(1.5, 500.0),
+            canvas.clone().guided_filter(1, 0.5),
+            canvas.clone().non_local_means(1000.0, 1, 2),
+        ];
+        for result in results {
+            let p = result.get_pixel(3, 4);
+            assert_eq!((p.r, p.g), (0, 0));
+            assert!(p.a > 0);
+        }
+    }
+}
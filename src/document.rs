@@ -0,0 +1,383 @@
+use crate::blend::BlendMode;
+use crate::canvas::{Canvas, ImageError, Point, Size};
+use crate::mask::Mask;
+use crate::pixels::Pixel;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const MANIFEST_FILE: &str = "manifest.txt";
+const MANIFEST_HEADER: &str = "pixtra-document 1";
+
+/// A single layer of a `Document`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    pub canvas: Canvas,
+    /// Position of the top left corner of the layer in the document
+    pub offset: Point,
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+    pub visible: bool,
+    /// Mask positioned like the layer canvas. Only set parts of the layer are drawn.
+    pub mask: Option<Mask>,
+}
+
+impl Layer {
+    /// Creates a new visible layer named `name` holding `canvas` at offset `(0, 0)`, with full
+    /// opacity and the normal blend mode.
+    pub fn new(name: &str, canvas: Canvas) -> Layer {
+        Layer {
+            name: name.to_string(),
+            canvas,
+            offset: Point { x: 0, y: 0 },
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            visible: true,
+            mask: None,
+        }
+    }
+
+    pub fn set_offset(mut self, offset: Point) -> Layer {
+        self.offset = offset;
+        self
+    }
+
+    pub fn set_opacity(mut self, opacity: f32) -> Layer {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn set_blend_mode(mut self, blend_mode: BlendMode) -> Layer {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    pub fn set_visible(mut self, visible: bool) -> Layer {
+        self.visible = visible;
+        self
+    }
+
+    pub fn set_mask(mut self, mask: Mask) -> Layer {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Returns the layer canvas with the mask applied to its alpha channel.
+    fn masked_canvas(&self) -> Canvas {
+        match &self.mask {
+            None => self.canvas.clone(),
+            Some(mask) => {
+                let dimensions = self.canvas.dimensions();
+                let mut canvas = self.canvas.clone();
+                for y in 0..dimensions.height {
+                    for x in 0..dimensions.width {
+                        let p = canvas.get_pixel(x, y);
+                        let alpha = (p.a as f32 * mask.get(x, y)).round() as u8;
+                        canvas.set_pixel_mut(x, y, &p.set_alpha(alpha));
+                    }
+                }
+                canvas
+            }
+        }
+    }
+}
+
+/// An ordered stack of named layers. The first layer is at the bottom.
+///
+/// # Examples
+///
+/// ```
+/// use pixtra::blend::BlendMode;
+/// use pixtra::canvas::{Canvas, Point};
+/// use pixtra::document::{Document, Layer};
+/// use pixtra::pixels::{ColorTrait, Colors};
+///
+/// let document = Document::new(100, 100)
+///     .add_layer(Layer::new("Background", Canvas::new(100, 100)))
+///     .add_layer(
+///         Layer::new("Shadow", Canvas::new_with_background(20, 20, Colors::BLACK))
+///             .set_offset(Point { x: 10, y: 10 })
+///             .set_blend_mode(BlendMode::Multiply)
+///             .set_opacity(0.5),
+///     );
+/// let canvas = document.flatten();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    width: u32,
+    height: u32,
+    layers: Vec<Layer>,
+}
+
+impl Document {
+    /// Creates a new empty document of size `width` and `height`
+    pub fn new(width: u32, height: u32) -> Document {
+        Document {
+            width: width.max(1),
+            height: height.max(1),
+            layers: vec![],
+        }
+    }
+
+    /// Retrieves width and height of the document in a `Size` struct.
+    pub fn dimensions(&self) -> Size {
+        Size {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Adds `layer` on top of all other layers
+    pub fn add_layer(mut self, layer: Layer) -> Document {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Adds `layer` on top of all other layers
+    pub fn add_layer_mut(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+
+    /// Returns all layers from bottom to top
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Returns all layers from bottom to top
+    pub fn layers_mut(&mut self) -> &mut Vec<Layer> {
+        &mut self.layers
+    }
+
+    /// Returns the first layer named `name`
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// Returns the first layer named `name`
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    /// Removes and returns the first layer named `name`
+    pub fn remove_layer(&mut self, name: &str) -> Option<Layer> {
+        let index = self.layers.iter().position(|layer| layer.name == name)?;
+        Some(self.layers.remove(index))
+    }
+
+    /// Moves the layer at index `from` to index `to` in the stack
+    pub fn move_layer(&mut self, from: usize, to: usize) {
+        if from < self.layers.len() && to < self.layers.len() {
+            let layer = self.layers.remove(from);
+            self.layers.insert(to, layer);
+        }
+    }
+
+    /// Draws all visible layers from bottom to top onto a transparent canvas
+    pub fn flatten(&self) -> Canvas {
+        let mut canvas =
+            Canvas::new_with_background(self.width, self.height, Pixel::new(0, 0, 0, 0));
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            canvas.draw_subimage_with_mut(
+                layer.offset.x,
+                layer.offset.y,
+                &layer.masked_canvas(),
+                layer.blend_mode,
+                layer.opacity,
+            );
+        }
+        canvas
+    }
+
+    /// Saves the document to the directory `directory`. The directory contains a
+    /// `manifest.txt` describing the layers and a PNG file per layer and mask.
+    pub fn save(&self, directory: &Path) -> Result<(), ImageError> {
+        fs::create_dir_all(directory).map_err(|e| ImageError::IoError(e.to_string()))?;
+        let mut manifest = vec![
+            MANIFEST_HEADER.to_string(),
+            format!("size {} {}", self.width, self.height),
+        ];
+        for (i, layer) in self.layers.iter().enumerate() {
+            let file = format!("layer-{}.png", i);
+            layer.canvas.save(&directory.join(&file))?;
+            manifest.push("layer".to_string());
+            manifest.push(format!("name {}", layer.name.replace('\n', " ")));
+            manifest.push(format!("file {}", file));
+            manifest.push(format!("offset {} {}", layer.offset.x, layer.offset.y));
+            manifest.push(format!("opacity {}", layer.opacity));
+            manifest.push(format!("blend {}", layer.blend_mode.name()));
+            manifest.push(format!("visible {}", layer.visible));
+            if let Some(mask) = &layer.mask {
+                let mask_file = format!("layer-{}-mask.png", i);
+                mask.to_canvas().save(&directory.join(&mask_file))?;
+                manifest.push(format!("mask {}", mask_file));
+            }
+            manifest.push("end".to_string());
+        }
+        manifest.push(String::new());
+        fs::write(directory.join(MANIFEST_FILE), manifest.join("\n"))
+            .map_err(|e| ImageError::IoError(e.to_string()))
+    }
+
+    /// Loads a document saved with `Document::save` from the directory `directory`
+    pub fn load(directory: &Path) -> Result<Document, ImageError> {
+        let content = fs::read_to_string(directory.join(MANIFEST_FILE))
+            .map_err(|e| ImageError::IoError(e.to_string()))?;
+        let invalid = |line: &str| ImageError::Decoding(format!("Invalid manifest line: {}", line));
+        let number = |line: &str, value: Option<&str>| -> Result<u32, ImageError> {
+            value
+                .and_then(|v| v.parse::<u32>().ok())
+                .ok_or_else(|| invalid(line))
+        };
+        // Layer files must be directly inside the document directory
+        let file = |line: &str, value: &str| -> Result<PathBuf, ImageError> {
+            let mut components = Path::new(value).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(name)), None) => Ok(directory.join(name)),
+                _ => Err(invalid(line)),
+            }
+        };
+
+        let mut lines = content.lines();
+        if lines.next() != Some(MANIFEST_HEADER) {
+            return Err(ImageError::Decoding(
+                "Not a pixtra document manifest".to_string(),
+            ));
+        }
+
+        let mut document: Option<Document> = None;
+        let mut layer: Option<Layer> = None;
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match (key, layer.as_mut()) {
+                ("", None) => {}
+                ("size", None) => {
+                    let mut parts = value.split(' ');
+                    let width = number(line, parts.next())?;
+                    let height = number(line, parts.next())?;
+                    document = Some(Document::new(width, height));
+                }
+                ("layer", None) => layer = Some(Layer::new("", Canvas::new(1, 1))),
+                ("name", Some(layer)) => layer.name = value.to_string(),
+                ("file", Some(layer)) => layer.canvas = Canvas::load(&file(line, value)?)?,
+                ("offset", Some(layer)) => {
+                    let mut parts = value.split(' ');
+                    layer.offset = Point {
+                        x: number(line, parts.next())?,
+                        y: number(line, parts.next())?,
+                    };
+                }
+                ("opacity", Some(layer)) => {
+                    layer.opacity = value.parse::<f32>().map_err(|_| invalid(line))?;
+                }
+                ("blend", Some(layer)) => {
+                    layer.blend_mode = BlendMode::from_name(value).ok_or_else(|| invalid(line))?;
+                }
+                ("visible", Some(layer)) => {
+                    layer.visible = value.parse::<bool>().map_err(|_| invalid(line))?;
+                }
+                ("mask", Some(layer)) => {
+                    layer.mask = Some(Mask::from_canvas(&Canvas::load(&file(line, value)?)?));
+                }
+                ("end", Some(_)) => {
+                    let document = document.as_mut().ok_or_else(|| invalid(line))?;
+                    document.layers.extend(layer.take());
+                }
+                _ => return Err(invalid(line)),
+            }
+        }
+        if layer.is_some() {
+            return Err(ImageError::Decoding("Unterminated layer".to_string()));
+        }
+        document.ok_or_else(|| ImageError::Decoding("Missing document size".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::{FillRule, Path as VectorPath};
+    use crate::pixels::{ColorTrait, Colors};
+
+    fn example_document() -> Document {
+        let mask = Mask::from_path(
+            4,
+            4,
+            &VectorPath::rect(0.0, 0.0, 2.0, 4.0),
+            FillRule::NonZero,
+        );
+        Document::new(8, 8)
+            .add_layer(Layer::new("Background", Canvas::new(8, 8)))
+            .add_layer(
+                Layer::new("Red box", Canvas::new_with_background(4, 4, Colors::RED))
+                    .set_offset(Point { x: 2, y: 2 })
+                    .set_mask(mask),
+            )
+            .add_layer(
+                Layer::new("Shade", Canvas::new_with_background(8, 8, Colors::BLACK))
+                    .set_blend_mode(BlendMode::Multiply)
+                    .set_opacity(0.5),
+            )
+            .add_layer(Layer::new("Hidden", Canvas::new(8, 8)).set_visible(false))
+    }
+
+    #[test]
+    fn flatten_uses_offset_mask_and_opacity() {
+        let canvas = example_document().flatten();
+        assert_eq!(canvas.get_pixel(0, 0), Pixel::new(128, 128, 128, 255));
+        assert_eq!(canvas.get_pixel(2, 2), Pixel::new(128, 0, 0, 255));
+        // The right half of the red box is masked out
+        assert_eq!(canvas.get_pixel(5, 2), Pixel::new(128, 128, 128, 255));
+    }
+
+    #[test]
+    fn empty_document_is_transparent() {
+        let canvas = Document::new(3, 3).flatten();
+        assert_eq!(canvas.count_pixels(&Pixel::new(0, 0, 0, 0)), 9);
+    }
+
+    #[test]
+    fn layer_management() {
+        let mut document = example_document();
+        document.move_layer(0, 3);
+        assert_eq!(document.layers()[3].name, "Background");
+        assert!(document.remove_layer("Shade").is_some());
+        assert!(document.layer("Shade").is_none());
+        document.layer_mut("Hidden").unwrap().visible = true;
+        assert_eq!(document.flatten().get_pixel(0, 0), Colors::WHITE);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let directory =
+            std::env::temp_dir().join(format!("pixtra-document-{}", std::process::id()));
+        let document = example_document();
+        document.save(&directory).unwrap();
+        let loaded = Document::load(&directory).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(loaded.layers().len(), 4);
+        assert_eq!(loaded.layers()[2].blend_mode, BlendMode::Multiply);
+        assert!(!loaded.layers()[3].visible);
+        assert_eq!(loaded.flatten(), document.flatten());
+    }
+
+    #[test]
+    fn load_rejects_files_outside_the_directory() {
+        let directory =
+            std::env::temp_dir().join(format!("pixtra-document-escape-{}", std::process::id()));
+        example_document().save(&directory).unwrap();
+        let manifest = directory.join(MANIFEST_FILE);
+        let content = fs::read_to_string(&manifest).unwrap();
+        let mut results = vec![];
+        for file in ["../layer-0.png", "/tmp/layer-0.png", "sub/layer-0.png"] {
+            let escaped = content.replacen("file layer-0.png", &format!("file {}", file), 1);
+            assert_ne!(escaped, content);
+            fs::write(&manifest, escaped).unwrap();
+            results.push(Document::load(&directory));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+        for result in results {
+            assert!(matches!(result, Err(ImageError::Decoding(_))));
+        }
+    }
+}
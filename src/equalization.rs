@@ -0,0 +1,260 @@
+use crate::canvas::Canvas;
+use crate::colorspace::{oklab_to_pixel, pixel_to_oklab};
+use crate::pixels::Pixel;
+
+/// A lookup table for global histogram equalization of the Oklab lightness of a canvas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Equalization {
+    /// Histogram of the quantized lightness the mapping was computed from
+    pub histogram: [u32; 256],
+    /// New lightness for every quantized lightness
+    pub mapping: [u8; 256],
+}
+
+impl Equalization {
+    /// Computes the equalization mapping for `histogram`
+    pub fn from_histogram(histogram: &[u32; 256]) -> Equalization {
+        let mut mapping = [0u8; 256];
+        let total: u64 = histogram.iter().map(|&c| c as u64).sum();
+        let first = histogram.iter().find(|&&c| c > 0).copied().unwrap_or(0) as u64;
+        let mut cdf = 0u64;
+        for (value, &count) in histogram.iter().enumerate() {
+            cdf += count as u64;
+            mapping[value] = if total > first {
+                ((cdf.saturating_sub(first)) as f64 / (total - first) as f64 * 255.0).round() as u8
+            } else {
+                value as u8
+            };
+        }
+        Equalization {
+            histogram: *histogram,
+            mapping,
+        }
+    }
+}
+
+/// Contrast limited adaptive histogram equalization (CLAHE) computed for a grid of tiles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clahe {
+    pub tile_size: u32,
+    pub clip_limit: f32,
+    pub tiles_x: u32,
+    pub tiles_y: u32,
+    /// Lightness histogram of each tile, row by row
+    pub histograms: Vec<[u32; 256]>,
+    /// Clipped equalization mapping of each tile, row by row
+    pub mappings: Vec<[u8; 256]>,
+}
+
+/// Oklab lightness of `p` quantized to `0..=255`.
+fn lightness(p: &Pixel) -> u8 {
+    let (l, _, _) = pixel_to_oklab(p);
+    (l.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Replaces the Oklab lightness of `p` while keeping its hue, chroma and alpha.
+fn with_lightness(p: &Pixel, new_lightness: f32) -> Pixel {
+    let (_, a, b) = pixel_to_oklab(p);
+    oklab_to_pixel(new_lightness.clamp(0.0, 1.0), a, b, p.a)
+}
+
+/// Clips `histogram` at `limit` and spreads the excess evenly over all bins.
+fn clip_histogram(histogram: &[u32; 256], limit: u32) -> [u32; 256] {
+    let mut clipped = *histogram;
+    let mut excess = 0u32;
+    for count in clipped.iter_mut() {
+        if *count > limit {
+            excess += *count - limit;
+            *count = limit;
+        }
+    }
+    let (share, remainder) = (excess / 256, (excess % 256) as usize);
+    for (i, count) in clipped.iter_mut().enumerate() {
+        *count += share;
+        if i < remainder {
+            *count += 1;
+        }
+    }
+    clipped
+}
+
+impl Canvas {
+    /// Counts how many pixels have each quantized Oklab lightness
+    pub fn lightness_histogram(&self) -> [u32; 256] {
+        let mut histogram = [0u32; 256];
+        for p in self.pixels() {
+            histogram[lightness(p) as usize] += 1;
+        }
+        histogram
+    }
+
+    /// Computes the global histogram equalization of the canvas
+    pub fn equalization(&self) -> Equalization {
+        Equalization::from_histogram(&self.lightness_histogram())
+    }
+
+    /// Remaps the lightness of every pixel with `equalization`. Hue and alpha are preserved.
+    pub fn apply_equalization(&self, equalization: &Equalization) -> Canvas {
+        let dimensions = self.dimensions();
+        let pixels = self
+            .pixels()
+            .map(|p| {
+                let mapped = equalization.mapping[lightness(p) as usize];
+                with_lightness(p, mapped as f32 / 255.0)
+            })
+            .collect();
+        Canvas::new_with_data(dimensions.width, dimensions.height, pixels)
+    }
+
+    /// Spreads the lightness of the canvas over the full range using global histogram
+    /// equalization in Oklab. Hue and alpha are preserved.
+    pub fn equalize_histogram(&self) -> Canvas {
+        self.apply_equalization(&self.equalization())
+    }
+
+    /// Computes the tiles used by contrast limited adaptive histogram equalization. The canvas
+    /// is split into tiles of `tile_size x tile_size` pixels and `clip_limit` limits each
+    /// histogram bin to that many times the average bin count.
+    pub fn clahe_tiles(&self, tile_size: u32, clip_limit: f32) -> Clahe {
+        let dimensions = self.dimensions();
+        let tile_size = tile_size.max(1);
+        let tiles_x = dimensions.width.div_ceil(tile_size);
+        let tiles_y = dimensions.height.div_ceil(tile_size);
+        let mut histograms = vec![[0u32; 256]; (tiles_x * tiles_y) as usize];
+        for p in self.iter_with_coordinates() {
+            let tile = (p.coordinate.y / tile_size) * tiles_x + p.coordinate.x / tile_size;
+            histograms[tile as usize][lightness(&p.pixel) as usize] += 1;
+        }
+
+        let mappings = histograms
+            .iter()
+            .map(|histogram| {
+                let total: u32 = histogram.iter().sum();
+                let limit = if clip_limit > 0.0 {
+                    ((clip_limit * total as f32 / 256.0).ceil() as u32).max(1)
+                } else {
+                    u32::MAX
+                };
+                let clipped = clip_histogram(histogram, limit);
+                let mut mapping = [0u8; 256];
+                let mut cdf = 0u32;
+                for (value, &count) in clipped.iter().enumerate() {
+                    cdf += count;
+                    mapping[value] = (cdf as f32 / total.max(1) as f32 * 255.0).round() as u8;
+                }
+                mapping
+            })
+            .collect();
+
+        Clahe {
+            tile_size,
+            clip_limit,
+            tiles_x,
+            tiles_y,
+            histograms,
+            mappings,
+        }
+    }
+
+    /// Remaps the lightness of every pixel by bilinear interpolation between the mappings of the
+    /// four closest tiles of `clahe`. Hue and alpha are preserved.
+    pub fn apply_clahe(&self, clahe: &Clahe) -> Canvas {
+        let dimensions = self.dimensions();
+        let size = clahe.tile_size as f32;
+        // Position in tile coordinates where tile centers are at whole numbers
+        let locate = |position: u32, tiles: u32| -> (usize, usize, f32) {
+            let t = ((position as f32 + 0.5) / size - 0.5).max(0.0);
+            let first = (t.floor() as u32).min(tiles - 1);
+            let second = (first + 1).min(tiles - 1);
+            (first as usize, second as usize, (t - first as f32).min(1.0))
+        };
+        let mut canvas = self.clone();
+        for y in 0..dimensions.height {
+            let (y0, y1, fy) = locate(y, clahe.tiles_y);
+            for x in 0..dimensions.width {
+                let (x0, x1, fx) = locate(x, clahe.tiles_x);
+                let p = self.get_pixel(x, y);
+                let value = lightness(&p) as usize;
+                let map = |tx: usize, ty: usize| {
+                    clahe.mappings[ty * clahe.tiles_x as usize + tx][value] as f32
+                };
+                let top = map(x0, y0) * (1.0 - fx) + map(x1, y0) * fx;
+                let bottom = map(x0, y1) * (1.0 - fx) + map(x1, y1) * fx;
+                let mapped = top * (1.0 - fy) + bottom * fy;
+                canvas.set_pixel_mut(x, y, &with_lightness(&p, mapped / 255.0));
+            }
+        }
+        canvas
+    }
+
+    /// Contrast limited adaptive histogram equalization (CLAHE) of the Oklab lightness. The
+    /// canvas is split into tiles of `tile_size x tile_size` pixels and `clip_limit` limits how
+    /// much contrast is added, as a multiple of the average histogram bin count. A `clip_limit`
+    /// of `0.0` disables clipping. Hue and alpha are preserved.
+    pub fn clahe(&self, tile_size: u32, clip_limit: f32) -> Canvas {
+        self.apply_clahe(&self.clahe_tiles(tile_size, clip_limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statistics::Channel;
+
+    fn low_contrast() -> Canvas {
+        let pixels = (0..256u32)
+            .map(|i| {
+                let v = 100 + (i % 64) as u8;
+                Pixel::new(v, v, v, 255)
+            })
+            .collect();
+        Canvas::new_with_data(16, 16, pixels)
+    }
+
+    #[test]
+    fn equalization_stretches_range() {
+        let equalized = low_contrast().equalize_histogram();
+        let statistics = equalized.statistics(Channel::Luma).unwrap();
+        assert!(statistics.min < 5);
+        assert!(statistics.max > 250);
+        // Grey stays grey
+        assert!(equalized.pixels().all(|p| p.r == p.g && p.g == p.b));
+    }
+
+    #[test]
+    fn equalization_preserves_hue() {
+        let pixels = (0..64u8)
+            .map(|i| Pixel::new(100 + i, 40 + i / 2, 40, 200))
+            .collect();
+        let canvas = Canvas::new_with_data(8, 8, pixels);
+        let equalized = canvas.equalize_histogram();
+        assert!(equalized
+            .pixels()
+            .all(|p| p.r >= p.g && p.g >= p.b && p.a == 200));
+        let equalization = canvas.equalization();
+        assert_eq!(equalization.histogram, canvas.lightness_histogram());
+        assert_eq!(equalized, canvas.apply_equalization(&equalization));
+    }
+
+    #[test]
+    fn clahe_limits_contrast() {
+        let canvas = low_contrast();
+        let clahe = canvas.clahe_tiles(8, 2.0);
+        assert_eq!((clahe.tiles_x, clahe.tiles_y), (2, 2));
+        assert_eq!(clahe.histograms[0].iter().sum::<u32>(), 64);
+
+        let limited = canvas.clahe(8, 1.5).statistics(Channel::Luma).unwrap();
+        let unlimited = canvas.clahe(8, 0.0).statistics(Channel::Luma).unwrap();
+        let original = canvas.statistics(Channel::Luma).unwrap();
+        assert!(unlimited.stddev > limited.stddev);
+        assert!(limited.stddev > original.stddev);
+    }
+
+    #[test]
+    fn clahe_keeps_flat_image_flat() {
+        let canvas = Canvas::new_with_background(20, 20, Pixel::new(90, 90, 90, 255));
+        let result = canvas.clahe(8, 2.0);
+        let first = result.get_pixel(0, 0);
+        assert!(result.pixels().all(|p| p == &first));
+    }
+}
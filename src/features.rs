@@ -0,0 +1,435 @@
+use crate::blur::{gaussian_values, EdgeMode};
+use crate::canvas::{Canvas, Point};
+use crate::path::{Path, Stroke};
+use crate::pixels::Pixel;
+
+/// Offsets of the 16 pixels on the Bresenham circle of radius 3 used by FAST, in order.
+const FAST_CIRCLE: [(i64, i64); 16] = [
+    (0, -3),
+    (1, -3),
+    (2, -2),
+    (3, -1),
+    (3, 0),
+    (3, 1),
+    (2, 2),
+    (1, 3),
+    (0, 3),
+    (-1, 3),
+    (-2, 2),
+    (-3, 1),
+    (-3, 0),
+    (-3, -1),
+    (-2, -2),
+    (-1, -3),
+];
+
+/// Number of contiguous circle pixels that must all be brighter or darker for FAST-9.
+const FAST_ARC: usize = 9;
+
+/// A distinctive point of a canvas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keypoint {
+    pub point: Point,
+    /// Strength of the response. Only comparable between keypoints of the same detector.
+    pub score: f32,
+    /// Radius in pixels of the neighbourhood the keypoint was detected in.
+    pub scale: f32,
+}
+
+/// Options for the Harris and Shi–Tomasi corner detectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CornerOptions {
+    /// Standard deviation of the gaussian window the gradients are summed over. Defaults
+    /// to `1.5`.
+    pub sigma: f32,
+    /// Sensitivity of the Harris response, usually between `0.04` and `0.06`. Defaults to
+    /// `0.04`.
+    pub harris_k: f32,
+    /// Minimum score relative to the strongest corner, between `0.0` and `1.0`. Defaults to
+    /// `0.01`.
+    pub quality: f32,
+    /// Minimum distance in pixels between two corners. Defaults to `3.0`.
+    pub min_distance: f32,
+    /// Maximum number of corners returned, the strongest first. Defaults to no limit.
+    pub max_corners: Option<usize>,
+}
+
+impl Default for CornerOptions {
+    fn default() -> Self {
+        CornerOptions::new()
+    }
+}
+
+impl CornerOptions {
+    pub fn new() -> CornerOptions {
+        CornerOptions {
+            sigma: 1.5,
+            harris_k: 0.04,
+            quality: 0.01,
+            min_distance: 3.0,
+            max_corners: None,
+        }
+    }
+
+    pub fn set_sigma(mut self, sigma: f32) -> CornerOptions {
+        self.sigma = sigma;
+        self
+    }
+
+    pub fn set_harris_k(mut self, harris_k: f32) -> CornerOptions {
+        self.harris_k = harris_k;
+        self
+    }
+
+    pub fn set_quality(mut self, quality: f32) -> CornerOptions {
+        self.quality = quality;
+        self
+    }
+
+    pub fn set_min_distance(mut self, min_distance: f32) -> CornerOptions {
+        self.min_distance = min_distance;
+        self
+    }
+
+    pub fn set_max_corners(mut self, max_corners: usize) -> CornerOptions {
+        self.max_corners = Some(max_corners);
+        self
+    }
+}
+
+/// Luma of every pixel between `0.0` and `255.0`.
+fn intensities(canvas: &Canvas) -> Vec<f32> {
+    canvas
+        .pixels()
+        .map(|p| 0.299 * p.r as f32 + 0.587 * p.g as f32 + 0.114 * p.b as f32)
+        .collect()
+}
+
+/// Gaussian weighted structure tensor `(Ixx, Iyy, Ixy)` of every pixel from Sobel gradients.
+fn structure_tensor(canvas: &Canvas, sigma: f32) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+    let dimensions = canvas.dimensions();
+    let (width, height) = (dimensions.width as usize, dimensions.height as usize);
+    let values: Vec<f32> = intensities(canvas).iter().map(|v| v / 255.0).collect();
+    let at = |x: i64, y: i64| {
+        let x = x.clamp(0, width as i64 - 1) as usize;
+        let y = y.clamp(0, height as i64 - 1) as usize;
+        values[y * width + x]
+    };
+    let mut xx = Vec::with_capacity(values.len());
+    let mut yy = Vec::with_capacity(values.len());
+    let mut xy = Vec::with_capacity(values.len());
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let gx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+            let gy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+            xx.push(gx * gx);
+            yy.push(gy * gy);
+            xy.push(gx * gy);
+        }
+    }
+    let blur = |values: &[f32]| gaussian_values(values, width, height, sigma, EdgeMode::Clamp);
+    (blur(&xx), blur(&yy), blur(&xy))
+}
+
+/// Keeps the local maxima of `response` that are within `options.quality` of the strongest,
+/// at least `options.min_distance` apart.
+fn select_corners(
+    response: &[f32],
+    width: usize,
+    height: usize,
+    scale: f32,
+    options: &CornerOptions,
+) -> Vec<Keypoint> {
+    let strongest = response.iter().cloned().fold(0.0, f32::max);
+    if strongest <= 0.0 {
+        return vec![];
+    }
+    let threshold = strongest * options.quality.max(f32::MIN_POSITIVE);
+    let mut candidates = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            let score = response[y * width + x];
+            if score < threshold {
+                continue;
+            }
+            let is_maximum = (y.saturating_sub(1)..=(y + 1).min(height - 1)).all(|ny| {
+                (x.saturating_sub(1)..=(x + 1).min(width - 1))
+                    .all(|nx| response[ny * width + nx] <= score)
+            });
+            if is_maximum {
+                candidates.push(Keypoint {
+                    point: Point {
+                        x: x as u32,
+                        y: y as u32,
+                    },
+                    score,
+                    scale,
+                });
+            }
+        }
+    }
+    suppress_nearby(candidates, options.min_distance, options.max_corners)
+}
+
+/// Keeps the strongest keypoints that are at least `min_distance` away from all stronger ones.
+fn suppress_nearby(
+    mut candidates: Vec<Keypoint>,
+    min_distance: f32,
+    max_keypoints: Option<usize>,
+) -> Vec<Keypoint> {
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let min_squared = min_distance * min_distance;
+    let mut keypoints: Vec<Keypoint> = vec![];
+    for candidate in candidates {
+        if max_keypoints.is_some_and(|max| keypoints.len() >= max) {
+            break;
+        }
+        let too_close = keypoints.iter().any(|k| {
+            let dx = k.point.x as f32 - candidate.point.x as f32;
+            let dy = k.point.y as f32 - candidate.point.y as f32;
+            dx * dx + dy * dy < min_squared
+        });
+        if !too_close {
+            keypoints.push(candidate);
+        }
+    }
+    keypoints
+}
+
+/// FAST score of the pixel at `(x, y)`: the summed difference beyond `threshold` of the
+/// brighter or darker circle pixels, or `0.0` if fewer than nine contiguous ones differ.
+fn fast_score(values: &[f32], width: usize, x: usize, y: usize, threshold: f32) -> f32 {
+    let center = values[y * width + x];
+    let ring: Vec<f32> = FAST_CIRCLE
+        .iter()
+        .map(|(dx, dy)| values[(y as i64 + dy) as usize * width + (x as i64 + dx) as usize])
+        .collect();
+    let mut best = 0f32;
+    for sign in [1.0, -1.0] {
+        let differs = |v: &f32| sign * (v - center) > threshold;
+        let mut run = 0;
+        let mut longest = 0;
+        // Going around twice finds arcs that wrap past the start
+        for v in ring.iter().chain(ring.iter()) {
+            run = if differs(v) { run + 1 } else { 0 };
+            longest = longest.max(run);
+        }
+        if longest >= FAST_ARC {
+            let score = ring
+                .iter()
+                .filter(|v| differs(v))
+                .map(|v| sign * (v - center) - threshold)
+                .sum();
+            best = best.max(score);
+        }
+    }
+    best
+}
+
+impl Canvas {
+    /// Finds corners with the Harris detector. See `CornerOptions` for the parameters.
+    pub fn harris_corners(&self, options: &CornerOptions) -> Vec<Keypoint> {
+        let dimensions = self.dimensions();
+        let (width, height) = (dimensions.width as usize, dimensions.height as usize);
+        if width == 0 || height == 0 {
+            return vec![];
+        }
+        let (xx, yy, xy) = structure_tensor(self, options.sigma);
+        let response: Vec<f32> = (0..width * height)
+            .map(|i| {
+                let trace = xx[i] + yy[i];
+                xx[i] * yy[i] - xy[i] * xy[i] - options.harris_k * trace * trace
+            })
+            .collect();
+        select_corners(&response, width, height, 3.0 * options.sigma, options)
+    }
+
+    /// Finds corners with the Shi–Tomasi detector, also known as good features to track. The
+    /// score is the smaller eigenvalue of the structure tensor. See `CornerOptions` for the
+    /// parameters; `harris_k` is not used.
+    pub fn shi_tomasi_corners(&self, options: &CornerOptions) -> Vec<Keypoint> {
+        let dimensions = self.dimensions();
+        let (width, height) = (dimensions.width as usize, dimensions.height as usize);
+        if width == 0 || height == 0 {
+            return vec![];
+        }
+        let (xx, yy, xy) = structure_tensor(self, options.sigma);
+        let response: Vec<f32> = (0..width * height)
+            .map(|i| {
+                let half_difference = (xx[i] - yy[i]) / 2.0;
+                (xx[i] + yy[i]) / 2.0 - half_difference.hypot(xy[i])
+            })
+            .collect();
+        select_corners(&response, width, height, 3.0 * options.sigma, options)
+    }
+
+    /// Finds corners with FAST-9. A pixel is a corner if nine contiguous pixels on the circle
+    /// of radius 3 around it are all brighter or all darker by more than `threshold`. Only the
+    /// strongest corner of every 3x3 neighbourhood is kept. Pixels within 3 of the border are
+    /// never corners.
+    pub fn fast_corners(&self, threshold: u8) -> Vec<Keypoint> {
+        let dimensions = self.dimensions();
+        let (width, height) = (dimensions.width as usize, dimensions.height as usize);
+        if width < 7 || height < 7 {
+            return vec![];
+        }
+        let values = intensities(self);
+        let mut scores = vec![0f32; width * height];
+        for y in 3..height - 3 {
+            for x in 3..width - 3 {
+                scores[y * width + x] = fast_score(&values, width, x, y, threshold as f32);
+            }
+        }
+
+        let mut keypoints = vec![];
+        for y in 3..height - 3 {
+            for x in 3..width - 3 {
+                let score = scores[y * width + x];
+                if score <= 0.0 {
+                    continue;
+                }
+                // Ties go to the first pixel in reading order
+                let is_maximum = (y - 1..=y + 1).all(|ny| {
+                    (x - 1..=x + 1).all(|nx| {
+                        let other = scores[ny * width + nx];
+                        if (ny, nx) < (y, x) {
+                            other < score
+                        } else {
+                            other <= score
+                        }
+                    })
+                });
+                if is_maximum {
+                    keypoints.push(Keypoint {
+                        point: Point {
+                            x: x as u32,
+                            y: y as u32,
+                        },
+                        score,
+                        scale: 3.0,
+                    });
+                }
+            }
+        }
+        keypoints.sort_by(|a, b| b.score.total_cmp(&a.score));
+        keypoints
+    }
+
+    /// Draws every keypoint as a circle with radius `scale` around a dot at its point.
+    pub fn draw_keypoints_mut(&mut self, keypoints: &[Keypoint], color: &Pixel) {
+        let mut circles = Path::new();
+        for keypoint in keypoints {
+            let (x, y) = (keypoint.point.x as f32 + 0.5, keypoint.point.y as f32 + 0.5);
+            circles = circles.append(&Path::circle(x, y, keypoint.scale.max(1.0)));
+        }
+        self.stroke_path_mut(&circles, &Stroke::new(1.0), color);
+        for keypoint in keypoints {
+            self.set_pixel_mut(keypoint.point.x, keypoint.point.y, color);
+        }
+    }
+
+    /// Draws every keypoint as a circle with radius `scale` around a dot at its point.
+    pub fn draw_keypoints(mut self, keypoints: &[Keypoint], color: &Pixel) -> Canvas {
+        self.draw_keypoints_mut(keypoints, color);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+
+    /// A white square from `(10, 10)` to `(19, 19)` on black.
+    fn square() -> Canvas {
+        Canvas::new_with_background(32, 32, Colors::BLACK).draw_square(
+            10,
+            10,
+            10,
+            10,
+            &Colors::WHITE,
+        )
+    }
+
+    const CORNERS: [(u32, u32); 4] = [(10, 10), (19, 10), (10, 19), (19, 19)];
+
+    fn assert_finds_corners(keypoints: &[Keypoint]) {
+        let near = |k: &Keypoint, (x, y): (u32, u32)| {
+            k.point.x.abs_diff(x) <= 2 && k.point.y.abs_diff(y) <= 2
+        };
+        assert_eq!(keypoints.len(), 4, "{:?}", keypoints);
+        for corner in CORNERS {
+            assert!(keypoints.iter().any(|k| near(k, corner)), "{:?}", corner);
+        }
+    }
+
+    #[test]
+    fn harris_finds_square_corners() {
+        assert_finds_corners(&square().harris_corners(&CornerOptions::new()));
+    }
+
+    #[test]
+    fn shi_tomasi_finds_square_corners() {
+        let keypoints = square().shi_tomasi_corners(&CornerOptions::new().set_quality(0.1));
+        assert_finds_corners(&keypoints);
+        assert!(keypoints.windows(2).all(|w| w[0].score >= w[1].score));
+        let limited = square().shi_tomasi_corners(&CornerOptions::new().set_max_corners(2));
+        assert_eq!(limited.len(), 2);
+    }
+
+    #[test]
+    fn fast_finds_square_corners() {
+        let keypoints = square().fast_corners(40);
+        assert_finds_corners(&keypoints);
+        assert!(keypoints.iter().all(|k| k.scale == 3.0));
+    }
+
+    #[test]
+    fn flat_canvases_have_no_corners() {
+        let canvas = Canvas::new_with_background(16, 16, Pixel::new(90, 20, 200, 255));
+        assert!(canvas.harris_corners(&CornerOptions::new()).is_empty());
+        assert!(canvas.shi_tomasi_corners(&CornerOptions::new()).is_empty());
+        assert!(canvas.fast_corners(10).is_empty());
+        assert!(Canvas::new(3, 3).fast_corners(10).is_empty());
+    }
+
+    #[test]
+    fn fast_needs_nine_contiguous_pixels() {
+        // A thin line only has two short arcs on the circle
+        let line = Canvas::new_with_background(15, 15, Colors::BLACK).draw_square(
+            0,
+            7,
+            15,
+            1,
+            &Colors::WHITE,
+        );
+        let values = intensities(&line);
+        assert_eq!(fast_score(&values, 15, 7, 7, 20.0), 0.0);
+        assert_eq!(fast_score(&values, 15, 7, 4, 20.0), 0.0);
+        // The tip of a line ending in the middle is surrounded by darker pixels
+        let tip = Canvas::new_with_background(15, 15, Colors::BLACK).draw_square(
+            0,
+            7,
+            8,
+            1,
+            &Colors::WHITE,
+        );
+        assert!(fast_score(&intensities(&tip), 15, 7, 7, 20.0) > 0.0);
+    }
+
+    #[test]
+    fn draws_keypoints() {
+        let keypoint = Keypoint {
+            point: Point { x: 10, y: 10 },
+            score: 1.0,
+            scale: 4.0,
+        };
+        let canvas = Canvas::new(21, 21).draw_keypoints(&[keypoint], &Colors::RED);
+        assert_eq!(canvas.get_pixel(10, 10), Colors::RED);
+        assert_ne!(canvas.get_pixel(14, 10), Colors::WHITE);
+        assert_eq!(canvas.get_pixel(12, 10), Colors::WHITE);
+        assert_eq!(canvas.get_pixel(0, 0), Colors::WHITE);
+    }
+}
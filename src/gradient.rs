@@ -0,0 +1,379 @@
+use crate::canvas::{Canvas, Island, Rect};
+use crate::colorspace::{linear_to_oklab, linear_to_pixel, oklab_to_linear, pixel_to_linear};
+use crate::path::{FillRule, Path};
+use crate::pixels::Pixel;
+use crate::utility::overlap_colors;
+use std::f32::consts::PI;
+
+/// A color at a position `offset` between `0.0` and `1.0` along a `Gradient`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorStop {
+    pub offset: f32,
+    pub color: Pixel,
+}
+
+/// The color space colors are blended in between two stops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Srgb,
+    LinearRgb,
+    Oklab,
+}
+
+/// The shape of a `Gradient`. Coordinates are in pixels of the canvas it is drawn on.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GradientKind {
+    /// Runs from `start` to `end`
+    Linear { start: (f32, f32), end: (f32, f32) },
+    /// Runs from `center` outwards to `radius`
+    Radial { center: (f32, f32), radius: f32 },
+    /// Runs clockwise around `center` starting at angle `angle` (radians)
+    Conic { center: (f32, f32), angle: f32 },
+}
+
+/// A gradient definition with any number of color stops.
+///
+/// # Examples
+///
+/// ```
+/// use pixtra::canvas::Canvas;
+/// use pixtra::gradient::{Gradient, Interpolation};
+/// use pixtra::pixels::{ColorTrait, Colors};
+///
+/// let gradient = Gradient::linear(0.0, 0.0, 100.0, 0.0)
+///     .add_stop(0.0, &Colors::BLUE)
+///     .add_stop(1.0, &Colors::RED)
+///     .set_interpolation(Interpolation::Oklab);
+/// let canvas = Canvas::new_with_gradient(100, 20, &gradient);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub interpolation: Interpolation,
+    stops: Vec<ColorStop>,
+}
+
+impl Gradient {
+    /// Creates a gradient of kind `kind` without any stops
+    pub fn new(kind: GradientKind) -> Gradient {
+        Gradient {
+            kind,
+            interpolation: Interpolation::Srgb,
+            stops: vec![],
+        }
+    }
+
+    /// Creates a linear gradient from `(x0, y0)` to `(x1, y1)`
+    pub fn linear(x0: f32, y0: f32, x1: f32, y1: f32) -> Gradient {
+        Gradient::new(GradientKind::Linear {
+            start: (x0, y0),
+            end: (x1, y1),
+        })
+    }
+
+    /// Creates a radial gradient centered at `(cx, cy)` with radius `radius`
+    pub fn radial(cx: f32, cy: f32, radius: f32) -> Gradient {
+        Gradient::new(GradientKind::Radial {
+            center: (cx, cy),
+            radius,
+        })
+    }
+
+    /// Creates a conic gradient centered at `(cx, cy)` starting at angle `angle` in radians
+    pub fn conic(cx: f32, cy: f32, angle: f32) -> Gradient {
+        Gradient::new(GradientKind::Conic {
+            center: (cx, cy),
+            angle,
+        })
+    }
+
+    /// Adds a color stop. Stops are kept sorted by offset.
+    pub fn add_stop(mut self, offset: f32, color: &Pixel) -> Gradient {
+        let offset = offset.clamp(0.0, 1.0);
+        let index = self.stops.partition_point(|stop| stop.offset <= offset);
+        self.stops.insert(
+            index,
+            ColorStop {
+                offset,
+                color: color.clone(),
+            },
+        );
+        self
+    }
+
+    pub fn set_interpolation(mut self, interpolation: Interpolation) -> Gradient {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Returns the color stops sorted by offset
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    /// Returns the color at position `t` between `0.0` and `1.0` along the gradient. Values
+    /// outside are clamped. A gradient without stops is fully transparent.
+    pub fn color_at(&self, t: f32) -> Pixel {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Pixel::new(0, 0, 0, 0),
+        };
+        if t <= first.offset {
+            return first.color.clone();
+        }
+        if t >= last.offset {
+            return last.color.clone();
+        }
+        let index = self.stops.partition_point(|stop| stop.offset <= t);
+        let (from, to) = (&self.stops[index - 1], &self.stops[index]);
+        let span = to.offset - from.offset;
+        let amount = if span > 0.0 {
+            (t - from.offset) / span
+        } else {
+            1.0
+        };
+        self.mix(&from.color, &to.color, amount)
+    }
+
+    /// Blends two colors in the interpolation space using premultiplied alpha.
+    fn mix(&self, from: &Pixel, to: &Pixel, amount: f32) -> Pixel {
+        let to_space = |p: &Pixel| -> (f32, f32, f32) {
+            match self.interpolation {
+                Interpolation::Srgb => {
+                    let (r, g, b, _) = p.normalize();
+                    (r, g, b)
+                }
+                Interpolation::LinearRgb => pixel_to_linear(p),
+                Interpolation::Oklab => {
+                    let (r, g, b) = pixel_to_linear(p);
+                    linear_to_oklab(r, g, b)
+                }
+            }
+        };
+        let (fa, ta) = (from.a as f32 / 255.0, to.a as f32 / 255.0);
+        let alpha = fa + (ta - fa) * amount;
+        if alpha <= 0.0 {
+            return Pixel::new(0, 0, 0, 0);
+        }
+        let (f, t) = (to_space(from), to_space(to));
+        let blend = |a: f32, b: f32| (a * fa + (b * ta - a * fa) * amount) / alpha;
+        let (c0, c1, c2) = (blend(f.0, t.0), blend(f.1, t.1), blend(f.2, t.2));
+        let alpha = (alpha * 255.0).round() as u8;
+        match self.interpolation {
+            Interpolation::Srgb => Pixel::from(
+                (c0 * 255.0).round(),
+                (c1 * 255.0).round(),
+                (c2 * 255.0).round(),
+                alpha as f32,
+            ),
+            Interpolation::LinearRgb => linear_to_pixel(c0, c1, c2, alpha),
+            Interpolation::Oklab => {
+                let (r, g, b) = oklab_to_linear(c0, c1, c2);
+                linear_to_pixel(r, g, b, alpha)
+            }
+        }
+    }
+
+    /// Returns the position along the gradient for the point `(x, y)`.
+    pub fn offset_at(&self, x: f32, y: f32) -> f32 {
+        match self.kind {
+            GradientKind::Linear { start, end } => {
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let length = dx * dx + dy * dy;
+                if length == 0.0 {
+                    return 0.0;
+                }
+                ((x - start.0) * dx + (y - start.1) * dy) / length
+            }
+            GradientKind::Radial { center, radius } => {
+                if radius <= 0.0 {
+                    return 1.0;
+                }
+                ((x - center.0).powi(2) + (y - center.1).powi(2)).sqrt() / radius
+            }
+            GradientKind::Conic { center, angle } => {
+                let theta = (y - center.1).atan2(x - center.0) - angle;
+                theta.rem_euclid(2.0 * PI) / (2.0 * PI)
+            }
+        }
+    }
+
+    /// Returns the color of the pixel at `(x, y)`, sampled at the pixel center.
+    pub fn color_at_pixel(&self, x: u32, y: u32) -> Pixel {
+        self.color_at(self.offset_at(x as f32 + 0.5, y as f32 + 0.5))
+    }
+}
+
+impl Canvas {
+    /// Creates a new `Canvas` of size `width` and `height` filled with `gradient`.
+    pub fn new_with_gradient(width: u32, height: u32, gradient: &Gradient) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        let dimensions = canvas.dimensions();
+        for y in 0..dimensions.height {
+            for x in 0..dimensions.width {
+                canvas.set_pixel_mut(x, y, &gradient.color_at_pixel(x, y));
+            }
+        }
+        canvas
+    }
+
+    fn blend_gradient_pixel(&mut self, x: u32, y: u32, gradient: &Gradient, coverage: f32) {
+        let color = gradient.color_at_pixel(x, y);
+        let color = if coverage < 1.0 {
+            let alpha = (color.a as f32 * coverage).round() as u8;
+            color.set_alpha(alpha)
+        } else {
+            color
+        };
+        let destination = self.get_pixel(x, y);
+        self.set_pixel_mut(x, y, &overlap_colors(&destination, &color));
+    }
+
+    /// Draws `gradient` over the entire canvas
+    pub fn fill_gradient_mut(&mut self, gradient: &Gradient) {
+        let dimensions = self.dimensions();
+        for y in 0..dimensions.height {
+            for x in 0..dimensions.width {
+                self.blend_gradient_pixel(x, y, gradient, 1.0);
+            }
+        }
+    }
+
+    /// Draws `gradient` over the entire canvas
+    pub fn fill_gradient(mut self, gradient: &Gradient) -> Canvas {
+        self.fill_gradient_mut(gradient);
+        self
+    }
+
+    /// Draws `gradient` inside `rect`. Gradient coordinates are relative to the canvas.
+    pub fn fill_gradient_rect_mut(&mut self, rect: &Rect, gradient: &Gradient) {
+        let dimensions = self.dimensions();
+        let end_x = rect
+            .start
+            .x
+            .saturating_add(rect.size.width)
+            .min(dimensions.width);
+        let end_y = rect
+            .start
+            .y
+            .saturating_add(rect.size.height)
+            .min(dimensions.height);
+        for y in rect.start.y..end_y {
+            for x in rect.start.x..end_x {
+                self.blend_gradient_pixel(x, y, gradient, 1.0);
+            }
+        }
+    }
+
+    /// Draws `gradient` inside `rect`. Gradient coordinates are relative to the canvas.
+    pub fn fill_gradient_rect(mut self, rect: &Rect, gradient: &Gradient) -> Canvas {
+        self.fill_gradient_rect_mut(rect, gradient);
+        self
+    }
+
+    /// Draws `gradient` on all points of `island`
+    pub fn fill_gradient_island_mut(&mut self, island: &Island, gradient: &Gradient) {
+        for point in island.points.iter() {
+            self.blend_gradient_pixel(point.x, point.y, gradient, 1.0);
+        }
+    }
+
+    /// Draws `gradient` on all points of `island`
+    pub fn fill_gradient_island(mut self, island: &Island, gradient: &Gradient) -> Canvas {
+        self.fill_gradient_island_mut(island, gradient);
+        self
+    }
+
+    /// Draws `gradient` inside `path` using fill rule `rule`. Edges are anti-aliased.
+    pub fn fill_path_gradient_mut(&mut self, path: &Path, gradient: &Gradient, rule: FillRule) {
+        let dimensions = self.dimensions();
+        let coverage = path.coverage(dimensions.width, dimensions.height, rule);
+        for y in 0..dimensions.height {
+            for x in 0..dimensions.width {
+                let amount = coverage[(y * dimensions.width + x) as usize];
+                if amount > 0.0 {
+                    self.blend_gradient_pixel(x, y, gradient, amount);
+                }
+            }
+        }
+    }
+
+    /// Draws `gradient` inside `path` using fill rule `rule`. Edges are anti-aliased.
+    pub fn fill_path_gradient(
+        mut self,
+        path: &Path,
+        gradient: &Gradient,
+        rule: FillRule,
+    ) -> Canvas {
+        self.fill_path_gradient_mut(path, gradient, rule);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{Point, Size};
+    use crate::pixels::{ColorTrait, Colors};
+
+    fn black_to_white() -> Gradient {
+        Gradient::linear(0.0, 0.0, 10.0, 0.0)
+            .add_stop(1.0, &Colors::WHITE)
+            .add_stop(0.0, &Colors::BLACK)
+    }
+
+    #[test]
+    fn stops_are_sorted_and_clamped() {
+        let gradient = black_to_white();
+        assert_eq!(gradient.stops()[0].color, Colors::BLACK);
+        assert_eq!(gradient.color_at(-1.0), Colors::BLACK);
+        assert_eq!(gradient.color_at(2.0), Colors::WHITE);
+        assert_eq!(gradient.color_at(0.5), Pixel::new(128, 128, 128, 255));
+    }
+
+    #[test]
+    fn interpolation_spaces_differ() {
+        let gradient = black_to_white().set_interpolation(Interpolation::LinearRgb);
+        assert_eq!(gradient.color_at(0.5), Pixel::new(188, 188, 188, 255));
+        // Oklab lightness 0.5 is perceptually halfway, which is darker than the sRGB midpoint
+        let oklab = black_to_white().set_interpolation(Interpolation::Oklab);
+        let mid = oklab.color_at(0.5);
+        assert!(mid.r > 90 && mid.r < 110);
+        assert_eq!(mid.r, mid.b);
+    }
+
+    #[test]
+    fn transparent_stop_keeps_color() {
+        let gradient = Gradient::linear(0.0, 0.0, 1.0, 0.0)
+            .add_stop(0.0, &Colors::RED)
+            .add_stop(1.0, &Pixel::new(0, 0, 0, 0));
+        assert_eq!(gradient.color_at(0.5), Pixel::new(255, 0, 0, 128));
+    }
+
+    #[test]
+    fn radial_and_conic_offsets() {
+        let radial = Gradient::radial(5.0, 5.0, 5.0);
+        assert_eq!(radial.offset_at(5.0, 5.0), 0.0);
+        assert_eq!(radial.offset_at(10.0, 5.0), 1.0);
+        let conic = Gradient::conic(5.0, 5.0, 0.0);
+        assert!((conic.offset_at(5.0, 10.0) - 0.25).abs() < 1e-5);
+        assert!((conic.offset_at(0.0, 5.0) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn fill_only_inside_rect() {
+        let rect = Rect {
+            start: Point { x: 2, y: 2 },
+            size: Size {
+                width: 3,
+                height: 3,
+            },
+        };
+        let gradient = Gradient::linear(0.0, 0.0, 1.0, 0.0).add_stop(0.0, &Colors::RED);
+        let canvas = Canvas::new(10, 10).fill_gradient_rect(&rect, &gradient);
+        assert_eq!(canvas.count_pixels(&Colors::RED), 9);
+        assert_eq!(canvas.get_pixel(2, 2), Colors::RED);
+        assert_eq!(canvas.get_pixel(5, 5), Colors::WHITE);
+    }
+}
@@ -0,0 +1,656 @@
+use crate::canvas::{Canvas, Point, Size};
+use crate::pixels::Pixel;
+use crate::utility::to_grey_lumiosity;
+use rand::rngs::StdRng;
+use rand::{Rng, RngExt, SeedableRng};
+use std::f32::consts::PI;
+
+/// Grey value from which a pixel of an edge image counts as an edge.
+const EDGE_THRESHOLD: u8 = 128;
+
+/// Number of angles the line accumulator of `hough_lines` is divided into.
+const THETA_STEPS: u32 = 180;
+
+/// Votes of a Hough transform. Brighter bins in `to_canvas` got more votes.
+#[derive(Debug, Clone)]
+pub struct Accumulator {
+    width: u32,
+    height: u32,
+    votes: Vec<u32>,
+}
+
+impl Accumulator {
+    fn new(width: u32, height: u32) -> Accumulator {
+        Accumulator {
+            width,
+            height,
+            votes: vec![0; (width * height) as usize],
+        }
+    }
+
+    /// Number of bins horizontally and vertically.
+    pub fn dimensions(&self) -> Size {
+        Size {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Votes of the bin at `(x, y)`.
+    pub fn get(&self, x: u32, y: u32) -> u32 {
+        self.votes[(y * self.width + x) as usize]
+    }
+
+    /// Votes of all bins row by row.
+    pub fn votes(&self) -> &[u32] {
+        &self.votes
+    }
+
+    /// Grey canvas of the votes where the fullest bin is white.
+    pub fn to_canvas(&self) -> Canvas {
+        let max = self.votes.iter().cloned().max().unwrap_or(0).max(1) as f32;
+        let pixels = self
+            .votes
+            .iter()
+            .map(|&v| {
+                let grey = (v as f32 / max * 255.0).round();
+                Pixel::from(grey, grey, grey, 255.0)
+            })
+            .collect();
+        Canvas::new_with_data(self.width, self.height, pixels)
+    }
+
+    fn add(&mut self, x: u32, y: u32, amount: i32) {
+        let bin = &mut self.votes[(y * self.width + x) as usize];
+        *bin = bin.saturating_add_signed(amount);
+    }
+
+    /// Bins with at least `threshold` votes that are not exceeded by a neighbour, most votes
+    /// first. With `wrap_lines` the first and last columns are neighbours with mirrored rows,
+    /// as angle `0` with distance `rho` is angle `PI` with distance `-rho`.
+    fn peaks(&self, threshold: u32, wrap_lines: bool) -> Vec<(u32, u32, u32)> {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let mut peaks = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                let votes = self.get(x as u32, y as u32);
+                if votes < threshold.max(1) {
+                    continue;
+                }
+                let mut is_maximum = true;
+                for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+                    let (mut nx, mut ny) = (x + dx, y + dy);
+                    if wrap_lines && !(0..width).contains(&nx) {
+                        nx = nx.rem_euclid(width);
+                        ny = height - 1 - ny;
+                    }
+                    let outside = !(0..width).contains(&nx) || !(0..height).contains(&ny);
+                    if (dx, dy) == (0, 0) || outside {
+                        continue;
+                    }
+                    // Ties go to the bin in the first column, the smallest angle for lines
+                    let other = self.get(nx as u32, ny as u32);
+                    let earlier = (nx, ny) < (x, y);
+                    if other > votes || (earlier && other == votes) {
+                        is_maximum = false;
+                        break;
+                    }
+                }
+                if is_maximum {
+                    peaks.push((x as u32, y as u32, votes));
+                }
+            }
+        }
+        peaks.sort_by_key(|p| std::cmp::Reverse(p.2));
+        peaks
+    }
+}
+
+/// An infinite line in normal form: the points `(x, y)` with
+/// `x * cos(theta) + y * sin(theta) = rho`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Line {
+    pub rho: f32,
+    /// Angle of the normal in radians between `0` and `PI`. `0` is a vertical line.
+    pub theta: f32,
+    pub votes: u32,
+}
+
+impl Line {
+    /// Where the line enters and leaves a `width x height` canvas, or `None` if it misses it.
+    pub fn endpoints(&self, width: u32, height: u32) -> Option<((f32, f32), (f32, f32))> {
+        let (sin, cos) = self.theta.sin_cos();
+        let (max_x, max_y) = (width as f32 - 1.0, height as f32 - 1.0);
+        let mut points: Vec<(f32, f32)> = vec![];
+        if cos.abs() > 1e-6 {
+            for y in [0.0, max_y] {
+                points.push(((self.rho - y * sin) / cos, y));
+            }
+        }
+        if sin.abs() > 1e-6 {
+            for x in [0.0, max_x] {
+                points.push((x, (self.rho - x * cos) / sin));
+            }
+        }
+        let epsilon = 1e-3;
+        points.retain(|(x, y)| {
+            (-epsilon..=max_x + epsilon).contains(x) && (-epsilon..=max_y + epsilon).contains(y)
+        });
+        // Order along the direction of the line to pick the two outermost points
+        let along = |p: &(f32, f32)| p.0 * sin - p.1 * cos;
+        let first = points
+            .iter()
+            .cloned()
+            .min_by(|a, b| along(a).total_cmp(&along(b)))?;
+        let last = points
+            .iter()
+            .cloned()
+            .max_by(|a, b| along(a).total_cmp(&along(b)))?;
+        Some((first, last))
+    }
+}
+
+/// A finite line segment found by `probabilistic_hough_lines`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl Segment {
+    pub fn length(&self) -> f32 {
+        let dx = self.end.x as f32 - self.start.x as f32;
+        let dy = self.end.y as f32 - self.start.y as f32;
+        dx.hypot(dy)
+    }
+}
+
+/// A circle found by `hough_circles`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Circle {
+    pub center: Point,
+    pub radius: f32,
+    /// Number of edge pixels on the circle.
+    pub votes: u32,
+}
+
+/// Options for `hough_circles`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircleOptions {
+    pub min_radius: u32,
+    pub max_radius: u32,
+    /// Minimum gradient magnitude of an edge pixel, in grey levels of a step edge. Defaults
+    /// to `64.0`.
+    pub edge_threshold: f32,
+    /// Minimum number of votes for a centre. Defaults to `20`.
+    pub min_votes: u32,
+    /// Minimum distance in pixels between two centres. Defaults to `min_radius`, at least 1.
+    pub min_distance: f32,
+}
+
+impl CircleOptions {
+    /// Options to find circles with a radius between `min_radius` and `max_radius`.
+    pub fn new(min_radius: u32, max_radius: u32) -> CircleOptions {
+        CircleOptions {
+            min_radius,
+            max_radius: max_radius.max(min_radius),
+            edge_threshold: 64.0,
+            min_votes: 20,
+            min_distance: min_radius.max(1) as f32,
+        }
+    }
+
+    pub fn set_edge_threshold(mut self, edge_threshold: f32) -> CircleOptions {
+        self.edge_threshold = edge_threshold;
+        self
+    }
+
+    pub fn set_min_votes(mut self, min_votes: u32) -> CircleOptions {
+        self.min_votes = min_votes;
+        self
+    }
+
+    pub fn set_min_distance(mut self, min_distance: f32) -> CircleOptions {
+        self.min_distance = min_distance;
+        self
+    }
+}
+
+/// Rho bins are offset by the canvas diagonal so negative distances fit.
+fn diagonal(width: u32, height: u32) -> u32 {
+    (width as f32).hypot(height as f32).ceil() as u32
+}
+
+/// Sines and cosines of the angles of the line accumulator.
+fn angles(steps: u32) -> Vec<(f32, f32)> {
+    (0..steps)
+        .map(|i| (i as f32 * PI / steps as f32).sin_cos())
+        .collect()
+}
+
+/// Adds `amount` votes of the edge pixel at `(x, y)` to every angle of `accumulator`.
+fn vote_line(accumulator: &mut Accumulator, angles: &[(f32, f32)], x: u32, y: u32, amount: i32) {
+    let offset = (accumulator.height / 2) as f32;
+    for (i, (sin, cos)) in angles.iter().enumerate() {
+        let rho = x as f32 * cos + y as f32 * sin;
+        accumulator.add(i as u32, (rho + offset).round() as u32, amount);
+    }
+}
+
+impl Canvas {
+    /// Whether every pixel is an edge, which are pixels with a grey value of at least 128.
+    fn edge_pixels(&self) -> Vec<bool> {
+        self.pixels()
+            .map(|p| p.a > 0 && to_grey_lumiosity(p).r >= EDGE_THRESHOLD)
+            .collect()
+    }
+
+    /// Hough transform for lines of an edge image, where pixels with a grey value of at least
+    /// 128 are edges. Column `x` holds angle `x * PI / theta_steps` and row `y` distance
+    /// `y - height / 2`.
+    pub fn hough_line_accumulator(&self, theta_steps: u32) -> Accumulator {
+        let dimensions = self.dimensions();
+        let diagonal = diagonal(dimensions.width, dimensions.height);
+        let mut accumulator = Accumulator::new(theta_steps.max(1), 2 * diagonal + 1);
+        let angles = angles(theta_steps.max(1));
+        for (i, edge) in self.edge_pixels().into_iter().enumerate() {
+            if edge {
+                let (x, y) = (i as u32 % dimensions.width, i as u32 / dimensions.width);
+                vote_line(&mut accumulator, &angles, x, y, 1);
+            }
+        }
+        accumulator
+    }
+
+    /// Finds straight lines with the standard Hough transform of an edge image, where pixels
+    /// with a grey value of at least 128 are edges. Lines need at least `threshold` edge
+    /// pixels and are returned with the most votes first.
+    pub fn hough_lines(&self, threshold: u32) -> Vec<Line> {
+        let accumulator = self.hough_line_accumulator(THETA_STEPS);
+        let offset = (accumulator.height / 2) as f32;
+        accumulator
+            .peaks(threshold, true)
+            .into_iter()
+            .map(|(x, y, votes)| Line {
+                rho: y as f32 - offset,
+                theta: x as f32 * PI / THETA_STEPS as f32,
+                votes,
+            })
+            .collect()
+    }
+
+    /// Finds line segments with the progressive probabilistic Hough transform. Edge pixels are
+    /// visited in a random order seeded with `seed`. See `probabilistic_hough_lines_with`.
+    pub fn probabilistic_hough_lines(
+        &self,
+        threshold: u32,
+        min_length: f32,
+        max_gap: u32,
+        seed: u64,
+    ) -> Vec<Segment> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.probabilistic_hough_lines_with(threshold, min_length, max_gap, &mut rng)
+    }
+
+    /// Finds line segments with the progressive probabilistic Hough transform of an edge
+    /// image, where pixels with a grey value of at least 128 are edges. Edge pixels are visited
+    /// in random order from `rng`. Once a line gets `threshold` votes it is followed along the
+    /// edge, bridging gaps of up to `max_gap` pixels, and its pixels are removed. Segments
+    /// shorter than `min_length` are dropped.
+    pub fn probabilistic_hough_lines_with<R: Rng + ?Sized>(
+        &self,
+        threshold: u32,
+        min_length: f32,
+        max_gap: u32,
+        rng: &mut R,
+    ) -> Vec<Segment> {
+        let dimensions = self.dimensions();
+        let (width, height) = (dimensions.width, dimensions.height);
+        let mut remaining = self.edge_pixels();
+        let mut points: Vec<u32> = (0..width * height)
+            .filter(|&i| remaining[i as usize])
+            .collect();
+        for i in (1..points.len()).rev() {
+            let j = rng.random_range(0..=i);
+            points.swap(i, j);
+        }
+
+        let diagonal = diagonal(width, height);
+        let mut accumulator = Accumulator::new(THETA_STEPS, 2 * diagonal + 1);
+        let angles = angles(THETA_STEPS);
+        let steps = THETA_STEPS as i64;
+        let mut voted = vec![false; (width * height) as usize];
+        let mut segments = vec![];
+        for index in points {
+            if !remaining[index as usize] {
+                continue;
+            }
+            let (x, y) = (index % width, index / width);
+            vote_line(&mut accumulator, &angles, x, y, 1);
+            voted[index as usize] = true;
+
+            let offset = diagonal as f32;
+            let line_votes: Vec<u32> = angles
+                .iter()
+                .enumerate()
+                .map(|(i, (sin, cos))| {
+                    let rho = (x as f32 * cos + y as f32 * sin + offset).round() as u32;
+                    accumulator.get(i as u32, rho)
+                })
+                .collect();
+            let votes = line_votes.iter().cloned().max().unwrap_or(0);
+            if votes < threshold.max(1) {
+                continue;
+            }
+            // Neighbouring angles often tie, the middle one of them is the most accurate
+            let mut tied: Vec<i64> = (0..steps)
+                .filter(|&i| line_votes[i as usize] == votes)
+                .collect();
+            if tied.contains(&0) && tied.contains(&(steps - 1)) {
+                for i in tied.iter_mut().filter(|i| **i >= steps / 2) {
+                    *i -= steps;
+                }
+                tied.sort();
+            }
+            let best = tied[tied.len() / 2].rem_euclid(steps) as usize;
+
+            // Walk along the line in both directions one pixel along the major axis at a time
+            let (sin, cos) = angles[best];
+            let (dx, dy) = (-sin, cos);
+            let major = dx.abs().max(dy.abs());
+            let (dx, dy) = (dx / major, dy / major);
+            let pixel_at = |t: i64| {
+                let px = (x as f32 + dx * t as f32).round();
+                let py = (y as f32 + dy * t as f32).round();
+                if px < 0.0 || py < 0.0 || px >= width as f32 || py >= height as f32 {
+                    None
+                } else {
+                    Some(py as u32 * width + px as u32)
+                }
+            };
+            let mut ends = [0i64; 2];
+            for (end, direction) in ends.iter_mut().zip([1i64, -1]) {
+                let mut gap = 0;
+                let mut t = direction;
+                while let Some(i) = pixel_at(t) {
+                    if remaining[i as usize] {
+                        *end = t;
+                        gap = 0;
+                    } else {
+                        gap += 1;
+                        if gap > max_gap {
+                            break;
+                        }
+                    }
+                    t += direction;
+                }
+            }
+
+            // Remove the pixels of the segment so they do not vote for other lines
+            for t in ends[1]..=ends[0] {
+                if let Some(i) = pixel_at(t) {
+                    if remaining[i as usize] {
+                        remaining[i as usize] = false;
+                        if voted[i as usize] {
+                            vote_line(&mut accumulator, &angles, i % width, i / width, -1);
+                        }
+                    }
+                }
+            }
+
+            let point = |t: i64| {
+                let i = pixel_at(t).unwrap_or(index);
+                Point {
+                    x: i % width,
+                    y: i / width,
+                }
+            };
+            let segment = Segment {
+                start: point(ends[1]),
+                end: point(ends[0]),
+            };
+            if segment.length() >= min_length {
+                segments.push(segment);
+            }
+        }
+        segments
+    }
+
+    /// Sobel gradients `(gx, gy)` of the grey values, scaled so a step from black to white
+    /// has a magnitude of 255.
+    fn grey_gradients(&self) -> Vec<(f32, f32)> {
+        let dimensions = self.dimensions();
+        let (width, height) = (dimensions.width as i64, dimensions.height as i64);
+        let grey: Vec<f32> = self
+            .pixels()
+            .map(|p| to_grey_lumiosity(p).r as f32)
+            .collect();
+        let at = |x: i64, y: i64| {
+            grey[(y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize]
+        };
+        let mut gradients = Vec::with_capacity(grey.len());
+        for y in 0..height {
+            for x in 0..width {
+                let gx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                    - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+                let gy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                    - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+                gradients.push((gx / 4.0, gy / 4.0));
+            }
+        }
+        gradients
+    }
+
+    /// Votes of the Hough gradient method for circle centres, the same size as the canvas.
+    /// Every edge pixel votes along its gradient at every distance between the minimum and
+    /// maximum radius of `options`.
+    pub fn hough_circle_accumulator(&self, options: &CircleOptions) -> Accumulator {
+        let dimensions = self.dimensions();
+        let (width, height) = (dimensions.width, dimensions.height);
+        let mut accumulator = Accumulator::new(width, height);
+        for (i, (gx, gy)) in self.grey_gradients().into_iter().enumerate() {
+            let magnitude = gx.hypot(gy);
+            if magnitude < options.edge_threshold.max(f32::EPSILON) {
+                continue;
+            }
+            let (x, y) = ((i as u32 % width) as f32, (i as u32 / width) as f32);
+            let (ux, uy) = (gx / magnitude, gy / magnitude);
+            // Edges of light circles on dark backgrounds point inwards and vice versa
+            for sign in [1.0, -1.0] {
+                for r in options.min_radius..=options.max_radius {
+                    let cx = (x + sign * r as f32 * ux).round();
+                    let cy = (y + sign * r as f32 * uy).round();
+                    if cx >= 0.0 && cy >= 0.0 && cx < width as f32 && cy < height as f32 {
+                        accumulator.add(cx as u32, cy as u32, 1);
+                    }
+                }
+            }
+        }
+        accumulator
+    }
+
+    /// Finds circles with the Hough gradient method. Centres come from the peaks of
+    /// `hough_circle_accumulator` and the radius of every centre is the distance shared by the
+    /// most edge pixels around it. Circles are returned with the most votes first. Nothing is
+    /// found if the maximum radius is below the minimum.
+    pub fn hough_circles(&self, options: &CircleOptions) -> Vec<Circle> {
+        if options.max_radius < options.min_radius {
+            return vec![];
+        }
+        let width = self.dimensions().width;
+        let accumulator = self.hough_circle_accumulator(options);
+        let edges: Vec<(f32, f32)> = self
+            .grey_gradients()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (gx, gy))| gx.hypot(*gy) >= options.edge_threshold.max(f32::EPSILON))
+            .map(|(i, _)| ((i as u32 % width) as f32, (i as u32 / width) as f32))
+            .collect();
+
+        let min_squared = options.min_distance * options.min_distance;
+        let bins = (options.max_radius - options.min_radius + 1) as usize;
+        let mut circles: Vec<Circle> = vec![];
+        for (x, y, _) in accumulator.peaks(options.min_votes, false) {
+            let too_close = circles.iter().any(|c| {
+                let dx = c.center.x as f32 - x as f32;
+                let dy = c.center.y as f32 - y as f32;
+                dx * dx + dy * dy < min_squared
+            });
+            if too_close {
+                continue;
+            }
+            let mut histogram = vec![0u32; bins];
+            for (ex, ey) in edges.iter() {
+                let distance = (ex - x as f32).hypot(ey - y as f32).round() as i64;
+                let bin = distance - options.min_radius as i64;
+                if (0..bins as i64).contains(&bin) {
+                    histogram[bin as usize] += 1;
+                }
+            }
+            // The smallest radius wins ties
+            let (bin, votes) =
+                histogram.iter().enumerate().fold(
+                    (0, 0),
+                    |best, (i, &v)| if v > best.1 { (i, v) } else { best },
+                );
+            if votes >= options.min_votes {
+                circles.push(Circle {
+                    center: Point { x, y },
+                    radius: (options.min_radius as usize + bin) as f32,
+                    votes,
+                });
+            }
+        }
+        circles.sort_by_key(|c| std::cmp::Reverse(c.votes));
+        circles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::{FillRule, Path};
+    use crate::pixels::{ColorTrait, Colors};
+
+    /// A horizontal line of 30 pixels and a vertical line of 24 pixels.
+    fn edges() -> Canvas {
+        Canvas::new_with_background(40, 30, Colors::BLACK)
+            .draw_square(4, 10, 30, 1, &Colors::WHITE)
+            .draw_square(25, 2, 1, 24, &Colors::WHITE)
+    }
+
+    /// The segment with its endpoints sorted.
+    fn bounds(segment: &Segment) -> ((u32, u32), (u32, u32)) {
+        let (start, end) = (&segment.start, &segment.end);
+        (
+            (start.x.min(end.x), start.y.min(end.y)),
+            (start.x.max(end.x), start.y.max(end.y)),
+        )
+    }
+
+    #[test]
+    fn finds_horizontal_and_vertical_lines() {
+        let lines = edges().hough_lines(22);
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+        assert_eq!(lines[0].votes, 30);
+        assert!((lines[0].theta - PI / 2.0).abs() < 1e-4);
+        assert_eq!(lines[0].rho, 10.0);
+        assert_eq!(lines[1].theta, 0.0);
+        assert_eq!(lines[1].rho, 25.0);
+    }
+
+    #[test]
+    fn lines_are_clipped_to_the_canvas() {
+        let horizontal = Line {
+            rho: 10.0,
+            theta: PI / 2.0,
+            votes: 0,
+        };
+        let ((x0, y0), (x1, y1)) = horizontal.endpoints(40, 30).unwrap();
+        assert!(x0.abs() < 1e-3 && (y0 - 10.0).abs() < 1e-3);
+        assert!((x1 - 39.0).abs() < 1e-3 && (y1 - 10.0).abs() < 1e-3);
+        let diagonal = Line {
+            rho: 0.0,
+            theta: 3.0 * PI / 4.0,
+            votes: 0,
+        };
+        let ((x0, y0), (x1, y1)) = diagonal.endpoints(10, 10).unwrap();
+        assert!(x0.abs() < 1e-3 && y0.abs() < 1e-3);
+        assert!((x1 - 9.0).abs() < 1e-3 && (y1 - 9.0).abs() < 1e-3);
+        let outside = Line {
+            rho: -5.0,
+            theta: 0.0,
+            votes: 0,
+        };
+        assert_eq!(outside.endpoints(10, 10), None);
+    }
+
+    #[test]
+    fn finds_segments() {
+        let canvas = edges();
+        for seed in 0..5 {
+            let segments = canvas.probabilistic_hough_lines(10, 10.0, 2, seed);
+            let mut found: Vec<_> = segments.iter().map(bounds).collect();
+            found.sort();
+            assert_eq!(found, vec![((4, 10), (33, 10)), ((25, 2), (25, 25))]);
+        }
+    }
+
+    #[test]
+    fn gaps_split_segments() {
+        let canvas = Canvas::new_with_background(40, 10, Colors::BLACK)
+            .draw_square(0, 5, 15, 1, &Colors::WHITE)
+            .draw_square(20, 5, 15, 1, &Colors::WHITE);
+        let split = canvas.probabilistic_hough_lines(8, 5.0, 2, 1);
+        assert_eq!(split.len(), 2, "{:?}", split);
+        let joined = canvas.probabilistic_hough_lines(8, 5.0, 6, 1);
+        assert_eq!(
+            joined.iter().map(bounds).collect::<Vec<_>>(),
+            vec![((0, 5), (34, 5))]
+        );
+    }
+
+    #[test]
+    fn finds_circles() {
+        let canvas = Canvas::new_with_background(60, 50, Colors::BLACK)
+            .fill_path(
+                &Path::circle(20.5, 20.5, 9.0),
+                &Colors::WHITE,
+                FillRule::NonZero,
+            )
+            .fill_path(
+                &Path::circle(45.5, 30.5, 6.0),
+                &Colors::WHITE,
+                FillRule::NonZero,
+            );
+        let circles = canvas.hough_circles(&CircleOptions::new(4, 12));
+        assert_eq!(circles.len(), 2, "{:?}", circles);
+        let big = &circles[0];
+        assert!(big.center.x.abs_diff(20) <= 1 && big.center.y.abs_diff(20) <= 1);
+        assert!((big.radius - 9.0).abs() <= 1.0);
+        let small = &circles[1];
+        assert!(small.center.x.abs_diff(45) <= 1 && small.center.y.abs_diff(30) <= 1);
+        assert!((small.radius - 6.0).abs() <= 1.0);
+
+        let mut empty_range = CircleOptions::new(4, 12);
+        empty_range.max_radius = 2;
+        assert!(canvas.hough_circles(&empty_range).is_empty());
+    }
+
+    #[test]
+    fn accumulators_can_be_visualized() {
+        let accumulator = edges().hough_line_accumulator(90);
+        assert_eq!(
+            accumulator.dimensions(),
+            Size {
+                width: 90,
+                height: 2 * 50 + 1
+            }
+        );
+        let canvas = accumulator.to_canvas();
+        assert_eq!(canvas.get_pixel(45, 50 + 10), Colors::WHITE);
+        let empty = Canvas::new(10, 10).hough_circle_accumulator(&CircleOptions::new(2, 4));
+        assert!(empty.votes().iter().all(|&v| v == 0));
+        assert_eq!(empty.to_canvas().get_pixel(3, 3), Colors::BLACK);
+    }
+}
@@ -0,0 +1,519 @@
+use crate::canvas::{Canvas, Island};
+use crate::denoise::{area_sum, integral, Planes};
+use crate::mask::Mask;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap};
+
+/// How far around the hole `InpaintMethod::Criminisi` looks for patches, in patch sizes.
+const SEARCH_PATCHES: i64 = 4;
+
+/// How a hole is filled by `inpaint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InpaintMethod {
+    /// Telea's fast marching method. Fills the hole from its border inwards with a weighted
+    /// average of the pixels around, which is fast and smooth but blurs texture. Best for thin
+    /// regions like scratches, text and sensor dust.
+    Telea,
+    /// Criminisi's exemplar-based inpainting. Copies whole patches from the surroundings,
+    /// continuing edges first, which keeps texture. Best for larger regions.
+    Criminisi,
+}
+
+/// State of a pixel while marching into the hole.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Flag {
+    Known,
+    Band,
+    Inside,
+}
+
+/// A pixel of the narrow band ordered so the smallest distance comes out of a `BinaryHeap`
+/// first.
+struct Queued {
+    distance: f32,
+    index: usize,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .total_cmp(&self.distance)
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+/// Index of the 4-neighbours of `index` inside a `width x height` canvas.
+fn neighbours(index: usize, width: usize, height: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = (index % width, index / width);
+    [
+        (x > 0).then(|| index - 1),
+        (x + 1 < width).then(|| index + 1),
+        (y > 0).then(|| index - width),
+        (y + 1 < height).then(|| index + width),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Fills the pixels of `hole` with Telea's fast marching method. Every pixel is estimated
+/// from the known pixels within `radius`, weighted by how close they are and how well they
+/// line up with the direction the border moves in.
+fn telea(planes: &mut Planes, hole: &[bool], radius: u32) {
+    let (width, height) = (planes.width, planes.height);
+    let mut flags: Vec<Flag> = hole
+        .iter()
+        .map(|&h| if h { Flag::Inside } else { Flag::Known })
+        .collect();
+    let mut distances: Vec<f32> = hole
+        .iter()
+        .map(|&h| if h { f32::INFINITY } else { 0.0 })
+        .collect();
+    let mut band = BinaryHeap::new();
+    for index in 0..hole.len() {
+        let borders_hole = neighbours(index, width, height).any(|n| hole[n]);
+        if !hole[index] && borders_hole {
+            flags[index] = Flag::Band;
+            band.push(Queued {
+                distance: 0.0,
+                index,
+            });
+        }
+    }
+
+    let radius = radius.max(1) as i64;
+    while let Some(Queued { index, .. }) = band.pop() {
+        if flags[index] == Flag::Known {
+            continue;
+        }
+        flags[index] = Flag::Known;
+        for next in neighbours(index, width, height) {
+            if flags[next] != Flag::Inside {
+                continue;
+            }
+            distances[next] = solve_distance(&distances, &flags, next, width, height);
+            fill_pixel(planes, &flags, &distances, next, radius);
+            flags[next] = Flag::Band;
+            band.push(Queued {
+                distance: distances[next],
+                index: next,
+            });
+        }
+    }
+}
+
+/// Distance of `index` to the initial border from the distances of its neighbours, the
+/// solution of the eikonal equation `|grad T| = 1`.
+fn solve_distance(
+    distances: &[f32],
+    flags: &[Flag],
+    index: usize,
+    width: usize,
+    height: usize,
+) -> f32 {
+    let (x, y) = (index % width, index / width);
+    let reached = |i: usize| {
+        if flags[i] == Flag::Inside {
+            f32::INFINITY
+        } else {
+            distances[i]
+        }
+    };
+    let mut horizontal = f32::INFINITY;
+    if x > 0 {
+        horizontal = horizontal.min(reached(index - 1));
+    }
+    if x + 1 < width {
+        horizontal = horizontal.min(reached(index + 1));
+    }
+    let mut vertical = f32::INFINITY;
+    if y > 0 {
+        vertical = vertical.min(reached(index - width));
+    }
+    if y + 1 < height {
+        vertical = vertical.min(reached(index + width));
+    }
+    let difference = horizontal - vertical;
+    if difference.abs() < 1.0 {
+        (horizontal + vertical + (2.0 - difference * difference).sqrt()) / 2.0
+    } else {
+        horizontal.min(vertical) + 1.0
+    }
+}
+
+/// Central difference of `values` at `(x, y)` along `(dx, dy)` using only pixels that are not
+/// inside the hole, one-sided where only one of the neighbours is.
+fn difference(
+    values: &[f32],
+    flags: &[Flag],
+    width: usize,
+    height: usize,
+    (x, y): (usize, usize),
+    (dx, dy): (usize, usize),
+) -> f32 {
+    let index = y * width + x;
+    let usable = |i: usize| flags[i] != Flag::Inside;
+    let before = (x >= dx && y >= dy).then(|| index - dy * width - dx);
+    let after = (x + dx < width && y + dy < height).then(|| index + dy * width + dx);
+    match (before.filter(|&i| usable(i)), after.filter(|&i| usable(i))) {
+        (Some(b), Some(a)) => (values[a] - values[b]) / 2.0,
+        (Some(b), None) if usable(index) => values[index] - values[b],
+        (None, Some(a)) if usable(index) => values[a] - values[index],
+        _ => 0.0,
+    }
+}
+
+fn fill_pixel(planes: &mut Planes, flags: &[Flag], distances: &[f32], index: usize, radius: i64) {
+    let (width, height) = (planes.width, planes.height);
+    let (x, y) = (index % width, index / width);
+    let normal_x = difference(distances, flags, width, height, (x, y), (1, 0));
+    let normal_y = difference(distances, flags, width, height, (x, y), (0, 1));
+
+    let mut sums = [0f32; 4];
+    let mut total = 0f32;
+    for qy in (y as i64 - radius).max(0)..=(y as i64 + radius).min(height as i64 - 1) {
+        for qx in (x as i64 - radius).max(0)..=(x as i64 + radius).min(width as i64 - 1) {
+            let (rx, ry) = (x as f32 - qx as f32, y as f32 - qy as f32);
+            let length_squared = rx * rx + ry * ry;
+            let (qx, qy) = (qx as usize, qy as usize);
+            let q = qy * width + qx;
+            if length_squared > (radius * radius) as f32 || flags[q] == Flag::Inside || q == index {
+                continue;
+            }
+            let direction = ((rx * normal_x + ry * normal_y) / length_squared.sqrt()).abs();
+            let level = 1.0 / (1.0 + (distances[q] - distances[index]).abs());
+            let weight = (direction + 1e-6) * level / length_squared;
+            for (sum, channel) in sums.iter_mut().zip(planes.channels.iter()) {
+                // First order estimate from the gradient at the known pixel
+                let gradient_x = difference(channel, flags, width, height, (qx, qy), (1, 0));
+                let gradient_y = difference(channel, flags, width, height, (qx, qy), (0, 1));
+                *sum += weight * (channel[q] + gradient_x * rx + gradient_y * ry);
+            }
+            total += weight;
+        }
+    }
+    if total > 0.0 {
+        for (channel, sum) in planes.channels.iter_mut().zip(sums) {
+            channel[index] = (sum / total).clamp(0.0, 255.0);
+        }
+    }
+}
+
+/// Fills the pixels of `hole` with Criminisi's exemplar-based method. Repeatedly picks the
+/// border pixel with the most known surroundings and the strongest edge running into the
+/// hole, and copies the most similar fully known patch of `2 * radius + 1` pixels over the
+/// missing part of its patch.
+fn criminisi(planes: &mut Planes, hole: &[bool], radius: u32) {
+    let (width, height) = (planes.width as i64, planes.height as i64);
+    let r = radius.max(1) as i64;
+    let size = 2 * r + 1;
+
+    // Source patches come from around the hole and may not contain any of it
+    let holes: Vec<f32> = hole.iter().map(|&h| if h { 1.0 } else { 0.0 }).collect();
+    let table = integral(&holes, width as usize, height as usize);
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, -1, -1);
+    for (i, _) in hole.iter().enumerate().filter(|(_, &h)| h) {
+        let (x, y) = (i as i64 % width, i as i64 / width);
+        (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+    }
+    let margin = SEARCH_PATCHES * size;
+    let mut candidates = vec![];
+    for y in (min_y - margin).max(r)..=(max_y + margin).min(height - 1 - r) {
+        for x in (min_x - margin).max(r)..=(max_x + margin).min(width - 1 - r) {
+            let (x0, y0, x1, y1) = (
+                (x - r) as usize,
+                (y - r) as usize,
+                (x + r) as usize,
+                (y + r) as usize,
+            );
+            if area_sum(&table, width as usize, x0, y0, x1, y1) == 0.0 {
+                candidates.push((x, y));
+            }
+        }
+    }
+    if candidates.is_empty() {
+        telea(planes, hole, radius);
+        return;
+    }
+
+    let mut missing = hole.to_vec();
+    let mut confidence: Vec<f32> = hole.iter().map(|&h| if h { 0.0 } else { 1.0 }).collect();
+    let at = |x: i64, y: i64| (y * width + x) as usize;
+    let inside = |x: i64, y: i64| x >= 0 && y >= 0 && x < width && y < height;
+
+    // Priorities only look at the patches around the hole and the gradients right next to them
+    let (box_x, box_y) = ((min_x - r - 1).max(0), (min_y - r - 1).max(0));
+    let box_width = (max_x + r + 1).min(width - 1) - box_x + 1;
+    let box_height = (max_y + r + 1).min(height - 1) - box_y + 1;
+    let in_box = |x: i64, y: i64| ((y - box_y) * box_width + x - box_x) as usize;
+    let luma = |planes: &Planes, i: usize| {
+        0.299 * planes.channels[0][i]
+            + 0.587 * planes.channels[1][i]
+            + 0.114 * planes.channels[2][i]
+    };
+    let mut grey: Vec<f32> = (0..box_width * box_height)
+        .map(|i| luma(planes, at(box_x + i % box_width, box_y + i / box_width)))
+        .collect();
+
+    // Missing pixels next to a known one, in reading order so ties go to the first
+    let mut front = BTreeSet::new();
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let i = at(x, y);
+            if missing[i] && neighbours(i, width as usize, height as usize).any(|n| !missing[n]) {
+                front.insert(i);
+            }
+        }
+    }
+
+    loop {
+        // The border pixel with the highest priority
+        let mut target: Option<(i64, i64, f32, f32)> = None;
+        for &i in front.iter() {
+            let (x, y) = (i as i64 % width, i as i64 / width);
+            let mut patch_confidence = 0.0;
+            let mut isophote = (0.0f32, 0.0f32);
+            for py in y - r..=y + r {
+                for px in x - r..=x + r {
+                    if !inside(px, py) || missing[at(px, py)] {
+                        continue;
+                    }
+                    patch_confidence += confidence[at(px, py)];
+                    let known = |x: i64, y: i64| inside(x, y) && !missing[at(x, y)];
+                    if known(px - 1, py)
+                        && known(px + 1, py)
+                        && known(px, py - 1)
+                        && known(px, py + 1)
+                    {
+                        let gx = (grey[in_box(px + 1, py)] - grey[in_box(px - 1, py)]) / 2.0;
+                        let gy = (grey[in_box(px, py + 1)] - grey[in_box(px, py - 1)]) / 2.0;
+                        if gx.hypot(gy) > isophote.0.hypot(isophote.1) {
+                            isophote = (-gy, gx);
+                        }
+                    }
+                }
+            }
+            let patch_confidence = patch_confidence / (size * size) as f32;
+            let filled = |x: i64, y: i64| {
+                let (x, y) = (x.clamp(0, width - 1), y.clamp(0, height - 1));
+                if missing[at(x, y)] {
+                    1.0f32
+                } else {
+                    0.0
+                }
+            };
+            let (nx, ny) = (
+                filled(x + 1, y) - filled(x - 1, y),
+                filled(x, y + 1) - filled(x, y - 1),
+            );
+            let length = nx.hypot(ny);
+            let data = if length > 0.0 {
+                (isophote.0 * nx + isophote.1 * ny).abs() / (length * 255.0)
+            } else {
+                0.0
+            };
+            let priority = patch_confidence * (data + 1e-3);
+            if target.is_none_or(|t| priority > t.2) {
+                target = Some((x, y, priority, patch_confidence));
+            }
+        }
+        let Some((x, y, _, patch_confidence)) = target else {
+            break;
+        };
+
+        // The most similar source patch over the known part of the target patch
+        let mut best = candidates[0];
+        let mut best_distance = f32::INFINITY;
+        for &(sx, sy) in candidates.iter() {
+            let mut distance = 0.0;
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let (tx, ty) = (x + dx, y + dy);
+                    if !inside(tx, ty) || missing[at(tx, ty)] {
+                        continue;
+                    }
+                    let (t, s) = (at(tx, ty), at(sx + dx, sy + dy));
+                    for channel in planes.channels.iter() {
+                        distance += (channel[t] - channel[s]) * (channel[t] - channel[s]);
+                    }
+                }
+                if distance >= best_distance {
+                    break;
+                }
+            }
+            if distance < best_distance {
+                best = (sx, sy);
+                best_distance = distance;
+            }
+        }
+
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let (tx, ty) = (x + dx, y + dy);
+                if !inside(tx, ty) || !missing[at(tx, ty)] {
+                    continue;
+                }
+                let (t, s) = (at(tx, ty), at(best.0 + dx, best.1 + dy));
+                for channel in planes.channels.iter_mut() {
+                    channel[t] = channel[s];
+                }
+                grey[in_box(tx, ty)] = luma(planes, t);
+                missing[t] = false;
+                confidence[t] = patch_confidence;
+                front.remove(&t);
+                for n in neighbours(t, width as usize, height as usize) {
+                    if missing[n] {
+                        front.insert(n);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Canvas {
+    /// Fills the pixels set in `mask` from their surroundings with `method`. For
+    /// `InpaintMethod::Telea` `radius` is how far around a pixel is averaged, for
+    /// `InpaintMethod::Criminisi` it is the radius of the copied patches. Partially set pixels
+    /// of a feathered mask are mixed with the original. A fully masked canvas has nothing to
+    /// fill from and is left unchanged.
+    pub fn inpaint_mut(&mut self, mask: &Mask, method: InpaintMethod, radius: u32) {
+        let dimensions = self.dimensions();
+        let hole: Vec<bool> = (0..dimensions.height)
+            .flat_map(|y| (0..dimensions.width).map(move |x| (x, y)))
+            .map(|(x, y)| mask.get(x, y) > 0.0)
+            .collect();
+        if !hole.contains(&true) {
+            return;
+        }
+        let mut planes = Planes::from_canvas(self);
+        match method {
+            InpaintMethod::Telea => telea(&mut planes, &hole, radius),
+            InpaintMethod::Criminisi => criminisi(&mut planes, &hole, radius),
+        }
+        *self = self.merge_masked(&planes.to_canvas(), mask);
+    }
+
+    pub fn inpaint(mut self, mask: &Mask, method: InpaintMethod, radius: u32) -> Canvas {
+        self.inpaint_mut(mask, method, radius);
+        self
+    }
+
+    /// Fills the points of `island`, such as a speck of dust found with `find_islands`, from
+    /// their surroundings. See `inpaint`.
+    pub fn inpaint_island_mut(&mut self, island: &Island, method: InpaintMethod, radius: u32) {
+        let dimensions = self.dimensions();
+        let mask = Mask::from_island(dimensions.width, dimensions.height, island);
+        self.inpaint_mut(&mask, method, radius);
+    }
+
+    pub fn inpaint_island(mut self, island: &Island, method: InpaintMethod, radius: u32) -> Canvas {
+        self.inpaint_island_mut(island, method, radius);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors, Pixel};
+
+    fn square_mask(width: u32, height: u32, x: u32, y: u32, size: u32) -> Mask {
+        let mut mask = Mask::new(width, height);
+        for my in y..y + size {
+            for mx in x..x + size {
+                mask.set(mx, my, 1.0);
+            }
+        }
+        mask
+    }
+
+    #[test]
+    fn telea_fills_flat_regions() {
+        let color = Pixel::new(30, 120, 200, 255);
+        let canvas = Canvas::new_with_background(30, 30, color.clone()).draw_square(
+            10,
+            12,
+            6,
+            4,
+            &Colors::RED,
+        );
+        let filled = canvas.inpaint(&square_mask(30, 30, 9, 11, 8), InpaintMethod::Telea, 3);
+        assert!(filled.pixels().all(|p| *p == color));
+    }
+
+    #[test]
+    fn telea_continues_gradients() {
+        let pixels = (0..40 * 20)
+            .map(|i| {
+                let value = ((i % 40) * 6) as u8;
+                Pixel::new(value, value, value, 255)
+            })
+            .collect();
+        let canvas = Canvas::new_with_data(40, 20, pixels);
+        let filled =
+            canvas
+                .clone()
+                .inpaint(&square_mask(40, 20, 15, 5, 8), InpaintMethod::Telea, 4);
+        for y in 5..13 {
+            for x in 15..23 {
+                let expected = canvas.get_pixel(x, y).r as i32;
+                assert!((filled.get_pixel(x, y).r as i32 - expected).abs() <= 6);
+            }
+        }
+    }
+
+    #[test]
+    fn criminisi_restores_texture() {
+        let pixels = (0..40 * 40)
+            .map(|i| {
+                if (i % 40) / 2 % 2 == 0 {
+                    Colors::BLACK
+                } else {
+                    Colors::WHITE
+                }
+            })
+            .collect();
+        let stripes = Canvas::new_with_data(40, 40, pixels);
+        let damaged = stripes.clone().draw_square(17, 17, 6, 6, &Colors::RED);
+        let mask = square_mask(40, 40, 17, 17, 6);
+        assert_eq!(
+            damaged.clone().inpaint(&mask, InpaintMethod::Criminisi, 3),
+            stripes
+        );
+        assert_ne!(damaged.inpaint(&mask, InpaintMethod::Telea, 3), stripes);
+    }
+
+    #[test]
+    fn inpaints_islands() {
+        let canvas = Canvas::new_with_background(25, 25, Colors::BLUE)
+            .draw_square(5, 5, 3, 2, &Colors::RED)
+            .draw_square(15, 18, 2, 2, &Colors::RED);
+        let islands = canvas.find_islands(&Colors::RED);
+        assert_eq!(islands.len(), 2);
+        let mut cleaned = canvas;
+        for island in islands.iter() {
+            cleaned.inpaint_island_mut(island, InpaintMethod::Criminisi, 2);
+        }
+        assert!(cleaned.pixels().all(|p| *p == Colors::BLUE));
+        let red = Canvas::new_with_background(6, 6, Colors::RED);
+        let everything = Mask::new_with_value(6, 6, 1.0);
+        for method in [InpaintMethod::Telea, InpaintMethod::Criminisi] {
+            assert_eq!(red.clone().inpaint(&everything, method, 2), red);
+        }
+    }
+}
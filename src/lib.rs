@@ -1,3 +1,26 @@
+pub mod adjustments;
+pub mod blend;
+pub mod blur;
 pub mod canvas;
+pub mod colorspace;
+pub mod denoise;
+pub mod document;
+pub mod equalization;
+pub mod features;
+pub mod gradient;
+pub mod hough;
+pub mod inpaint;
+pub mod lut;
+pub mod mask;
+pub mod matching;
+pub mod metrics;
+pub mod noise;
+pub mod path;
 pub mod pixels;
+pub mod pyramid;
+pub mod seam;
+pub mod sharpen;
+pub mod statistics;
+pub mod testing;
+pub mod text;
 pub mod utility;
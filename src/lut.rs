@@ -0,0 +1,564 @@
+use crate::adjustments::Curve;
+use crate::canvas::{Canvas, ImageError};
+use crate::pixels::Pixel;
+use std::fs;
+use std::path::Path;
+
+/// Something that maps one color to another.
+pub trait LookupTable {
+    /// Returns the color `p` is mapped to. Alpha is kept.
+    fn lookup(&self, p: &Pixel) -> Pixel;
+}
+
+/// A lookup table with 256 entries for each color channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut1D {
+    pub red: [u8; 256],
+    pub green: [u8; 256],
+    pub blue: [u8; 256],
+}
+
+impl Lut1D {
+    /// Creates a table from the entries of each channel
+    pub fn new(red: [u8; 256], green: [u8; 256], blue: [u8; 256]) -> Lut1D {
+        Lut1D { red, green, blue }
+    }
+
+    /// Creates a table that maps every value to itself
+    pub fn identity() -> Lut1D {
+        let mut table = [0u8; 256];
+        for (value, entry) in table.iter_mut().enumerate() {
+            *entry = value as u8;
+        }
+        Lut1D::new(table, table, table)
+    }
+
+    /// Creates a table that maps every channel through `curve`
+    pub fn from_curve(curve: &Curve) -> Lut1D {
+        let table = curve.to_table();
+        Lut1D::new(table, table, table)
+    }
+}
+
+impl LookupTable for Lut1D {
+    fn lookup(&self, p: &Pixel) -> Pixel {
+        Pixel::new(
+            self.red[p.r as usize],
+            self.green[p.g as usize],
+            self.blue[p.b as usize],
+            p.a,
+        )
+    }
+}
+
+/// How a `Lut3D` is sampled between its grid points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LutInterpolation {
+    Trilinear,
+    /// Interpolates within one of the six tetrahedra of a grid cell. Preserves the neutral axis.
+    Tetrahedral,
+}
+
+/// Largest number of points per axis a 3D table may have.
+pub const MAX_3D_SIZE: usize = 256;
+
+/// A three dimensional lookup table. Holds `size * size * size` colors with red changing
+/// fastest, as in `.cube` files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut3D {
+    size: usize,
+    data: Vec<[f32; 3]>,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    interpolation: LutInterpolation,
+}
+
+impl Lut3D {
+    /// Creates a table of `size` points per axis from normalized colors in `data`. The size
+    /// must be between `2` and `MAX_3D_SIZE`.
+    pub fn new(size: usize, data: Vec<[f32; 3]>) -> Result<Lut3D, ImageError> {
+        if !(2..=MAX_3D_SIZE).contains(&size) {
+            return Err(ImageError::Parameter(format!(
+                "3D LUT size must be between 2 and {}, got {}",
+                MAX_3D_SIZE, size
+            )));
+        }
+        if data.len() != size * size * size {
+            return Err(ImageError::Parameter(format!(
+                "3D LUT of size {} needs {} entries, got {}",
+                size,
+                size * size * size,
+                data.len()
+            )));
+        }
+        Ok(Lut3D {
+            size,
+            data,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            interpolation: LutInterpolation::Trilinear,
+        })
+    }
+
+    /// Creates a table of `size` points per axis that maps every color to itself. The size is
+    /// clamped to `2..=MAX_3D_SIZE`.
+    pub fn identity(size: usize) -> Lut3D {
+        let size = size.clamp(2, MAX_3D_SIZE);
+        let scale = (size - 1) as f32;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([r as f32 / scale, g as f32 / scale, b as f32 / scale]);
+                }
+            }
+        }
+        Lut3D {
+            size,
+            data,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            interpolation: LutInterpolation::Trilinear,
+        }
+    }
+
+    /// Sets how the table is sampled between grid points
+    pub fn set_interpolation(mut self, interpolation: LutInterpolation) -> Lut3D {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Sets the input range covered by the table. Defaults to `0.0..=1.0` for every channel.
+    pub fn set_domain(mut self, min: [f32; 3], max: [f32; 3]) -> Lut3D {
+        self.domain_min = min;
+        self.domain_max = max;
+        self
+    }
+
+    /// Returns the number of grid points per axis
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the colors of the table with red changing fastest
+    pub fn data(&self) -> &[[f32; 3]] {
+        &self.data
+    }
+
+    fn at(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.data[(b * self.size + g) * self.size + r]
+    }
+
+    /// Samples the table at normalized color `(r, g, b)`
+    pub fn sample(&self, r: f32, g: f32, b: f32) -> [f32; 3] {
+        let scale = (self.size - 1) as f32;
+        let position = |value: f32, channel: usize| {
+            let range = self.domain_max[channel] - self.domain_min[channel];
+            let t = if range > 0.0 {
+                (value - self.domain_min[channel]) / range
+            } else {
+                0.0
+            };
+            let t = t.clamp(0.0, 1.0) * scale;
+            let low = (t.floor() as usize).min(self.size - 2);
+            (low, t - low as f32)
+        };
+        let (r0, fr) = position(r, 0);
+        let (g0, fg) = position(g, 1);
+        let (b0, fb) = position(b, 2);
+        let corner = |dr: usize, dg: usize, db: usize| self.at(r0 + dr, g0 + dg, b0 + db);
+        let mix = |weights: &[(f32, [f32; 3])]| {
+            let mut out = [0.0; 3];
+            for (weight, color) in weights {
+                for channel in 0..3 {
+                    out[channel] += weight * color[channel];
+                }
+            }
+            out
+        };
+
+        match self.interpolation {
+            LutInterpolation::Trilinear => {
+                let mut weights = Vec::with_capacity(8);
+                for (db, wb) in [(0, 1.0 - fb), (1, fb)] {
+                    for (dg, wg) in [(0, 1.0 - fg), (1, fg)] {
+                        for (dr, wr) in [(0, 1.0 - fr), (1, fr)] {
+                            weights.push((wr * wg * wb, corner(dr, dg, db)));
+                        }
+                    }
+                }
+                mix(&weights)
+            }
+            LutInterpolation::Tetrahedral => {
+                let c000 = corner(0, 0, 0);
+                let c111 = corner(1, 1, 1);
+                let weights = if fr > fg {
+                    if fg > fb {
+                        [
+                            (1.0 - fr, c000),
+                            (fr - fg, corner(1, 0, 0)),
+                            (fg - fb, corner(1, 1, 0)),
+                            (fb, c111),
+                        ]
+                    } else if fr > fb {
+                        [
+                            (1.0 - fr, c000),
+                            (fr - fb, corner(1, 0, 0)),
+                            (fb - fg, corner(1, 0, 1)),
+                            (fg, c111),
+                        ]
+                    } else {
+                        [
+                            (1.0 - fb, c000),
+                            (fb - fr, corner(0, 0, 1)),
+                            (fr - fg, corner(1, 0, 1)),
+                            (fg, c111),
+                        ]
+                    }
+                } else if fb > fg {
+                    [
+                        (1.0 - fb, c000),
+                        (fb - fg, corner(0, 0, 1)),
+                        (fg - fr, corner(0, 1, 1)),
+                        (fr, c111),
+                    ]
+                } else if fb > fr {
+                    [
+                        (1.0 - fg, c000),
+                        (fg - fb, corner(0, 1, 0)),
+                        (fb - fr, corner(0, 1, 1)),
+                        (fr, c111),
+                    ]
+                } else {
+                    [
+                        (1.0 - fg, c000),
+                        (fg - fr, corner(0, 1, 0)),
+                        (fr - fb, corner(1, 1, 0)),
+                        (fb, c111),
+                    ]
+                };
+                mix(&weights)
+            }
+        }
+    }
+
+    /// Loads a 3D LUT from an Adobe/Resolve `.cube` file
+    pub fn load_cube(filename: &Path) -> Result<Lut3D, ImageError> {
+        let content =
+            fs::read_to_string(filename).map_err(|e| ImageError::IoError(e.to_string()))?;
+        Lut3D::parse_cube(&content)
+    }
+
+    /// Parses a 3D LUT in the Adobe/Resolve `.cube` format. Use `parse_cube` to also accept
+    /// files holding a 1D LUT.
+    pub fn parse_cube(content: &str) -> Result<Lut3D, ImageError> {
+        match parse_cube(content)? {
+            CubeLut::ThreeD(lut) => Ok(lut),
+            CubeLut::OneD(_) => Err(ImageError::Unsupported(
+                "Expected a 3D LUT but the .cube file holds a 1D LUT".to_string(),
+            )),
+        }
+    }
+
+    /// Writes the table in the `.cube` format
+    pub fn to_cube(&self, title: &str) -> String {
+        let mut out = format!("TITLE \"{}\"\nLUT_3D_SIZE {}\n", title, self.size);
+        if self.domain_min != [0.0; 3] || self.domain_max != [1.0; 3] {
+            let [r, g, b] = self.domain_min;
+            out.push_str(&format!("DOMAIN_MIN {} {} {}\n", r, g, b));
+            let [r, g, b] = self.domain_max;
+            out.push_str(&format!("DOMAIN_MAX {} {} {}\n", r, g, b));
+        }
+        for [r, g, b] in &self.data {
+            out.push_str(&format!("{:.6} {:.6} {:.6}\n", r, g, b));
+        }
+        out
+    }
+
+    /// Saves the table as a `.cube` file
+    pub fn save_cube(&self, filename: &Path, title: &str) -> Result<(), ImageError> {
+        fs::write(filename, self.to_cube(title)).map_err(|e| ImageError::IoError(e.to_string()))
+    }
+
+    /// Reads a Hald CLUT image. A Hald image of level `L` is `L^3 x L^3` pixels and holds a
+    /// table of `L^2` points per axis.
+    pub fn from_hald(canvas: &Canvas) -> Result<Lut3D, ImageError> {
+        let dimensions = canvas.dimensions();
+        let level = (dimensions.width as f64).cbrt().round() as u32;
+        if dimensions.width != dimensions.height || level.pow(3) != dimensions.width || level < 2 {
+            return Err(ImageError::Parameter(format!(
+                "A Hald CLUT must be a square of a cubed size, got {}x{}",
+                dimensions.width, dimensions.height
+            )));
+        }
+        let data = canvas
+            .pixels()
+            .map(|p| {
+                let (r, g, b, _) = p.normalize();
+                [r, g, b]
+            })
+            .collect();
+        Lut3D::new((level * level) as usize, data)
+    }
+
+    /// Renders the table as a Hald CLUT image. Fails unless the size is a square number.
+    pub fn to_hald(&self) -> Result<Canvas, ImageError> {
+        let level = (self.size as f64).sqrt().round() as u32;
+        if (level * level) as usize != self.size {
+            return Err(ImageError::Parameter(format!(
+                "Only LUTs with a square size can be written as a Hald CLUT, got {}",
+                self.size
+            )));
+        }
+        let side = level.pow(3);
+        let pixels = self
+            .data
+            .iter()
+            .map(|[r, g, b]| {
+                Pixel::from(
+                    (r * 255.0).round(),
+                    (g * 255.0).round(),
+                    (b * 255.0).round(),
+                    255.0,
+                )
+            })
+            .collect();
+        Ok(Canvas::new_with_data(side, side, pixels))
+    }
+}
+
+impl LookupTable for Lut3D {
+    fn lookup(&self, p: &Pixel) -> Pixel {
+        let (r, g, b, _) = p.normalize();
+        let [r, g, b] = self.sample(r, g, b);
+        Pixel::from(
+            (r * 255.0).round(),
+            (g * 255.0).round(),
+            (b * 255.0).round(),
+            p.a as f32,
+        )
+    }
+}
+
+/// The table held by a `.cube` file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CubeLut {
+    OneD(Box<Lut1D>),
+    ThreeD(Lut3D),
+}
+
+impl LookupTable for CubeLut {
+    fn lookup(&self, p: &Pixel) -> Pixel {
+        match self {
+            CubeLut::OneD(lut) => lut.lookup(p),
+            CubeLut::ThreeD(lut) => lut.lookup(p),
+        }
+    }
+}
+
+/// Parses a 1D or 3D LUT in the Adobe/Resolve `.cube` format
+pub fn parse_cube(content: &str) -> Result<CubeLut, ImageError> {
+    let invalid =
+        |message: String| ImageError::Decoding(format!("Invalid .cube file: {}", message));
+    let floats = |values: &[&str]| -> Result<[f32; 3], ImageError> {
+        let mut out = [0.0; 3];
+        for (slot, value) in out.iter_mut().zip(values) {
+            *slot = value
+                .parse::<f32>()
+                .map_err(|_| invalid(format!("not a number: {}", value)))?;
+        }
+        Ok(out)
+    };
+
+    let mut size_3d: Option<usize> = None;
+    let mut size_1d: Option<usize> = None;
+    let mut domain_min = [0.0; 3];
+    let mut domain_max = [1.0; 3];
+    let mut data = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts[0] {
+            "TITLE" => {}
+            "LUT_3D_SIZE" | "LUT_1D_SIZE" if parts.len() == 2 => {
+                let size = parts[1]
+                    .parse::<usize>()
+                    .map_err(|_| invalid(format!("invalid size: {}", parts[1])))?;
+                if parts[0] == "LUT_3D_SIZE" {
+                    if size > MAX_3D_SIZE {
+                        return Err(ImageError::Parameter(format!(
+                            "3D LUT size must be at most {}, got {}",
+                            MAX_3D_SIZE, size
+                        )));
+                    }
+                    size_3d = Some(size);
+                } else {
+                    size_1d = Some(size);
+                }
+            }
+            "DOMAIN_MIN" if parts.len() == 4 => domain_min = floats(&parts[1..])?,
+            "DOMAIN_MAX" if parts.len() == 4 => domain_max = floats(&parts[1..])?,
+            "LUT_3D_INPUT_RANGE" | "LUT_1D_INPUT_RANGE" if parts.len() == 3 => {
+                let [min, max, _] = floats(&parts[1..])?;
+                domain_min = [min; 3];
+                domain_max = [max; 3];
+            }
+            _ if parts.len() == 3 => data.push(floats(&parts)?),
+            _ => return Err(invalid(format!("unexpected line: {}", line))),
+        }
+    }
+
+    match (size_3d, size_1d) {
+        (Some(size), None) => Ok(CubeLut::ThreeD(
+            Lut3D::new(size, data)?.set_domain(domain_min, domain_max),
+        )),
+        (None, Some(size)) => {
+            if size < 2 || data.len() != size {
+                return Err(invalid(format!(
+                    "1D LUT of size {} has {} entries",
+                    size,
+                    data.len()
+                )));
+            }
+            let mut tables = [[0u8; 256]; 3];
+            for (channel, table) in tables.iter_mut().enumerate() {
+                let range = domain_max[channel] - domain_min[channel];
+                for (value, entry) in table.iter_mut().enumerate() {
+                    let t = if range > 0.0 {
+                        ((value as f32 / 255.0 - domain_min[channel]) / range).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    let t = t * (size - 1) as f32;
+                    let low = (t.floor() as usize).min(size - 2);
+                    let f = t - low as f32;
+                    let mapped = data[low][channel] * (1.0 - f) + data[low + 1][channel] * f;
+                    *entry = (mapped * 255.0).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+            Ok(CubeLut::OneD(Box::new(Lut1D::new(
+                tables[0], tables[1], tables[2],
+            ))))
+        }
+        _ => Err(invalid(
+            "expected exactly one of LUT_1D_SIZE and LUT_3D_SIZE".to_string(),
+        )),
+    }
+}
+
+/// Creates the identity Hald CLUT image of `level`. It is `level^3 x level^3` pixels. The level
+/// is clamped to `2..=16` so the table stays within `MAX_3D_SIZE`.
+pub fn hald_identity(level: u32) -> Canvas {
+    let level = level.clamp(2, 16);
+    let size = (level * level) as usize;
+    Lut3D::identity(size)
+        .to_hald()
+        .unwrap_or_else(|_| Canvas::new(0, 0))
+}
+
+impl Canvas {
+    /// Maps every pixel through `lut`
+    pub fn apply_lut_mut<L: LookupTable>(&mut self, lut: &L) {
+        let dimensions = self.dimensions();
+        let pixels = self.pixels().map(|p| lut.lookup(p)).collect();
+        *self = Canvas::new_with_data(dimensions.width, dimensions.height, pixels);
+    }
+
+    /// Maps every pixel through `lut`
+    pub fn apply_lut<L: LookupTable>(mut self, lut: &L) -> Canvas {
+        self.apply_lut_mut(lut);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors() -> Canvas {
+        let pixels = (0..64u32)
+            .map(|i| {
+                Pixel::new(
+                    (i * 4) as u8,
+                    (255 - i * 3) as u8,
+                    (i * 37 % 256) as u8,
+                    200,
+                )
+            })
+            .collect();
+        Canvas::new_with_data(8, 8, pixels)
+    }
+
+    #[test]
+    fn identity_luts_keep_colors() {
+        let canvas = colors();
+        assert_eq!(canvas.clone().apply_lut(&Lut1D::identity()), canvas);
+        for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral] {
+            let lut = Lut3D::identity(17).set_interpolation(interpolation);
+            assert_eq!(canvas.clone().apply_lut(&lut), canvas);
+        }
+    }
+
+    #[test]
+    fn interpolation_between_grid_points() {
+        // Inverting LUT with only the corners
+        let data = Lut3D::identity(2)
+            .data()
+            .iter()
+            .map(|[r, g, b]| [1.0 - r, 1.0 - g, 1.0 - b])
+            .collect();
+        let lut = Lut3D::new(2, data).unwrap();
+        let p = Pixel::new(10, 100, 200, 255);
+        assert_eq!(lut.lookup(&p), Pixel::new(245, 155, 55, 255));
+        let tetrahedral = lut.set_interpolation(LutInterpolation::Tetrahedral);
+        assert_eq!(tetrahedral.lookup(&p), Pixel::new(245, 155, 55, 255));
+        assert!(Lut3D::new(2, vec![[0.0; 3]; 7]).is_err());
+    }
+
+    #[test]
+    fn parse_cube_files() {
+        let content = "# comment\nTITLE \"invert\"\nLUT_3D_SIZE 2\n\
+            1 1 1\n0 1 1\n1 0 1\n0 0 1\n1 1 0\n0 1 0\n1 0 0\n0 0 0\n";
+        let lut = Lut3D::parse_cube(content).unwrap();
+        assert_eq!(lut.size(), 2);
+        assert_eq!(
+            lut.lookup(&Pixel::new(0, 255, 0, 9)),
+            Pixel::new(255, 0, 255, 9)
+        );
+        assert_eq!(Lut3D::parse_cube(&lut.to_cube("invert")).unwrap(), lut);
+
+        let one_d = "LUT_1D_SIZE 3\n0 0 0\n0.25 0.5 1\n1 1 1\n";
+        match parse_cube(one_d).unwrap() {
+            CubeLut::OneD(lut) => {
+                assert_eq!(lut.red[255], 255);
+                assert_eq!(lut.blue[128], 255);
+                assert_eq!(lut.green[0], 0);
+            }
+            CubeLut::ThreeD(_) => panic!("expected a 1D LUT"),
+        }
+        assert!(Lut3D::parse_cube(one_d).is_err());
+        assert!(parse_cube("LUT_3D_SIZE 2\n0 0\n").is_err());
+        assert!(parse_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(matches!(
+            parse_cube("LUT_3D_SIZE 100000000\n"),
+            Err(ImageError::Parameter(_))
+        ));
+        assert!(Lut3D::new(3_000_000, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn hald_round_trip() {
+        let hald = hald_identity(4);
+        assert_eq!(hald.dimensions().width, 64);
+        let lut = Lut3D::from_hald(&hald).unwrap();
+        assert_eq!(lut.size(), 16);
+        let canvas = colors();
+        assert_eq!(canvas.clone().apply_lut(&lut), canvas);
+        assert_eq!(lut.to_hald().unwrap(), hald);
+        assert!(Lut3D::from_hald(&Canvas::new(10, 10)).is_err());
+        assert!(Lut3D::identity(5).to_hald().is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use crate::blend::{blend_pixels, BlendMode};
+use crate::blur::{gaussian_values, EdgeMode};
+use crate::canvas::{Canvas, Island, Size};
+use crate::path::{FillRule, Path, Stroke};
+use crate::pixels::Pixel;
+use crate::utility::{from_grey, to_grey_lumiosity};
+
+/// A single channel coverage map. Every position holds a value between `0.0` (not set) and
+/// `1.0` (fully set). Masks restrict operations on a `Canvas` to parts of the image.
+///
+/// # Examples
+///
+/// ```
+/// use pixtra::canvas::Canvas;
+/// use pixtra::mask::Mask;
+/// use pixtra::path::{FillRule, Path};
+///
+/// let canvas = Canvas::new(40, 40);
+/// let mask = Mask::from_path(40, 40, &Path::circle(20.0, 20.0, 10.0), FillRule::NonZero).feather(2.0);
+/// let grey_circle = canvas.to_grey_masked(&mask);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    values: Vec<f32>,
+    width: u32,
+    height: u32,
+}
+
+impl Mask {
+    /// Creates a new `Mask` of size `width` and `height` where nothing is set.
+    pub fn new(width: u32, height: u32) -> Mask {
+        Mask::new_with_value(width, height, 0.0)
+    }
+
+    /// Creates a new `Mask` of size `width` and `height` with every position set to `value`.
+    pub fn new_with_value(width: u32, height: u32, value: f32) -> Mask {
+        let width = width.max(1);
+        let height = height.max(1);
+        Mask {
+            values: vec![value.clamp(0.0, 1.0); (width * height) as usize],
+            width,
+            height,
+        }
+    }
+
+    /// Creates a `Mask` from coverage values given row by row. Values are clamped to
+    /// `0.0..=1.0`. Missing values are treated as not set.
+    pub fn new_with_data(width: u32, height: u32, data: Vec<f32>) -> Mask {
+        let mut mask = Mask::new(width, height);
+        for (value, data) in mask.values.iter_mut().zip(data) {
+            *value = data.clamp(0.0, 1.0);
+        }
+        mask
+    }
+
+    /// Creates a mask from the grey values of `canvas` where white is set and black is unset.
+    /// This is the inverse of `Mask::to_canvas`.
+    pub fn from_canvas(canvas: &Canvas) -> Mask {
+        let dimensions = canvas.dimensions();
+        let values = canvas
+            .pixels()
+            .map(|p| (p.r as f32 + p.g as f32 + p.b as f32) / (3.0 * 255.0))
+            .collect();
+        Mask::new_with_data(dimensions.width, dimensions.height, values)
+    }
+
+    /// Creates a mask of size `width x height` where all points of `island` are set.
+    pub fn from_island(width: u32, height: u32, island: &Island) -> Mask {
+        let mut mask = Mask::new(width, height);
+        for point in island.points.iter() {
+            mask.set(point.x, point.y, 1.0);
+        }
+        mask
+    }
+
+    /// Creates a mask where every pixel of `canvas` for which `predicate` holds is set. Uses the
+    /// same predicate as `Canvas::find_with_predicate`.
+    pub fn from_predicate(canvas: &Canvas, predicate: fn(&Pixel, u32, u32) -> bool) -> Mask {
+        let dimensions = canvas.dimensions();
+        let mut mask = Mask::new(dimensions.width, dimensions.height);
+        for p in canvas.find_with_predicate(predicate) {
+            mask.set(p.coordinate.x, p.coordinate.y, 1.0);
+        }
+        mask
+    }
+
+    /// Creates a mask where every pixel of `canvas` with a grey value of at least `threshold`
+    /// is set.
+    pub fn from_threshold(canvas: &Canvas, threshold: u8) -> Mask {
+        let dimensions = canvas.dimensions();
+        let values = canvas
+            .pixels()
+            .map(|p| {
+                if to_grey_lumiosity(p).r >= threshold {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        Mask::new_with_data(dimensions.width, dimensions.height, values)
+    }
+
+    /// Creates a mask of size `width x height` covering the filled `path`. Edges are
+    /// anti-aliased.
+    pub fn from_path(width: u32, height: u32, path: &Path, rule: FillRule) -> Mask {
+        let (width, height) = (width.max(1), height.max(1));
+        Mask::new_with_data(width, height, path.coverage(width, height, rule))
+    }
+
+    /// Creates a mask of size `width x height` covering the stroked `path`.
+    pub fn from_stroke(width: u32, height: u32, path: &Path, stroke: &Stroke) -> Mask {
+        let (width, height) = (width.max(1), height.max(1));
+        Mask::new_with_data(width, height, path.stroke_coverage(width, height, stroke))
+    }
+
+    /// Retrieves width and height of the mask in a `Size` struct.
+    pub fn dimensions(&self) -> Size {
+        Size {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Returns the coverage at `(x, y)`. Positions outside the mask are not set.
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        if x < self.width && y < self.height {
+            self.values[(y * self.width + x) as usize]
+        } else {
+            0.0
+        }
+    }
+
+    /// Sets the coverage at `(x, y)` to `value`. Positions outside the mask are ignored.
+    pub fn set(&mut self, x: u32, y: u32, value: f32) {
+        if x < self.width && y < self.height {
+            self.values[(y * self.width + x) as usize] = value.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Returns the coverage values row by row
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Returns `true` if no position of the mask is set
+    pub fn is_empty(&self) -> bool {
+        self.values.iter().all(|&v| v <= 0.0)
+    }
+
+    /// Swaps set and unset parts of the mask
+    pub fn invert(mut self) -> Mask {
+        for value in self.values.iter_mut() {
+            *value = 1.0 - *value;
+        }
+        self
+    }
+
+    /// Combines two masks by keeping the highest coverage of each position
+    pub fn union(mut self, other: &Mask) -> Mask {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = self.get(x, y).max(other.get(x, y));
+                self.set(x, y, value);
+            }
+        }
+        self
+    }
+
+    /// Combines two masks by keeping the lowest coverage of each position
+    pub fn intersect(mut self, other: &Mask) -> Mask {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = self.get(x, y).min(other.get(x, y));
+                self.set(x, y, value);
+            }
+        }
+        self
+    }
+
+    /// Softens the edges of the mask. The transition from set to unset spreads over roughly
+    /// `radius` pixels on each side of the original edge.
+    pub fn feather(mut self, radius: f32) -> Mask {
+        if radius > 0.0 {
+            self.values = gaussian_blur_values(&self.values, self.width, self.height, radius / 3.0);
+        }
+        self
+    }
+
+    /// Turns the mask into a grey canvas where set is white and unset is black
+    pub fn to_canvas(&self) -> Canvas {
+        let pixels = self
+            .values
+            .iter()
+            .map(|v| from_grey((v * 255.0).round() as u8))
+            .collect();
+        Canvas::new_with_data(self.width, self.height, pixels)
+    }
+}
+
+/// Separable gaussian blur of a single channel with edges clamped to the border.
+pub(crate) fn gaussian_blur_values(
+    values: &[f32],
+    width: u32,
+    height: u32,
+    sigma: f32,
+) -> Vec<f32> {
+    gaussian_values(values, width as usize, height as usize, sigma, EdgeMode::Clamp)
+}
+
+/// Linear interpolation between two pixels where `amount = 0.0` gives `from`.
+fn mix_pixels(from: &Pixel, to: &Pixel, amount: f32) -> Pixel {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round();
+    Pixel::from(
+        mix(from.r, to.r),
+        mix(from.g, to.g),
+        mix(from.b, to.b),
+        mix(from.a, to.a),
+    )
+}
+
+impl Canvas {
+    /// Returns the canvas where every pixel is replaced by the pixel of `processed` weighted by
+    /// the coverage of `mask`. Unset parts of the mask keep the original pixels.
+    pub fn merge_masked(&self, processed: &Canvas, mask: &Mask) -> Canvas {
+        let dimensions = self.dimensions();
+        let mut canvas = self.clone();
+        for y in 0..dimensions.height {
+            for x in 0..dimensions.width {
+                let amount = mask.get(x, y);
+                if amount > 0.0 {
+                    let original = self.get_pixel(x, y);
+                    let changed = processed.get_pixel(x, y);
+                    canvas.set_pixel_mut(x, y, &mix_pixels(&original, &changed, amount));
+                }
+            }
+        }
+        canvas
+    }
+
+    /// Applies `operation` to the canvas, but only keeps the result where `mask` is set.
+    pub fn apply_masked<F>(&self, mask: &Mask, operation: F) -> Canvas
+    where
+        F: Fn(&Canvas) -> Canvas,
+    {
+        self.merge_masked(&operation(self), mask)
+    }
+
+    /// Applies filter `filter` where `mask` is set. See `Canvas::filter`.
+    pub fn filter_masked(&self, filter: fn(&Canvas, u32, u32) -> Pixel, mask: &Mask) -> Canvas {
+        self.apply_masked(mask, |canvas| canvas.filter(filter))
+    }
+
+    /// Turns the canvas grayscale where `mask` is set.
+    pub fn to_grey_masked(&self, mask: &Mask) -> Canvas {
+        self.apply_masked(mask, |canvas| canvas.to_grey())
+    }
+
+    /// Fills the canvas with `color` where `mask` is set.
+    pub fn fill_masked(&self, mask: &Mask, color: &Pixel) -> Canvas {
+        let dimensions = self.dimensions();
+        let filled =
+            Canvas::new_with_background(dimensions.width, dimensions.height, color.clone());
+        self.merge_masked(&filled, mask)
+    }
+
+    /// Replaces all pixels equal to `find_pixel` with `replace_pixel` where `mask` is set.
+    pub fn replace_pixel_with_masked(
+        &self,
+        find_pixel: &Pixel,
+        replace_pixel: &Pixel,
+        mask: &Mask,
+    ) -> Canvas {
+        self.apply_masked(mask, |canvas| {
+            canvas.clone().replace_pixel_with(find_pixel, replace_pixel)
+        })
+    }
+
+    /// Draws canvas `canvas` as a subimage at `(x, y)` with blend mode `mode` and opacity
+    /// `opacity`, but only where `mask` is set. `mask` is positioned like the canvas drawn on.
+    pub fn draw_subimage_with_masked(
+        &self,
+        x: u32,
+        y: u32,
+        canvas: &Canvas,
+        mode: BlendMode,
+        opacity: f32,
+        mask: &Mask,
+    ) -> Canvas {
+        let dimensions = self.dimensions();
+        let source_dimensions = canvas.dimensions();
+        let width = source_dimensions
+            .width
+            .min(dimensions.width.saturating_sub(x));
+        let height = source_dimensions
+            .height
+            .min(dimensions.height.saturating_sub(y));
+        let mut result = self.clone();
+        for j in 0..height {
+            for i in 0..width {
+                let amount = mask.get(x + i, y + j) * opacity;
+                if amount > 0.0 {
+                    let destination = self.get_pixel(x + i, y + j);
+                    let source = canvas.get_pixel(i, j);
+                    let new_color = blend_pixels(&destination, &source, mode, amount);
+                    result.set_pixel_mut(x + i, y + j, &new_color);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+
+    fn is_red(p: &Pixel, _: u32, _: u32) -> bool {
+        p == &Colors::RED
+    }
+
+    #[test]
+    fn mask_from_island_and_predicate_agree() {
+        let canvas = Canvas::new(10, 10).draw_square(2, 2, 3, 3, &Colors::RED);
+        let island = Island {
+            points: canvas
+                .find_with_predicate(is_red)
+                .iter()
+                .map(|p| p.coordinate)
+                .collect(),
+        };
+        let from_island = Mask::from_island(10, 10, &island);
+        let from_predicate = Mask::from_predicate(&canvas, is_red);
+        assert_eq!(from_island, from_predicate);
+        assert_eq!(from_island.values().iter().sum::<f32>(), 9.0);
+    }
+
+    #[test]
+    fn operations_only_apply_inside_mask() {
+        let canvas = Canvas::new_with_background(10, 10, Colors::RED);
+        let mask = Mask::from_path(10, 10, &Path::rect(0.0, 0.0, 5.0, 10.0), FillRule::NonZero);
+        let grey = canvas.to_grey_masked(&mask);
+        assert_eq!(grey.get_pixel(9, 9), Colors::RED);
+        assert_eq!(grey.get_pixel(0, 0), to_grey_lumiosity(&Colors::RED));
+
+        let filled = canvas.fill_masked(&mask.clone().invert(), &Colors::BLUE);
+        assert_eq!(filled.count_pixels(&Colors::BLUE), 50);
+        let replaced = canvas.replace_pixel_with_masked(&Colors::RED, &Colors::GREEN, &mask);
+        assert_eq!(replaced.count_pixels(&Colors::GREEN), 50);
+    }
+
+    #[test]
+    fn feathering_softens_edges() {
+        let mask = Mask::from_path(20, 1, &Path::rect(0.0, 0.0, 10.0, 1.0), FillRule::NonZero);
+        let feathered = mask.clone().feather(3.0);
+        assert!(feathered.get(9, 0) < 1.0 && feathered.get(9, 0) > 0.5);
+        assert!(feathered.get(10, 0) > 0.0 && feathered.get(10, 0) < 0.5);
+        assert!(feathered.get(0, 0) > 0.999);
+        assert_eq!(feathered.get(19, 0), 0.0);
+        let canvas = Canvas::new_with_background(20, 1, Colors::BLACK)
+            .fill_masked(&feathered, &Colors::WHITE);
+        assert!(canvas.get_pixel(9, 0).r > 128);
+    }
+
+    #[test]
+    fn threshold_and_blend_with_mask() {
+        let canvas = Canvas::new(4, 4).draw_square(0, 0, 2, 4, &Colors::BLACK);
+        let bright = Mask::from_threshold(&canvas, 128);
+        assert_eq!(bright.get(0, 0), 0.0);
+        assert_eq!(bright.get(3, 0), 1.0);
+        let layer = Canvas::new_with_background(2, 2, Colors::BLUE);
+        let result =
+            canvas.draw_subimage_with_masked(1, 1, &layer, BlendMode::Normal, 1.0, &bright);
+        assert_eq!(result.get_pixel(1, 1), Colors::BLACK);
+        assert_eq!(result.get_pixel(2, 2), Colors::BLUE);
+        assert_eq!(result.get_pixel(3, 3), Colors::WHITE);
+    }
+}
@@ -0,0 +1,511 @@
+use crate::canvas::{Canvas, Point, Size};
+use crate::denoise::{area_sum, integral};
+use crate::pixels::Pixel;
+
+/// How well a template fits the canvas at a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMethod {
+    /// Mean squared difference of the color channels between `0.0` and `1.0`. Lower is better
+    /// and `0.0` is an exact match.
+    Ssd,
+    /// Normalized cross-correlation between `0.0` and `1.0`. Higher is better. Insensitive to
+    /// the overall brightness of the template.
+    Ncc,
+    /// Zero-mean normalized cross-correlation between `-1.0` and `1.0`. Higher is better.
+    /// Insensitive to both brightness and contrast changes.
+    Zncc,
+}
+
+impl MatchMethod {
+    /// Whether `score` is a better match than `other`.
+    pub fn is_better(&self, score: f32, other: f32) -> bool {
+        match self {
+            MatchMethod::Ssd => score < other,
+            MatchMethod::Ncc | MatchMethod::Zncc => score > other,
+        }
+    }
+
+    /// Whether `score` is at least as good as `threshold`.
+    fn passes(&self, score: f32, threshold: f32) -> bool {
+        score == threshold || self.is_better(score, threshold)
+    }
+}
+
+/// A position of the template in the canvas and how well it matches there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub point: Point,
+    pub score: f32,
+}
+
+/// Scores of a template at every position where it fits entirely inside the canvas.
+#[derive(Debug, Clone)]
+pub struct ScoreMap {
+    width: u32,
+    height: u32,
+    template: Size,
+    method: MatchMethod,
+    scores: Vec<f32>,
+}
+
+impl ScoreMap {
+    /// Number of positions horizontally and vertically. Zero if the template is larger than
+    /// the canvas.
+    pub fn dimensions(&self) -> Size {
+        Size {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Size of the template the scores were computed for.
+    pub fn template_size(&self) -> Size {
+        self.template.clone()
+    }
+
+    pub fn method(&self) -> MatchMethod {
+        self.method
+    }
+
+    /// Scores row by row.
+    pub fn scores(&self) -> &[f32] {
+        &self.scores
+    }
+
+    /// Score of the template with its top left corner at `(x, y)`.
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        self.scores[(y * self.width + x) as usize]
+    }
+
+    fn point(&self, index: usize) -> Point {
+        Point {
+            x: index as u32 % self.width,
+            y: index as u32 / self.width,
+        }
+    }
+
+    /// The position with the best score, or `None` if the template did not fit.
+    pub fn find_best_match(&self) -> Option<Match> {
+        let mut best: Option<Match> = None;
+        for (i, &score) in self.scores.iter().enumerate() {
+            if best
+                .as_ref()
+                .is_none_or(|b| self.method.is_better(score, b.score))
+            {
+                best = Some(Match {
+                    point: self.point(i),
+                    score,
+                });
+            }
+        }
+        best
+    }
+
+    /// All positions scoring at least as well as `threshold`, best first. Of overlapping
+    /// matches only the best is kept, so every occurrence is reported once.
+    pub fn find_all_matches(&self, threshold: f32) -> Vec<Match> {
+        let mut candidates: Vec<Match> = self
+            .scores
+            .iter()
+            .enumerate()
+            .filter(|(_, &score)| self.method.passes(score, threshold))
+            .map(|(i, &score)| Match {
+                point: self.point(i),
+                score,
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            if self.method.is_better(a.score, b.score) {
+                std::cmp::Ordering::Less
+            } else if self.method.is_better(b.score, a.score) {
+                std::cmp::Ordering::Greater
+            } else {
+                (a.point.y, a.point.x).cmp(&(b.point.y, b.point.x))
+            }
+        });
+
+        let mut matches: Vec<Match> = Vec::new();
+        for candidate in candidates {
+            let overlaps = matches.iter().any(|m| {
+                m.point.x.abs_diff(candidate.point.x) < self.template.width
+                    && m.point.y.abs_diff(candidate.point.y) < self.template.height
+            });
+            if !overlaps {
+                matches.push(candidate);
+            }
+        }
+        matches
+    }
+
+    /// Grey visualization of the scores where the best match is white and the worst black.
+    pub fn to_canvas(&self) -> Canvas {
+        let min = self.scores.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = self
+            .scores
+            .iter()
+            .cloned()
+            .fold(f32::NEG_INFINITY, f32::max);
+        let range = (max - min).max(f32::EPSILON);
+        let pixels = self
+            .scores
+            .iter()
+            .map(|score| {
+                let mut value = (score - min) / range;
+                if self.method == MatchMethod::Ssd {
+                    value = 1.0 - value;
+                }
+                let grey = (value * 255.0).round();
+                Pixel::from(grey, grey, grey, 255.0)
+            })
+            .collect();
+        Canvas::new_with_data(self.width, self.height, pixels)
+    }
+}
+
+/// The color channels of a canvas between `0.0` and `1.0`. Alpha is ignored.
+fn channels(canvas: &Canvas) -> [Vec<f32>; 3] {
+    let mut channels: [Vec<f32>; 3] = Default::default();
+    for p in canvas.pixels() {
+        channels[0].push(p.r as f32 / 255.0);
+        channels[1].push(p.g as f32 / 255.0);
+        channels[2].push(p.b as f32 / 255.0);
+    }
+    channels
+}
+
+/// Sum over all channels of the cross-correlation of the image with the template at every
+/// position where the template fits.
+fn cross_correlation(
+    image: &[Vec<f32>; 3],
+    image_size: (usize, usize),
+    template: &[Vec<f32>; 3],
+    template_size: (usize, usize),
+) -> Vec<f64> {
+    let (width, height) = image_size;
+    let (w, h) = template_size;
+    let direct_cost = ((width - w + 1) * (height - h + 1) * w * h) as f64;
+    let (pw, ph) = (width.next_power_of_two(), height.next_power_of_two());
+    let fft_cost = 8.0 * (pw * ph) as f64 * ((pw * ph) as f64).log2();
+    if direct_cost <= fft_cost {
+        direct_correlation(image, image_size, template, template_size)
+    } else {
+        fft_correlation(image, image_size, template, template_size)
+    }
+}
+
+fn direct_correlation(
+    image: &[Vec<f32>; 3],
+    (width, height): (usize, usize),
+    template: &[Vec<f32>; 3],
+    (w, h): (usize, usize),
+) -> Vec<f64> {
+    let mut result = Vec::with_capacity((width - w + 1) * (height - h + 1));
+    for y in 0..=height - h {
+        for x in 0..=width - w {
+            let mut sum = 0f64;
+            for (i, t) in image.iter().zip(template.iter()) {
+                for v in 0..h {
+                    let row = &i[(y + v) * width + x..(y + v) * width + x + w];
+                    let template_row = &t[v * w..(v + 1) * w];
+                    for (a, b) in row.iter().zip(template_row) {
+                        sum += (*a as f64) * (*b as f64);
+                    }
+                }
+            }
+            result.push(sum);
+        }
+    }
+    result
+}
+
+/// In-place radix-2 FFT of `values`, whose length must be a power of two.
+fn fft(values: &mut [(f64, f64)], inverse: bool) {
+    let n = values.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut length = 2;
+    while length <= n {
+        let angle = sign * 2.0 * std::f64::consts::PI / length as f64;
+        let (sin, cos) = angle.sin_cos();
+        for start in (0..n).step_by(length) {
+            let mut w = (1.0, 0.0);
+            for k in 0..length / 2 {
+                let (a, b) = (values[start + k], values[start + k + length / 2]);
+                let t = (b.0 * w.0 - b.1 * w.1, b.0 * w.1 + b.1 * w.0);
+                values[start + k] = (a.0 + t.0, a.1 + t.1);
+                values[start + k + length / 2] = (a.0 - t.0, a.1 - t.1);
+                w = (w.0 * cos - w.1 * sin, w.0 * sin + w.1 * cos);
+            }
+        }
+        length <<= 1;
+    }
+    if inverse {
+        for v in values.iter_mut() {
+            *v = (v.0 / n as f64, v.1 / n as f64);
+        }
+    }
+}
+
+/// Two dimensional FFT of a `width` by `height` grid stored row by row.
+fn fft_2d(values: &mut [(f64, f64)], width: usize, height: usize, inverse: bool) {
+    for row in values.chunks_mut(width) {
+        fft(row, inverse);
+    }
+    let mut column = vec![(0.0, 0.0); height];
+    for x in 0..width {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = values[y * width + x];
+        }
+        fft(&mut column, inverse);
+        for (y, value) in column.iter().enumerate() {
+            values[y * width + x] = *value;
+        }
+    }
+}
+
+fn fft_correlation(
+    image: &[Vec<f32>; 3],
+    (width, height): (usize, usize),
+    template: &[Vec<f32>; 3],
+    (w, h): (usize, usize),
+) -> Vec<f64> {
+    // Padding to at least the image size keeps the valid positions free of wrap around
+    let (pw, ph) = (width.next_power_of_two(), height.next_power_of_two());
+    let padded = |values: &[f32], vw: usize, vh: usize| {
+        let mut grid = vec![(0.0, 0.0); pw * ph];
+        for y in 0..vh {
+            for x in 0..vw {
+                grid[y * pw + x] = (values[y * vw + x] as f64, 0.0);
+            }
+        }
+        fft_2d(&mut grid, pw, ph, false);
+        grid
+    };
+    let mut spectrum = vec![(0.0, 0.0); pw * ph];
+    for (i, t) in image.iter().zip(template.iter()) {
+        let (fi, ft) = (padded(i, width, height), padded(t, w, h));
+        for ((s, a), b) in spectrum.iter_mut().zip(fi).zip(ft) {
+            // Multiplying with the conjugate correlates instead of convolves
+            s.0 += a.0 * b.0 + a.1 * b.1;
+            s.1 += a.1 * b.0 - a.0 * b.1;
+        }
+    }
+    fft_2d(&mut spectrum, pw, ph, true);
+    let mut result = Vec::with_capacity((width - w + 1) * (height - h + 1));
+    for y in 0..=height - h {
+        for x in 0..=width - w {
+            result.push(spectrum[y * pw + x].0);
+        }
+    }
+    result
+}
+
+impl Canvas {
+    /// Scores how well `template` matches at every position of the canvas using `method`.
+    /// Window sums come from integral images and the cross-correlation is computed with an
+    /// FFT when the template is large. Only the color channels are compared, so grey canvases
+    /// from `to_grey` work as well. The score map is empty if the template does not fit.
+    pub fn match_template(&self, template: &Canvas, method: MatchMethod) -> ScoreMap {
+        let (size, template_size) = (self.dimensions(), template.dimensions());
+        let empty = template_size.width == 0
+            || template_size.height == 0
+            || template_size.width > size.width
+            || template_size.height > size.height;
+        if empty {
+            return ScoreMap {
+                width: 0,
+                height: 0,
+                template: template_size,
+                method,
+                scores: vec![],
+            };
+        }
+        let (width, height) = (size.width as usize, size.height as usize);
+        let (w, h) = (template_size.width as usize, template_size.height as usize);
+        let image = channels(self);
+        let pattern = channels(template);
+        let correlation = cross_correlation(&image, (width, height), &pattern, (w, h));
+
+        let sums: Vec<Vec<f64>> = image.iter().map(|c| integral(c, width, height)).collect();
+        let squares: Vec<Vec<f64>> = image
+            .iter()
+            .map(|c| {
+                let squared: Vec<f32> = c.iter().map(|v| v * v).collect();
+                integral(&squared, width, height)
+            })
+            .collect();
+        let template_sums: Vec<f64> = pattern
+            .iter()
+            .map(|c| c.iter().map(|&v| v as f64).sum())
+            .collect();
+        let template_squares: Vec<f64> = pattern
+            .iter()
+            .map(|c| c.iter().map(|&v| (v as f64) * (v as f64)).sum())
+            .collect();
+        let n = (w * h) as f64;
+
+        let (map_width, map_height) = (width - w + 1, height - h + 1);
+        let mut scores = Vec::with_capacity(map_width * map_height);
+        for y in 0..map_height {
+            for x in 0..map_width {
+                let cross = correlation[y * map_width + x];
+                let window = |table: &Vec<f64>| area_sum(table, width, x, y, x + w - 1, y + h - 1);
+                let score = match method {
+                    MatchMethod::Ssd => {
+                        let image_squares: f64 = squares.iter().map(window).sum();
+                        let total: f64 = template_squares.iter().sum();
+                        ((image_squares - 2.0 * cross + total) / (3.0 * n)).max(0.0)
+                    }
+                    MatchMethod::Ncc => {
+                        let image_squares: f64 = squares.iter().map(window).sum();
+                        let total: f64 = template_squares.iter().sum();
+                        let norm = (image_squares * total).sqrt();
+                        if norm > 1e-9 {
+                            cross / norm
+                        } else {
+                            0.0
+                        }
+                    }
+                    MatchMethod::Zncc => {
+                        let mut covariance = cross;
+                        let (mut image_variance, mut template_variance) = (0f64, 0f64);
+                        for c in 0..3 {
+                            let sum = window(&sums[c]);
+                            covariance -= sum * template_sums[c] / n;
+                            image_variance += window(&squares[c]) - sum * sum / n;
+                            template_variance +=
+                                template_squares[c] - template_sums[c] * template_sums[c] / n;
+                        }
+                        let norm = (image_variance.max(0.0) * template_variance.max(0.0)).sqrt();
+                        if norm > 1e-6 {
+                            covariance / norm
+                        } else {
+                            0.0
+                        }
+                    }
+                };
+                scores.push(score as f32);
+            }
+        }
+        ScoreMap {
+            width: map_width as u32,
+            height: map_height as u32,
+            template: template_size,
+            method,
+            scores,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+
+    /// A seeded random canvas with the template cut out at `(x, y)`.
+    fn scene(x: u32, y: u32) -> (Canvas, Canvas) {
+        let canvas = Canvas::new_random(40, 30, 7);
+        let template = canvas.get_subimage(x, y, 9, 6);
+        (canvas, template)
+    }
+
+    #[test]
+    fn fft_matches_direct_correlation() {
+        let (canvas, template) = scene(3, 4);
+        let (image, pattern) = (channels(&canvas), channels(&template));
+        let direct = direct_correlation(&image, (40, 30), &pattern, (9, 6));
+        let fast = fft_correlation(&image, (40, 30), &pattern, (9, 6));
+        assert_eq!(direct.len(), 32 * 25);
+        for (a, b) in direct.iter().zip(&fast) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn finds_the_template() {
+        let (canvas, template) = scene(17, 11);
+        for method in [MatchMethod::Ssd, MatchMethod::Ncc, MatchMethod::Zncc] {
+            let scores = canvas.match_template(&template, method);
+            assert_eq!(
+                scores.dimensions(),
+                Size {
+                    width: 32,
+                    height: 25
+                }
+            );
+            let best = scores.find_best_match().unwrap();
+            assert_eq!(best.point, Point { x: 17, y: 11 });
+            let perfect = if method == MatchMethod::Ssd { 0.0 } else { 1.0 };
+            assert!((best.score - perfect).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn zncc_ignores_brightness_and_contrast() {
+        let (canvas, template) = scene(5, 20);
+        let template = template.contrast(0.5).brightness(0.1);
+        let scores = canvas.match_template(&template, MatchMethod::Zncc);
+        assert_eq!(
+            scores.find_best_match().unwrap().point,
+            Point { x: 5, y: 20 }
+        );
+    }
+
+    #[test]
+    fn finds_all_occurrences_once() {
+        let icon = Canvas::new(4, 4)
+            .draw_square(1, 0, 2, 4, &Colors::RED)
+            .draw_square(0, 1, 4, 2, &Colors::RED);
+        let canvas = Canvas::new(30, 20)
+            .draw_subimage(2, 3, &icon)
+            .draw_subimage(20, 3, &icon)
+            .draw_subimage(11, 14, &icon)
+            .to_grey();
+        let icon = icon.to_grey();
+        let scores = canvas.match_template(&icon, MatchMethod::Ssd);
+        let matches = scores.find_all_matches(0.001);
+        let mut points: Vec<(u32, u32)> = matches.iter().map(|m| (m.point.x, m.point.y)).collect();
+        points.sort();
+        assert_eq!(points, vec![(2, 3), (11, 14), (20, 3)]);
+
+        let matches = canvas
+            .match_template(&icon, MatchMethod::Zncc)
+            .find_all_matches(0.5);
+        assert_eq!(matches.len(), 3);
+        assert!(matches.iter().all(|m| m.score > 0.99));
+    }
+
+    #[test]
+    fn oversized_templates_give_empty_maps() {
+        let scores = Canvas::new(4, 4).match_template(&Canvas::new(5, 2), MatchMethod::Ncc);
+        assert_eq!(
+            scores.dimensions(),
+            Size {
+                width: 0,
+                height: 0
+            }
+        );
+        assert_eq!(scores.find_best_match(), None);
+        assert!(scores.find_all_matches(0.0).is_empty());
+    }
+
+    #[test]
+    fn visualization_marks_the_best_match_white() {
+        let (canvas, template) = scene(2, 2);
+        let image = canvas
+            .match_template(&template, MatchMethod::Ssd)
+            .to_canvas();
+        assert_eq!(image.get_pixel(2, 2), Colors::WHITE);
+    }
+}
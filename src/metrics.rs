@@ -0,0 +1,345 @@
+use crate::canvas::{Canvas, ImageError};
+use crate::colorspace::pixel_to_lab;
+use crate::gradient::Gradient;
+use crate::mask::gaussian_blur_values;
+use crate::pixels::{ColorTrait, Colors, Pixel};
+
+/// Standard deviation of the gaussian window used by `ssim`.
+const SSIM_SIGMA: f32 = 1.5;
+/// Weights of the scales of `ms_ssim`, from the finest to the coarsest.
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+/// The formula used to compute the perceptual distance between two colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaE {
+    /// Euclidean distance in CIELAB
+    Cie76,
+    Ciede2000,
+}
+
+impl DeltaE {
+    /// Returns the distance between the colors of `p1` and `p2`. Alpha is ignored.
+    pub fn between(&self, p1: &Pixel, p2: &Pixel) -> f32 {
+        let lab1 = pixel_to_lab(p1);
+        let lab2 = pixel_to_lab(p2);
+        match self {
+            DeltaE::Cie76 => {
+                ((lab1.0 - lab2.0).powi(2) + (lab1.1 - lab2.1).powi(2) + (lab1.2 - lab2.2).powi(2))
+                    .sqrt()
+            }
+            DeltaE::Ciede2000 => ciede2000(lab1, lab2),
+        }
+    }
+}
+
+/// CIEDE2000 color difference between two CIELAB colors.
+fn ciede2000(lab1: (f32, f32, f32), lab2: (f32, f32, f32)) -> f32 {
+    let (l1, a1, b1) = (lab1.0 as f64, lab1.1 as f64, lab1.2 as f64);
+    let (l2, a2, b2) = (lab2.0 as f64, lab2.1 as f64, lab2.2 as f64);
+    let pow7 = |v: f64| v.powi(7);
+    let hue = |b: f64, a: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+
+    let c_bar = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let g = 0.5 * (1.0 - (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt());
+    let (a1p, a2p) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1p, c2p) = (a1p.hypot(b1), a2p.hypot(b2));
+    let (h1p, h2p) = (hue(b1, a1p), hue(b2, a2p));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2p - c1p;
+    let delta_h = if c1p * c2p == 0.0 {
+        0.0
+    } else {
+        let d = h2p - h1p;
+        if d > 180.0 {
+            d - 360.0
+        } else if d < -180.0 {
+            d + 360.0
+        } else {
+            d
+        }
+    };
+    let delta_h = 2.0 * (c1p * c2p).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+    let l_bar = (l1 + l2) / 2.0;
+    let c_bar_p = (c1p + c2p) / 2.0;
+    let h_bar = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= 180.0 {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 360.0 {
+        (h1p + h2p + 360.0) / 2.0
+    } else {
+        (h1p + h2p - 360.0) / 2.0
+    };
+
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t =
+        1.0 - 0.17 * cos(h_bar - 30.0) + 0.24 * cos(2.0 * h_bar) + 0.32 * cos(3.0 * h_bar + 6.0)
+            - 0.20 * cos(4.0 * h_bar - 63.0);
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (pow7(c_bar_p) / (pow7(c_bar_p) + pow7(25.0))).sqrt();
+    let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar_p;
+    let s_h = 1.0 + 0.015 * c_bar_p * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).sqrt() as f32
+}
+
+/// Returns an error unless `c1` and `c2` have the same dimensions.
+pub(crate) fn check_dimensions(c1: &Canvas, c2: &Canvas) -> Result<(), ImageError> {
+    let (d1, d2) = (c1.dimensions(), c2.dimensions());
+    if d1.width != d2.width || d1.height != d2.height {
+        return Err(ImageError::Parameter(format!(
+            "Canvas dimensions differ: {}x{} and {}x{}",
+            d1.width, d1.height, d2.width, d2.height
+        )));
+    }
+    Ok(())
+}
+
+/// Mean squared error over all four channels of `c1` and `c2`. Fails if the dimensions differ.
+pub fn mse(c1: &Canvas, c2: &Canvas) -> Result<f64, ImageError> {
+    check_dimensions(c1, c2)?;
+    let count = c1.pixels().len() as f64 * 4.0;
+    if count == 0.0 {
+        return Ok(0.0);
+    }
+    let sum: f64 = c1
+        .pixels()
+        .zip(c2.pixels())
+        .map(|(p1, p2)| {
+            [(p1.r, p2.r), (p1.g, p2.g), (p1.b, p2.b), (p1.a, p2.a)]
+                .iter()
+                .map(|&(v1, v2)| (v1 as f64 - v2 as f64).powi(2))
+                .sum::<f64>()
+        })
+        .sum();
+    Ok(sum / count)
+}
+
+/// Peak signal-to-noise ratio in decibels. Identical canvases give infinity.
+pub fn psnr(c1: &Canvas, c2: &Canvas) -> Result<f64, ImageError> {
+    let mse = mse(c1, c2)?;
+    if mse == 0.0 {
+        return Ok(f64::INFINITY);
+    }
+    Ok(10.0 * (255.0 * 255.0 / mse).log10())
+}
+
+/// Luma of every pixel between `0.0` and `255.0`.
+fn luma_values(canvas: &Canvas) -> Vec<f32> {
+    canvas
+        .pixels()
+        .map(|p| 0.299 * p.r as f32 + 0.587 * p.g as f32 + 0.114 * p.b as f32)
+        .collect()
+}
+
+/// Halves the size of `values` by averaging blocks of 2x2 values.
+fn downsample(values: &[f32], width: u32, height: u32) -> (Vec<f32>, u32, u32) {
+    let (new_width, new_height) = (width / 2, height / 2);
+    let mut result = Vec::with_capacity((new_width * new_height) as usize);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let at = |dx: u32, dy: u32| values[((2 * y + dy) * width + 2 * x + dx) as usize];
+            result.push((at(0, 0) + at(1, 0) + at(0, 1) + at(1, 1)) / 4.0);
+        }
+    }
+    (result, new_width, new_height)
+}
+
+/// Mean SSIM and mean contrast-structure term of two luma planes.
+fn ssim_values(x: &[f32], y: &[f32], width: u32, height: u32) -> (f64, f64) {
+    if x.is_empty() {
+        return (1.0, 1.0);
+    }
+    let c1 = (0.01f32 * 255.0).powi(2);
+    let c2 = (0.03f32 * 255.0).powi(2);
+    let blur = |values: &[f32]| gaussian_blur_values(values, width, height, SSIM_SIGMA);
+    let product =
+        |a: &[f32], b: &[f32]| -> Vec<f32> { a.iter().zip(b).map(|(a, b)| a * b).collect() };
+
+    let mu_x = blur(x);
+    let mu_y = blur(y);
+    let xx = blur(&product(x, x));
+    let yy = blur(&product(y, y));
+    let xy = blur(&product(x, y));
+
+    let (mut ssim, mut cs) = (0f64, 0f64);
+    for i in 0..x.len() {
+        let var_x = xx[i] - mu_x[i] * mu_x[i];
+        let var_y = yy[i] - mu_y[i] * mu_y[i];
+        let covariance = xy[i] - mu_x[i] * mu_y[i];
+        let contrast = (2.0 * covariance + c2) / (var_x + var_y + c2);
+        let luminance =
+            (2.0 * mu_x[i] * mu_y[i] + c1) / (mu_x[i] * mu_x[i] + mu_y[i] * mu_y[i] + c1);
+        ssim += (luminance * contrast) as f64;
+        cs += contrast as f64;
+    }
+    (ssim / x.len() as f64, cs / x.len() as f64)
+}
+
+/// Structural similarity index of the luma of `c1` and `c2` with an 11x11 gaussian window.
+/// `1.0` means identical. Alpha is ignored. Fails if the dimensions differ.
+pub fn ssim(c1: &Canvas, c2: &Canvas) -> Result<f64, ImageError> {
+    check_dimensions(c1, c2)?;
+    let dimensions = c1.dimensions();
+    let (ssim, _) = ssim_values(
+        &luma_values(c1),
+        &luma_values(c2),
+        dimensions.width,
+        dimensions.height,
+    );
+    Ok(ssim)
+}
+
+/// Multi-scale structural similarity of the luma of `c1` and `c2`. Uses up to five scales,
+/// fewer for canvases too small to halve that often. Fails if the dimensions differ.
+pub fn ms_ssim(c1: &Canvas, c2: &Canvas) -> Result<f64, ImageError> {
+    check_dimensions(c1, c2)?;
+    let dimensions = c1.dimensions();
+    let (mut width, mut height) = (dimensions.width, dimensions.height);
+    let (mut x, mut y) = (luma_values(c1), luma_values(c2));
+
+    let mut scales = 1;
+    while scales < MS_SSIM_WEIGHTS.len() && (width >> scales).min(height >> scales) >= 1 {
+        scales += 1;
+    }
+    let total: f64 = MS_SSIM_WEIGHTS[..scales].iter().sum();
+
+    let mut result = 1.0;
+    for (scale, weight) in MS_SSIM_WEIGHTS[..scales].iter().enumerate() {
+        let (ssim, cs) = ssim_values(&x, &y, width, height);
+        let value = if scale == scales - 1 { ssim } else { cs };
+        result *= value.max(0.0).powf(weight / total);
+        if scale < scales - 1 {
+            let (next_x, w, h) = downsample(&x, width, height);
+            let (next_y, _, _) = downsample(&y, width, height);
+            (x, y, width, height) = (next_x, next_y, w, h);
+        }
+    }
+    Ok(result)
+}
+
+/// Perceptual color distance of every pixel of `c1` and `c2`, row by row. Fails if the
+/// dimensions differ.
+pub fn delta_e(c1: &Canvas, c2: &Canvas, formula: DeltaE) -> Result<Vec<f32>, ImageError> {
+    check_dimensions(c1, c2)?;
+    Ok(c1
+        .pixels()
+        .zip(c2.pixels())
+        .map(|(p1, p2)| formula.between(p1, p2))
+        .collect())
+}
+
+/// Renders the color distance of every pixel of `c1` and `c2` as a heatmap. Identical pixels
+/// are black and distances of `max` or more are white, passing through red and yellow.
+pub fn delta_e_map(
+    c1: &Canvas,
+    c2: &Canvas,
+    formula: DeltaE,
+    max: f32,
+) -> Result<Canvas, ImageError> {
+    let distances = delta_e(c1, c2, formula)?;
+    let heat = Gradient::linear(0.0, 0.0, 1.0, 0.0)
+        .add_stop(0.0, &Colors::BLACK)
+        .add_stop(0.4, &Colors::RED)
+        .add_stop(0.75, &Pixel::new(255, 255, 0, 255))
+        .add_stop(1.0, &Colors::WHITE);
+    let max = max.max(f32::EPSILON);
+    let pixels = distances
+        .iter()
+        .map(|d| heat.color_at((d / max).min(1.0)))
+        .collect();
+    let dimensions = c1.dimensions();
+    Ok(Canvas::new_with_data(
+        dimensions.width,
+        dimensions.height,
+        pixels,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(offset: u8) -> Canvas {
+        let pixels = (0..32 * 32u32)
+            .map(|i| {
+                let v = ((i % 32 * 7 + i / 32 * 13) % 256) as u8;
+                Pixel::new(v.saturating_add(offset), 255 - v, v / 2, 255)
+            })
+            .collect();
+        Canvas::new_with_data(32, 32, pixels)
+    }
+
+    #[test]
+    fn mse_and_psnr() {
+        let a = Canvas::new_with_background(4, 4, Pixel::new(10, 10, 10, 255));
+        let b = Canvas::new_with_background(4, 4, Pixel::new(20, 10, 10, 255));
+        assert_eq!(mse(&a, &b).unwrap(), 25.0);
+        assert!((psnr(&a, &b).unwrap() - 34.15).abs() < 0.01);
+        assert_eq!(psnr(&a, &a).unwrap(), f64::INFINITY);
+        assert!(mse(&a, &Canvas::new(4, 5)).is_err());
+        assert!(psnr(&a, &Canvas::new(5, 4)).is_err());
+    }
+
+    #[test]
+    fn ssim_orders_distortions() {
+        let original = pattern(0);
+        let shifted = pattern(10);
+        let noisy = original.filter(|c, x, y| {
+            let p = c.get_pixel(x, y);
+            if (x + y) % 2 == 0 {
+                Pixel::new(p.r.saturating_add(60), p.g.saturating_sub(60), p.b, p.a)
+            } else {
+                p
+            }
+        });
+        assert!((ssim(&original, &original).unwrap() - 1.0).abs() < 1e-6);
+        let small = ssim(&original, &shifted).unwrap();
+        let large = ssim(&original, &noisy).unwrap();
+        assert!(small < 1.0 && large < small);
+        assert!((ms_ssim(&original, &original).unwrap() - 1.0).abs() < 1e-6);
+        assert!(ms_ssim(&original, &noisy).unwrap() < ms_ssim(&original, &shifted).unwrap());
+        assert!(ssim(&original, &Canvas::new(3, 3)).is_err());
+        assert!(ms_ssim(&original, &Canvas::new(3, 3)).is_err());
+    }
+
+    #[test]
+    fn ciede2000_reference_values() {
+        // Pairs from Sharma, Wu and Dalal's CIEDE2000 test data
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, -1.3802, -84.2814), (50.0, 0.0, -82.7485), 1.0),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            ((2.0776, 0.0795, -1.135), (0.9033, -0.0636, -0.5514), 0.9082),
+        ];
+        for (lab1, lab2, expected) in pairs {
+            assert!((ciede2000(lab1, lab2) - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn delta_e_heatmap() {
+        let a = Canvas::new(2, 1).set_pixel(1, 0, &Colors::BLACK);
+        let b = Canvas::new(2, 1);
+        let distances = delta_e(&a, &b, DeltaE::Cie76).unwrap();
+        assert_eq!(distances[0], 0.0);
+        assert!((distances[1] - 100.0).abs() < 0.01);
+        assert!(DeltaE::Ciede2000.between(&Colors::BLACK, &Colors::WHITE) > 99.0);
+
+        let map = delta_e_map(&a, &b, DeltaE::Ciede2000, 10.0).unwrap();
+        assert_eq!(map.get_pixel(0, 0), Colors::BLACK);
+        assert_eq!(map.get_pixel(1, 0), Colors::WHITE);
+        assert!(delta_e_map(&a, &Canvas::new(1, 1), DeltaE::Cie76, 1.0).is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use crate::canvas::Canvas;
+use crate::gradient::Gradient;
+use crate::utility::from_grey;
+use rand::Rng;
+use std::f32::consts::PI;
+
+/// The base function of a `Noise`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Independent random values for every cell
+    White,
+    Perlin,
+    Simplex,
+    /// Distance to the closest of randomly placed feature points, also known as cellular noise
+    Worley,
+}
+
+/// A seeded noise function. With more than one octave the base function is summed at
+/// increasing frequencies to give fractal Brownian motion (fBm).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Noise {
+    pub kind: NoiseKind,
+    pub seed: u64,
+    /// Size in pixels of the coarsest features
+    pub scale: f32,
+    pub octaves: u32,
+    /// Frequency multiplier between octaves
+    pub lacunarity: f32,
+    /// Amplitude multiplier between octaves
+    pub gain: f32,
+}
+
+/// Mixes `seed`, a lattice coordinate and `salt` into a pseudo random number (SplitMix64).
+fn hash(seed: u64, x: i64, y: i64, salt: u64) -> u64 {
+    let mut z = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ salt.wrapping_mul(0x1656_67B1_9E37_79F9);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Pseudo random number between `0.0` and `1.0` for a lattice coordinate.
+fn unit(seed: u64, x: i64, y: i64, salt: u64) -> f32 {
+    (hash(seed, x, y, salt) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Wraps a lattice coordinate into `0..period` when tiling.
+fn wrap(value: i64, period: Option<i64>) -> i64 {
+    match period {
+        Some(period) => value.rem_euclid(period),
+        None => value,
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Unit gradient for a lattice point, one of eight directions.
+fn gradient(seed: u64, x: i64, y: i64) -> (f32, f32) {
+    let angle = (hash(seed, x, y, 1) % 8) as f32 * PI / 4.0;
+    (angle.cos(), angle.sin())
+}
+
+fn white(seed: u64, x: f32, y: f32, period: Option<(i64, i64)>) -> f32 {
+    let (px, py) = (period.map(|p| p.0), period.map(|p| p.1));
+    let (ix, iy) = (wrap(x.floor() as i64, px), wrap(y.floor() as i64, py));
+    unit(seed, ix, iy, 0) * 2.0 - 1.0
+}
+
+fn perlin(seed: u64, x: f32, y: f32, period: Option<(i64, i64)>) -> f32 {
+    let (px, py) = (period.map(|p| p.0), period.map(|p| p.1));
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let corner = |dx: i64, dy: i64| {
+        let ix = wrap(x0 as i64 + dx, px);
+        let iy = wrap(y0 as i64 + dy, py);
+        let (gx, gy) = gradient(seed, ix, iy);
+        gx * (fx - dx as f32) + gy * (fy - dy as f32)
+    };
+    let (u, v) = (fade(fx), fade(fy));
+    let value = lerp(
+        lerp(corner(0, 0), corner(1, 0), u),
+        lerp(corner(0, 1), corner(1, 1), u),
+        v,
+    );
+    // Unit gradients give values within +-sqrt(0.5)
+    (value * std::f32::consts::SQRT_2).clamp(-1.0, 1.0)
+}
+
+fn simplex(seed: u64, x: f32, y: f32) -> f32 {
+    let f2 = 0.5 * (3f32.sqrt() - 1.0);
+    let g2 = (3.0 - 3f32.sqrt()) / 6.0;
+    let s = (x + y) * f2;
+    let (i, j) = ((x + s).floor(), (y + s).floor());
+    let t = (i + j) * g2;
+    let (x0, y0) = (x - (i - t), y - (j - t));
+    let (i1, j1) = if x0 > y0 { (1.0, 0.0) } else { (0.0, 1.0) };
+    let corners = [
+        (0.0, 0.0, x0, y0),
+        (i1, j1, x0 - i1 + g2, y0 - j1 + g2),
+        (1.0, 1.0, x0 - 1.0 + 2.0 * g2, y0 - 1.0 + 2.0 * g2),
+    ];
+    let mut value = 0.0;
+    for (di, dj, cx, cy) in corners {
+        let t = 0.5 - cx * cx - cy * cy;
+        if t > 0.0 {
+            let (gx, gy) = gradient(seed, (i + di) as i64, (j + dj) as i64);
+            value += t.powi(4) * (gx * cx + gy * cy);
+        }
+    }
+    (value * 99.0).clamp(-1.0, 1.0)
+}
+
+fn worley(seed: u64, x: f32, y: f32, period: Option<(i64, i64)>) -> f32 {
+    let (px, py) = (period.map(|p| p.0), period.map(|p| p.1));
+    let (cx, cy) = (x.floor() as i64, y.floor() as i64);
+    let mut closest = f32::MAX;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let (ix, iy) = (wrap(cx + dx, px), wrap(cy + dy, py));
+            let fx = (cx + dx) as f32 + unit(seed, ix, iy, 2);
+            let fy = (cy + dy) as f32 + unit(seed, ix, iy, 3);
+            closest = closest.min((fx - x).hypot(fy - y));
+        }
+    }
+    closest.min(1.0) * 2.0 - 1.0
+}
+
+impl Noise {
+    /// Creates single octave noise of `kind` with features of 32 pixels
+    pub fn new(kind: NoiseKind, seed: u64) -> Noise {
+        Noise {
+            kind,
+            seed,
+            scale: 32.0,
+            octaves: 1,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+
+    /// Creates white noise with one random value per pixel
+    pub fn white(seed: u64) -> Noise {
+        Noise::new(NoiseKind::White, seed).set_scale(1.0)
+    }
+
+    pub fn perlin(seed: u64) -> Noise {
+        Noise::new(NoiseKind::Perlin, seed)
+    }
+
+    pub fn simplex(seed: u64) -> Noise {
+        Noise::new(NoiseKind::Simplex, seed)
+    }
+
+    pub fn worley(seed: u64) -> Noise {
+        Noise::new(NoiseKind::Worley, seed)
+    }
+
+    pub fn set_seed(mut self, seed: u64) -> Noise {
+        self.seed = seed;
+        self
+    }
+
+    /// Draws the seed from `rng`
+    pub fn set_seed_from<R: Rng + ?Sized>(mut self, rng: &mut R) -> Noise {
+        self.seed = rng.next_u64();
+        self
+    }
+
+    /// Sets the size in pixels of the coarsest features
+    pub fn set_scale(mut self, scale: f32) -> Noise {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the number of octaves summed for fractal Brownian motion
+    pub fn set_octaves(mut self, octaves: u32) -> Noise {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn set_lacunarity(mut self, lacunarity: f32) -> Noise {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn set_gain(mut self, gain: f32) -> Noise {
+        self.gain = gain;
+        self
+    }
+
+    /// The base function at lattice coordinate `(x, y)` between `-1.0` and `1.0`.
+    fn base(&self, seed: u64, x: f32, y: f32, period: Option<(i64, i64)>) -> f32 {
+        match self.kind {
+            NoiseKind::White => white(seed, x, y, period),
+            NoiseKind::Perlin => perlin(seed, x, y, period),
+            NoiseKind::Worley => worley(seed, x, y, period),
+            NoiseKind::Simplex => match period {
+                None => simplex(seed, x, y),
+                Some((px, py)) => {
+                    // The simplex grid cannot wrap, so blend four shifted copies instead
+                    let (w, h) = (px as f32, py as f32);
+                    let (x, y) = (x.rem_euclid(w), y.rem_euclid(h));
+                    (simplex(seed, x, y) * (w - x) * (h - y)
+                        + simplex(seed, x - w, y) * x * (h - y)
+                        + simplex(seed, x, y - h) * (w - x) * y
+                        + simplex(seed, x - w, y - h) * x * y)
+                        / (w * h)
+                }
+            },
+        }
+    }
+
+    /// Sums the octaves at pixel `(x, y)`. When `size` is given the noise repeats every
+    /// `width x height` pixels.
+    fn fractal(&self, x: f32, y: f32, size: Option<(u32, u32)>) -> f32 {
+        let scale = self.scale.max(f32::EPSILON);
+        let (mut frequency, mut amplitude) = (1.0 / scale, 1.0);
+        let (mut sum, mut total) = (0.0, 0.0);
+        for octave in 0..self.octaves {
+            let seed = hash(self.seed, octave as i64, 0, 4);
+            let value = match size {
+                None => self.base(seed, x * frequency, y * frequency, None),
+                Some((width, height)) => {
+                    // Round to a whole number of lattice cells so the edges line up
+                    let px = (width as f32 * frequency).round().max(1.0);
+                    let py = (height as f32 * frequency).round().max(1.0);
+                    self.base(
+                        seed,
+                        x * px / width as f32,
+                        y * py / height as f32,
+                        Some((px as i64, py as i64)),
+                    )
+                }
+            };
+            sum += value * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        ((sum / total + 1.0) / 2.0).clamp(0.0, 1.0)
+    }
+
+    /// Samples the noise at pixel `(x, y)`. The result is between `0.0` and `1.0`.
+    pub fn sample(&self, x: f32, y: f32) -> f32 {
+        self.fractal(x, y, None)
+    }
+
+    /// Samples noise that repeats every `width x height` pixels. The feature size is adjusted
+    /// so a whole number of lattice cells fit.
+    pub fn sample_tiled(&self, x: f32, y: f32, width: u32, height: u32) -> f32 {
+        self.fractal(x, y, Some((width.max(1), height.max(1))))
+    }
+}
+
+impl Canvas {
+    fn new_with_noise_function(width: u32, height: u32, f: impl Fn(f32, f32) -> f32) -> Canvas {
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(from_grey((f(x as f32, y as f32) * 255.0).round() as u8));
+            }
+        }
+        Canvas::new_with_data(width, height, pixels)
+    }
+
+    /// Creates a grayscale canvas filled with `noise`
+    pub fn new_with_noise(width: u32, height: u32, noise: &Noise) -> Canvas {
+        Canvas::new_with_noise_function(width, height, |x, y| noise.sample(x, y))
+    }
+
+    /// Creates a grayscale canvas filled with `noise` that tiles seamlessly
+    pub fn new_with_tileable_noise(width: u32, height: u32, noise: &Noise) -> Canvas {
+        Canvas::new_with_noise_function(width, height, |x, y| {
+            noise.sample_tiled(x, y, width, height)
+        })
+    }
+
+    /// Creates a canvas filled with `noise` where every value is colored by `gradient`
+    pub fn new_with_noise_gradient(
+        width: u32,
+        height: u32,
+        noise: &Noise,
+        gradient: &Gradient,
+    ) -> Canvas {
+        Canvas::new_with_noise(width, height, noise).colorize(gradient)
+    }
+
+    /// Creates a canvas filled with `noise` colored by `gradient` that tiles seamlessly
+    pub fn new_with_tileable_noise_gradient(
+        width: u32,
+        height: u32,
+        noise: &Noise,
+        gradient: &Gradient,
+    ) -> Canvas {
+        Canvas::new_with_tileable_noise(width, height, noise).colorize(gradient)
+    }
+
+    /// Maps the red channel of every pixel through `gradient`
+    fn colorize(&self, gradient: &Gradient) -> Canvas {
+        let dimensions = self.dimensions();
+        let pixels = self
+            .pixels()
+            .map(|p| gradient.color_at(p.r as f32 / 255.0))
+            .collect();
+        Canvas::new_with_data(dimensions.width, dimensions.height, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+    use crate::statistics::Channel;
+
+    const KINDS: [NoiseKind; 4] = [
+        NoiseKind::White,
+        NoiseKind::Perlin,
+        NoiseKind::Simplex,
+        NoiseKind::Worley,
+    ];
+
+    #[test]
+    fn noise_is_seeded() {
+        for kind in KINDS {
+            let noise = Noise::new(kind, 7).set_scale(8.0).set_octaves(3);
+            let a = Canvas::new_with_noise(32, 32, &noise);
+            assert_eq!(a, Canvas::new_with_noise(32, 32, &noise));
+            let other = Canvas::new_with_noise(32, 32, &Noise::new(kind, 8).set_scale(8.0));
+            assert_ne!(a, other);
+            let statistics = a.statistics(Channel::Red).unwrap();
+            assert!(statistics.stddev > 5.0, "{:?} is flat", kind);
+        }
+    }
+
+    #[test]
+    fn seed_from_rng() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        let a = Noise::perlin(0).set_seed_from(&mut StdRng::seed_from_u64(5));
+        let b = Noise::perlin(1).set_seed_from(&mut StdRng::seed_from_u64(5));
+        assert_eq!(a, b);
+        assert_eq!(a.clone().set_seed(9).seed, 9);
+    }
+
+    #[test]
+    fn white_noise_covers_range() {
+        let canvas = Canvas::new_with_noise(64, 64, &Noise::white(1));
+        let statistics = canvas.statistics(Channel::Red).unwrap();
+        assert!(statistics.min < 10 && statistics.max > 245);
+        assert!((statistics.mean - 127.5).abs() < 8.0);
+    }
+
+    #[test]
+    fn perlin_is_smooth() {
+        let noise = Noise::perlin(3).set_scale(16.0);
+        for i in 0..100 {
+            let (x, y) = (i as f32 * 0.37, i as f32 * 0.11);
+            assert!((noise.sample(x, y) - noise.sample(x + 0.1, y)).abs() < 0.05);
+        }
+        // Lattice points are always zero
+        assert_eq!(noise.sample(32.0, 48.0), 0.5);
+    }
+
+    /// Whether the step across the right or bottom edge back to the start is no larger than
+    /// the steps between neighbouring pixels inside `canvas`.
+    fn is_seamless(canvas: &Canvas, horizontal: bool) -> bool {
+        let size = canvas.dimensions();
+        let (along, across) = if horizontal {
+            (size.width, size.height)
+        } else {
+            (size.height, size.width)
+        };
+        let step = |a1: u32, a2: u32, b: u32| {
+            let (p1, p2) = if horizontal {
+                (canvas.get_pixel(a1, b), canvas.get_pixel(a2, b))
+            } else {
+                (canvas.get_pixel(b, a1), canvas.get_pixel(b, a2))
+            };
+            p1.r.abs_diff(p2.r) as f32
+        };
+        let inside: Vec<f32> = (0..across)
+            .flat_map(|b| (1..along).map(move |a| step(a - 1, a, b)))
+            .collect();
+        let seam: Vec<f32> = (0..across).map(|b| step(along - 1, 0, b)).collect();
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+        let max = |values: &[f32]| values.iter().cloned().fold(0.0, f32::max);
+        mean(&seam) <= 2.0 * mean(&inside) && max(&seam) <= max(&inside) + mean(&inside)
+    }
+
+    #[test]
+    fn tileable_noise_wraps() {
+        // White noise has no continuity to check
+        for kind in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley] {
+            let noise = Noise::new(kind, 11).set_scale(10.0).set_octaves(2);
+            let tiled = Canvas::new_with_tileable_noise(40, 30, &noise);
+            let plain = Canvas::new_with_noise(40, 30, &noise);
+            for horizontal in [true, false] {
+                assert!(is_seamless(&tiled, horizontal), "{:?} does not tile", kind);
+                assert!(!is_seamless(&plain, horizontal), "{:?} tiles anyway", kind);
+            }
+        }
+    }
+
+    #[test]
+    fn colorized_noise() {
+        let gradient = Gradient::linear(0.0, 0.0, 1.0, 0.0)
+            .add_stop(0.0, &Colors::BLUE)
+            .add_stop(1.0, &Colors::RED);
+        let canvas = Canvas::new_with_tileable_noise_gradient(16, 16, &Noise::worley(2), &gradient);
+        assert!(canvas.pixels().all(|p| p.g == 0 && p.a == 255));
+    }
+}
@@ -0,0 +1,644 @@
+use crate::canvas::Canvas;
+use crate::pixels::Pixel;
+use crate::utility::overlap_colors;
+use std::f32::consts::PI;
+
+/// Number of sub-scanlines sampled per pixel row when computing coverage.
+const SUBSAMPLES: u32 = 5;
+
+/// Tolerance used when flattening curves into line segments.
+const FLATTEN_TOLERANCE: f32 = 0.05;
+
+/// A single drawing command in a `Path`
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathCommand {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    QuadTo(f32, f32, f32, f32),
+    CubicTo(f32, f32, f32, f32, f32, f32),
+    Close,
+}
+
+/// Rule deciding which parts of a path are considered inside when filling.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+/// How two connected segments of a stroke are joined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// How the ends of an open stroke are drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// Describes how a `Path` is stroked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+}
+
+impl Stroke {
+    /// Creates a new `Stroke` of width `width` with miter joins and butt caps.
+    pub fn new(width: f32) -> Stroke {
+        Stroke {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+        }
+    }
+
+    pub fn set_join(mut self, join: LineJoin) -> Stroke {
+        self.join = join;
+        self
+    }
+
+    pub fn set_cap(mut self, cap: LineCap) -> Stroke {
+        self.cap = cap;
+        self
+    }
+
+    pub fn set_miter_limit(mut self, miter_limit: f32) -> Stroke {
+        self.miter_limit = miter_limit;
+        self
+    }
+}
+
+/// A vector path made of lines and bezier curves. Build it by chaining commands.
+///
+/// # Examples
+///
+/// ```
+/// use pixtra::canvas::Canvas;
+/// use pixtra::path::{FillRule, Path};
+/// use pixtra::pixels::{ColorTrait, Colors};
+///
+/// let triangle = Path::new()
+///     .move_to(2.0, 2.0)
+///     .line_to(18.0, 2.0)
+///     .line_to(10.0, 18.0)
+///     .close();
+/// let canvas = Canvas::new(20, 20).fill_path(&triangle, &Colors::RED, FillRule::NonZero);
+/// ```
+/// For more examples look at examples (examples/paths.rs)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+}
+
+/// A flattened subpath: a list of points and whether it was explicitly closed.
+#[derive(Clone, Debug)]
+struct Polyline {
+    points: Vec<(f32, f32)>,
+    closed: bool,
+}
+
+impl Path {
+    /// Creates a new empty `Path`
+    pub fn new() -> Path {
+        Path { commands: vec![] }
+    }
+
+    /// Starts a new subpath at `(x, y)`
+    pub fn move_to(mut self, x: f32, y: f32) -> Path {
+        self.commands.push(PathCommand::MoveTo(x, y));
+        self
+    }
+
+    /// Adds a straight line from the current point to `(x, y)`
+    pub fn line_to(mut self, x: f32, y: f32) -> Path {
+        self.commands.push(PathCommand::LineTo(x, y));
+        self
+    }
+
+    /// Adds a quadratic bezier curve with control point `(cx, cy)` ending in `(x, y)`
+    pub fn quad_to(mut self, cx: f32, cy: f32, x: f32, y: f32) -> Path {
+        self.commands.push(PathCommand::QuadTo(cx, cy, x, y));
+        self
+    }
+
+    /// Adds a cubic bezier curve with control points `(c1x, c1y)` and `(c2x, c2y)` ending in
+    /// `(x, y)`
+    pub fn cubic_to(mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) -> Path {
+        self.commands
+            .push(PathCommand::CubicTo(c1x, c1y, c2x, c2y, x, y));
+        self
+    }
+
+    /// Closes the current subpath with a line back to its starting point
+    pub fn close(mut self) -> Path {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+
+    /// Creates a closed rectangular path
+    pub fn rect(x: f32, y: f32, width: f32, height: f32) -> Path {
+        Path::new()
+            .move_to(x, y)
+            .line_to(x + width, y)
+            .line_to(x + width, y + height)
+            .line_to(x, y + height)
+            .close()
+    }
+
+    /// Creates a closed circular path made of four cubic curves
+    pub fn circle(cx: f32, cy: f32, radius: f32) -> Path {
+        // Standard constant for approximating a quarter circle with a cubic curve
+        let k = 0.552_284_8 * radius;
+        Path::new()
+            .move_to(cx + radius, cy)
+            .cubic_to(cx + radius, cy + k, cx + k, cy + radius, cx, cy + radius)
+            .cubic_to(cx - k, cy + radius, cx - radius, cy + k, cx - radius, cy)
+            .cubic_to(cx - radius, cy - k, cx - k, cy - radius, cx, cy - radius)
+            .cubic_to(cx + k, cy - radius, cx + radius, cy - k, cx + radius, cy)
+            .close()
+    }
+
+    /// Adds the subpaths of `other` after the subpaths of this path
+    pub fn append(mut self, other: &Path) -> Path {
+        self.commands.extend_from_slice(&other.commands);
+        self
+    }
+
+    /// Returns the commands making up the path
+    pub fn commands(&self) -> &[PathCommand] {
+        &self.commands
+    }
+
+    /// Turns the path into polylines by subdividing all curves.
+    fn flatten(&self) -> Vec<Polyline> {
+        let mut polylines: Vec<Polyline> = vec![];
+        let mut current: Vec<(f32, f32)> = vec![];
+        let mut start = (0.0, 0.0);
+        let mut last = (0.0, 0.0);
+
+        let finish = |current: &mut Vec<(f32, f32)>, polylines: &mut Vec<Polyline>, closed| {
+            if !current.is_empty() {
+                polylines.push(Polyline {
+                    points: std::mem::take(current),
+                    closed,
+                });
+            }
+        };
+
+        for command in self.commands.iter() {
+            match *command {
+                PathCommand::MoveTo(x, y) => {
+                    finish(&mut current, &mut polylines, false);
+                    start = (x, y);
+                    last = start;
+                    current.push(start);
+                }
+                PathCommand::LineTo(x, y) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    last = (x, y);
+                    current.push(last);
+                }
+                PathCommand::QuadTo(cx, cy, x, y) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    let (p0, p1, p2) = (last, (cx, cy), (x, y));
+                    let steps = curve_steps(&[p0, p1, p2]);
+                    for i in 1..=steps {
+                        let t = i as f32 / steps as f32;
+                        let mt = 1.0 - t;
+                        current.push((
+                            mt * mt * p0.0 + 2.0 * mt * t * p1.0 + t * t * p2.0,
+                            mt * mt * p0.1 + 2.0 * mt * t * p1.1 + t * t * p2.1,
+                        ));
+                    }
+                    last = p2;
+                }
+                PathCommand::CubicTo(c1x, c1y, c2x, c2y, x, y) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    let (p0, p1, p2, p3) = (last, (c1x, c1y), (c2x, c2y), (x, y));
+                    let steps = curve_steps(&[p0, p1, p2, p3]);
+                    for i in 1..=steps {
+                        let t = i as f32 / steps as f32;
+                        let mt = 1.0 - t;
+                        let (a, b, c, d) =
+                            (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                        current.push((
+                            a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                            a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+                        ));
+                    }
+                    last = p3;
+                }
+                PathCommand::Close => {
+                    finish(&mut current, &mut polylines, true);
+                    last = start;
+                }
+            }
+        }
+        finish(&mut current, &mut polylines, false);
+
+        // Remove consecutive duplicates so that every segment has a direction
+        for polyline in polylines.iter_mut() {
+            polyline.points.dedup_by(|a, b| distance(*a, *b) < 1e-6);
+            if polyline.closed && polyline.points.len() > 1 {
+                let first = polyline.points[0];
+                if distance(first, polyline.points[polyline.points.len() - 1]) < 1e-6 {
+                    polyline.points.pop();
+                }
+            }
+        }
+        polylines
+    }
+
+    /// Computes the coverage of the filled path for every pixel of a `width x height` area.
+    /// Coverage is between `0.0` and `1.0` and is stored row by row.
+    pub fn coverage(&self, width: u32, height: u32, rule: FillRule) -> Vec<f32> {
+        let polygons: Vec<Vec<(f32, f32)>> = self
+            .flatten()
+            .into_iter()
+            .map(|polyline| polyline.points)
+            .collect();
+        rasterize(&polygons, width, height, rule)
+    }
+
+    /// Computes the coverage of the stroked path for every pixel of a `width x height` area.
+    /// Coverage is between `0.0` and `1.0` and is stored row by row.
+    pub fn stroke_coverage(&self, width: u32, height: u32, stroke: &Stroke) -> Vec<f32> {
+        let polygons = self
+            .flatten()
+            .iter()
+            .flat_map(|polyline| stroke_polyline(polyline, stroke))
+            .map(|polygon| {
+                if signed_area(&polygon) < 0.0 {
+                    polygon.into_iter().rev().collect()
+                } else {
+                    polygon
+                }
+            })
+            .collect::<Vec<Vec<(f32, f32)>>>();
+        rasterize(&polygons, width, height, FillRule::NonZero)
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Number of line segments used for a curve based on the length of its control polygon.
+fn curve_steps(points: &[(f32, f32)]) -> u32 {
+    let length: f32 = points.windows(2).map(|w| distance(w[0], w[1])).sum();
+    ((length / FLATTEN_TOLERANCE).sqrt().ceil() as u32).clamp(1, 256)
+}
+
+fn signed_area(polygon: &[(f32, f32)]) -> f32 {
+    let n = polygon.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f32>()
+        / 2.0
+}
+
+fn circle_polygon(center: (f32, f32), radius: f32) -> Vec<(f32, f32)> {
+    let steps = ((radius * 4.0).ceil() as usize).clamp(8, 128);
+    (0..steps)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / steps as f32;
+            (
+                center.0 + radius * angle.cos(),
+                center.1 + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+/// Turns a single polyline into a set of polygons which together make up the stroke.
+fn stroke_polyline(polyline: &Polyline, stroke: &Stroke) -> Vec<Vec<(f32, f32)>> {
+    let half = stroke.width / 2.0;
+    let points = &polyline.points;
+    let mut polygons = vec![];
+    if half <= 0.0 || points.is_empty() {
+        return polygons;
+    }
+
+    if points.len() == 1 {
+        let p = points[0];
+        match stroke.cap {
+            LineCap::Butt => {}
+            LineCap::Round => polygons.push(circle_polygon(p, half)),
+            LineCap::Square => polygons.push(vec![
+                (p.0 - half, p.1 - half),
+                (p.0 + half, p.1 - half),
+                (p.0 + half, p.1 + half),
+                (p.0 - half, p.1 + half),
+            ]),
+        }
+        return polygons;
+    }
+
+    let closed = polyline.closed && points.len() > 2;
+    let segment_count = if closed {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    let segment = |i: usize| (points[i], points[(i + 1) % points.len()]);
+    let direction = |i: usize| {
+        let (a, b) = segment(i);
+        let length = distance(a, b);
+        ((b.0 - a.0) / length, (b.1 - a.1) / length)
+    };
+
+    for i in 0..segment_count {
+        let (a, b) = segment(i);
+        let d = direction(i);
+        let n = (-d.1 * half, d.0 * half);
+        polygons.push(vec![
+            (a.0 + n.0, a.1 + n.1),
+            (b.0 + n.0, b.1 + n.1),
+            (b.0 - n.0, b.1 - n.1),
+            (a.0 - n.0, a.1 - n.1),
+        ]);
+    }
+
+    // Joins between consecutive segments
+    let joins: Vec<(usize, usize)> = if closed {
+        (0..segment_count)
+            .map(|i| (i, (i + 1) % segment_count))
+            .collect()
+    } else {
+        (0..segment_count - 1).map(|i| (i, i + 1)).collect()
+    };
+    for (incoming, outgoing) in joins {
+        let p = segment(outgoing).0;
+        let (d0, d1) = (direction(incoming), direction(outgoing));
+        let cross = d0.0 * d1.1 - d0.1 * d1.0;
+        if cross.abs() < 1e-6 && d0.0 * d1.0 + d0.1 * d1.1 > 0.0 {
+            continue;
+        }
+        // The outer side of the turn is opposite to the direction we turn in
+        let side = if cross > 0.0 { -1.0 } else { 1.0 };
+        let n0 = (-d0.1 * half * side, d0.0 * half * side);
+        let n1 = (-d1.1 * half * side, d1.0 * half * side);
+        let bevel = vec![p, (p.0 + n0.0, p.1 + n0.1), (p.0 + n1.0, p.1 + n1.1)];
+        match stroke.join {
+            LineJoin::Round => polygons.push(circle_polygon(p, half)),
+            LineJoin::Bevel => polygons.push(bevel),
+            LineJoin::Miter => {
+                let mid = (n0.0 + n1.0, n0.1 + n1.1);
+                let mid_length = (mid.0 * mid.0 + mid.1 * mid.1).sqrt();
+                // cos of half the angle between the two normals
+                let cos_half = mid_length / (2.0 * half);
+                if cos_half < 1e-6 || 1.0 / cos_half > stroke.miter_limit {
+                    polygons.push(bevel);
+                } else {
+                    let scale = half / cos_half / mid_length;
+                    polygons.push(vec![
+                        p,
+                        (p.0 + n0.0, p.1 + n0.1),
+                        (p.0 + mid.0 * scale, p.1 + mid.1 * scale),
+                        (p.0 + n1.0, p.1 + n1.1),
+                    ]);
+                }
+            }
+        }
+    }
+
+    // Caps at the ends of open polylines
+    if !closed {
+        let ends = [
+            (points[0], {
+                let d = direction(0);
+                (-d.0, -d.1)
+            }),
+            (points[points.len() - 1], direction(segment_count - 1)),
+        ];
+        for (p, d) in ends {
+            match stroke.cap {
+                LineCap::Butt => {}
+                LineCap::Round => polygons.push(circle_polygon(p, half)),
+                LineCap::Square => {
+                    let n = (-d.1 * half, d.0 * half);
+                    let e = (p.0 + d.0 * half, p.1 + d.1 * half);
+                    polygons.push(vec![
+                        (p.0 + n.0, p.1 + n.1),
+                        (e.0 + n.0, e.1 + n.1),
+                        (e.0 - n.0, e.1 - n.1),
+                        (p.0 - n.0, p.1 - n.1),
+                    ]);
+                }
+            }
+        }
+    }
+
+    polygons
+}
+
+/// Scanline rasterizer computing per pixel coverage of a set of implicitly closed polygons.
+fn rasterize(polygons: &[Vec<(f32, f32)>], width: u32, height: u32, rule: FillRule) -> Vec<f32> {
+    let mut coverage = vec![0f32; (width * height) as usize];
+    // Edges as (x0, y0, x1, y1, winding) with y0 < y1
+    let edges: Vec<(f32, f32, f32, f32, i32)> = polygons
+        .iter()
+        .filter(|polygon| polygon.len() > 2)
+        .flat_map(|polygon| {
+            (0..polygon.len()).filter_map(move |i| {
+                let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+                if a.1 == b.1 {
+                    None
+                } else if a.1 < b.1 {
+                    Some((a.0, a.1, b.0, b.1, 1))
+                } else {
+                    Some((b.0, b.1, a.0, a.1, -1))
+                }
+            })
+        })
+        .collect();
+    if edges.is_empty() {
+        return coverage;
+    }
+
+    let min_y = edges.iter().map(|e| e.1).fold(f32::MAX, f32::min);
+    let max_y = edges.iter().map(|e| e.3).fold(f32::MIN, f32::max);
+    let first_row = min_y.floor().max(0.0) as u32;
+    let last_row = (max_y.ceil().max(0.0) as u32).min(height);
+
+    let mut crossings: Vec<(f32, i32)> = vec![];
+    for row in first_row..last_row {
+        let line = &mut coverage[(row * width) as usize..((row + 1) * width) as usize];
+        for sub in 0..SUBSAMPLES {
+            let sample_y = row as f32 + (sub as f32 + 0.5) / SUBSAMPLES as f32;
+            crossings.clear();
+            for &(x0, y0, x1, y1, winding) in edges.iter() {
+                if sample_y >= y0 && sample_y < y1 {
+                    let x = x0 + (sample_y - y0) * (x1 - x0) / (y1 - y0);
+                    crossings.push((x, winding));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                let inside = match rule {
+                    FillRule::NonZero => winding != 0,
+                    FillRule::EvenOdd => winding % 2 != 0,
+                };
+                if inside {
+                    add_span(line, pair[0].0, pair[1].0, 1.0 / SUBSAMPLES as f32);
+                }
+            }
+        }
+    }
+    for value in coverage.iter_mut() {
+        *value = value.min(1.0);
+    }
+    coverage
+}
+
+/// Adds `weight` times the horizontal overlap of the span `[start, end)` to every pixel in `line`.
+fn add_span(line: &mut [f32], start: f32, end: f32, weight: f32) {
+    let width = line.len() as f32;
+    let (start, end) = (start.clamp(0.0, width), end.clamp(0.0, width));
+    if end <= start {
+        return;
+    }
+    let first = start.floor() as usize;
+    let last = (end.ceil() as usize).min(line.len());
+    for (x, value) in line.iter_mut().enumerate().take(last).skip(first) {
+        let overlap = end.min(x as f32 + 1.0) - start.max(x as f32);
+        if overlap > 0.0 {
+            *value += overlap * weight;
+        }
+    }
+}
+
+impl Canvas {
+    /// Blends `color` onto the canvas where each pixel is weighted by `coverage`. `coverage` is
+    /// given row by row and must match the size of the canvas.
+    pub fn draw_coverage_mut(&mut self, coverage: &[f32], color: &Pixel) {
+        let dimensions = self.dimensions();
+        for y in 0..dimensions.height {
+            for x in 0..dimensions.width {
+                let amount = coverage[(y * dimensions.width + x) as usize];
+                if amount > 0.0 {
+                    let source = color
+                        .clone()
+                        .set_alpha((color.a as f32 * amount).round() as u8);
+                    let destination = self.get_pixel(x, y);
+                    self.set_pixel_mut(x, y, &overlap_colors(&destination, &source));
+                }
+            }
+        }
+    }
+
+    /// Fills `path` with `color` using fill rule `rule`. Edges are anti-aliased.
+    pub fn fill_path_mut(&mut self, path: &Path, color: &Pixel, rule: FillRule) {
+        let dimensions = self.dimensions();
+        let coverage = path.coverage(dimensions.width, dimensions.height, rule);
+        self.draw_coverage_mut(&coverage, color);
+    }
+
+    /// Fills `path` with `color` using fill rule `rule`. Edges are anti-aliased.
+    pub fn fill_path(mut self, path: &Path, color: &Pixel, rule: FillRule) -> Canvas {
+        self.fill_path_mut(path, color, rule);
+        self
+    }
+
+    /// Strokes the outline of `path` with `color` as described by `stroke`.
+    pub fn stroke_path_mut(&mut self, path: &Path, stroke: &Stroke, color: &Pixel) {
+        let dimensions = self.dimensions();
+        let coverage = path.stroke_coverage(dimensions.width, dimensions.height, stroke);
+        self.draw_coverage_mut(&coverage, color);
+    }
+
+    /// Strokes the outline of `path` with `color` as described by `stroke`.
+    pub fn stroke_path(mut self, path: &Path, stroke: &Stroke, color: &Pixel) -> Canvas {
+        self.stroke_path_mut(path, stroke, color);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+
+    #[test]
+    fn fill_rectangle_is_exact_on_pixel_grid() {
+        let path = Path::rect(2.0, 2.0, 4.0, 3.0);
+        let canvas = Canvas::new(10, 10).fill_path(&path, &Colors::BLACK, FillRule::NonZero);
+        assert_eq!(canvas.count_pixels(&Colors::BLACK), 12);
+        assert_eq!(canvas.count_pixels(&Colors::WHITE), 100 - 12);
+    }
+
+    #[test]
+    fn even_odd_leaves_hole() {
+        let path = Path::rect(0.0, 0.0, 10.0, 10.0)
+            .move_to(2.0, 2.0)
+            .line_to(8.0, 2.0)
+            .line_to(8.0, 8.0)
+            .line_to(2.0, 8.0)
+            .close();
+        let even_odd = path.coverage(10, 10, FillRule::EvenOdd);
+        let non_zero = path.coverage(10, 10, FillRule::NonZero);
+        assert_eq!(even_odd[5 * 10 + 5], 0.0);
+        assert_eq!(non_zero[5 * 10 + 5], 1.0);
+        assert_eq!(even_odd[0], 1.0);
+    }
+
+    #[test]
+    fn half_covered_pixel_is_anti_aliased() {
+        let coverage = Path::rect(0.0, 0.0, 1.5, 1.0).coverage(2, 1, FillRule::NonZero);
+        assert!((coverage[0] - 1.0).abs() < 1e-5);
+        assert!((coverage[1] - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stroke_caps_extend_line() {
+        let path = Path::new().move_to(4.0, 5.0).line_to(16.0, 5.0);
+        let butt = path.stroke_coverage(20, 10, &Stroke::new(2.0));
+        let square = path.stroke_coverage(20, 10, &Stroke::new(2.0).set_cap(LineCap::Square));
+        let butt_sum: f32 = butt.iter().sum();
+        let square_sum: f32 = square.iter().sum();
+        assert!((butt_sum - 24.0).abs() < 1e-3);
+        assert!((square_sum - 28.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn miter_join_fills_corner() {
+        let path = Path::new()
+            .move_to(2.0, 10.0)
+            .line_to(10.0, 10.0)
+            .line_to(10.0, 2.0);
+        let miter = path.stroke_coverage(20, 20, &Stroke::new(2.0));
+        let bevel = path.stroke_coverage(20, 20, &Stroke::new(2.0).set_join(LineJoin::Bevel));
+        // The outer corner pixel is only covered by the miter join
+        assert!(miter[10 * 20 + 10] > 0.99);
+        assert!(bevel[10 * 20 + 10] < 0.6);
+    }
+
+    #[test]
+    fn circle_area_is_close() {
+        let coverage = Path::circle(10.0, 10.0, 6.0).coverage(20, 20, FillRule::NonZero);
+        let area: f32 = coverage.iter().sum();
+        assert!((area - PI * 36.0).abs() < 0.5);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::utility::clamp;
 use rand::distr::{Distribution, Uniform};
+use rand::Rng;
 use std::fmt;
 use std::ops::{Add, Div, Mul, Sub};
 
@@ -273,15 +274,21 @@ impl Pixel {
         )
     }
 
+    /// Creates a random opaque color using the thread RNG. Use `random_with` for reproducible
+    /// colors.
     pub fn random() -> Pixel {
-        let mut rng = rand::rng();
+        Pixel::random_with(&mut rand::rng())
+    }
+
+    /// Creates a random opaque color drawn from `rng`
+    pub fn random_with<R: Rng + ?Sized>(rng: &mut R) -> Pixel {
         let uniform = Uniform::try_from(0..=255);
         match uniform {
             Ok(random) => 
                 Pixel::new(
-                    random.sample(&mut rng).into(),
-                    random.sample(&mut rng).into(),
-                    random.sample(&mut rng).into(),
+                    random.sample(rng),
+                    random.sample(rng),
+                    random.sample(rng),
                     255,
                 ),
             Err(_) => Pixel::new(0,0,0,255,),
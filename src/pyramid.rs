@@ -0,0 +1,298 @@
+use crate::canvas::{Canvas, ImageError};
+use crate::denoise::Planes;
+use crate::mask::Mask;
+use crate::metrics::check_dimensions;
+use crate::pixels::Pixel;
+
+/// The 5-tap binomial kernel of Burt and Adelson used to shrink and grow levels.
+const KERNEL: [f32; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+
+/// A stack of ever smaller versions of a canvas, each half the size of the one before.
+///
+/// Levels of a Gaussian pyramid are blurred copies of the canvas. Levels of a Laplacian
+/// pyramid hold the detail lost between two levels of the Gaussian pyramid, except the last
+/// one which is the smallest Gaussian level. Both can be turned back into the canvas with
+/// `reconstruct`.
+///
+/// # Examples
+///
+/// ```
+/// use pixtra::canvas::Canvas;
+/// use pixtra::pyramid::Pyramid;
+///
+/// let canvas = Canvas::new_random(64, 48, 7);
+/// let pyramid = Pyramid::laplacian(&canvas, 4);
+/// assert_eq!(pyramid.levels(), 4);
+/// assert_eq!(pyramid.reconstruct(), canvas);
+/// ```
+#[derive(Clone)]
+pub struct Pyramid {
+    levels: Vec<Planes>,
+    laplacian: bool,
+}
+
+impl Pyramid {
+    /// Gaussian pyramid of `canvas` with at most `levels` levels, the first being the canvas
+    /// itself. Fewer levels are made once a level is a single pixel.
+    pub fn gaussian(canvas: &Canvas, levels: usize) -> Pyramid {
+        let mut planes = vec![Planes::from_canvas(canvas)];
+        while planes.len() < levels {
+            let last = &planes[planes.len() - 1];
+            if last.width <= 1 && last.height <= 1 {
+                break;
+            }
+            planes.push(reduce_planes(last));
+        }
+        Pyramid {
+            levels: planes,
+            laplacian: false,
+        }
+    }
+
+    /// Laplacian pyramid of `canvas` with at most `levels` levels. Every level except the last
+    /// holds the detail of the Gaussian level that is lost when shrinking it.
+    pub fn laplacian(canvas: &Canvas, levels: usize) -> Pyramid {
+        let gaussian = Pyramid::gaussian(canvas, levels);
+        let mut planes = gaussian.levels.clone();
+        for i in 0..planes.len() - 1 {
+            let expanded = expand_planes(&gaussian.levels[i + 1], &planes[i]);
+            for (channel, expanded) in planes[i].channels.iter_mut().zip(expanded.channels) {
+                for (value, expanded) in channel.iter_mut().zip(expanded) {
+                    *value -= expanded;
+                }
+            }
+        }
+        Pyramid {
+            levels: planes,
+            laplacian: true,
+        }
+    }
+
+    /// Number of levels.
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Returns `true` for a Laplacian pyramid.
+    pub fn is_laplacian(&self) -> bool {
+        self.laplacian
+    }
+
+    /// Level `index` as a canvas, `0` being the largest. Detail levels of a Laplacian pyramid
+    /// have `128` added to every color so no detail is mid grey.
+    pub fn level(&self, index: usize) -> Canvas {
+        let level = &self.levels[index];
+        if !self.laplacian || index == self.levels.len() - 1 {
+            return level.to_canvas();
+        }
+        let pixels = (0..level.width * level.height)
+            .map(|i| {
+                let shifted = |c: usize| (level.channels[c][i] + 128.0).round();
+                Pixel::from(shifted(0), shifted(1), shifted(2), 255.0)
+            })
+            .collect();
+        Canvas::new_with_data(level.width as u32, level.height as u32, pixels)
+    }
+
+    /// Turns the pyramid back into a canvas of the size of the first level. A Laplacian pyramid
+    /// is collapsed by growing every level and adding the detail of the level above it.
+    pub fn reconstruct(&self) -> Canvas {
+        if !self.laplacian {
+            return self.levels[0].to_canvas();
+        }
+        let mut result = self.levels[self.levels.len() - 1].clone();
+        for detail in self.levels.iter().rev().skip(1) {
+            result = expand_planes(&result, detail);
+            for (channel, detail) in result.channels.iter_mut().zip(detail.channels.iter()) {
+                for (value, detail) in channel.iter_mut().zip(detail) {
+                    *value += detail;
+                }
+            }
+        }
+        result.to_canvas()
+    }
+}
+
+/// Index `i` clamped to `0..len`.
+fn clamped(i: i64, len: usize) -> usize {
+    i.clamp(0, len as i64 - 1) as usize
+}
+
+/// Blurs `values` with `KERNEL` and keeps every second pixel in both directions.
+fn reduce(values: &[f32], width: usize, height: usize) -> (Vec<f32>, usize, usize) {
+    let (new_width, new_height) = (width.div_ceil(2), height.div_ceil(2));
+    let mut rows: Vec<f32> = Vec::with_capacity(new_width * height);
+    for y in 0..height {
+        for x in 0..new_width {
+            let sum = KERNEL.iter().enumerate().map(|(k, weight)| {
+                weight * values[y * width + clamped(2 * x as i64 + k as i64 - 2, width)]
+            });
+            rows.push(sum.sum());
+        }
+    }
+    let mut result: Vec<f32> = Vec::with_capacity(new_width * new_height);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let sum = KERNEL.iter().enumerate().map(|(k, weight)| {
+                weight * rows[clamped(2 * y as i64 + k as i64 - 2, height) * new_width + x]
+            });
+            result.push(sum.sum());
+        }
+    }
+    (result, new_width, new_height)
+}
+
+/// Grows `values` to `new_width` and `new_height` by filling in every second pixel and
+/// interpolating with twice `KERNEL`, the inverse of `reduce`.
+fn expand(
+    values: &[f32],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    new_height: usize,
+) -> Vec<f32> {
+    // Only the taps landing on an existing pixel count, which are half the kernel
+    let taps = |x: usize| {
+        KERNEL.iter().enumerate().filter_map(move |(k, weight)| {
+            let shifted = x as i64 + 2 - k as i64;
+            (shifted % 2 == 0).then_some((shifted / 2, 2.0 * weight))
+        })
+    };
+    let mut rows: Vec<f32> = Vec::with_capacity(new_width * height);
+    for y in 0..height {
+        for x in 0..new_width {
+            let sum = taps(x).map(|(i, weight)| weight * values[y * width + clamped(i, width)]);
+            rows.push(sum.sum());
+        }
+    }
+    let mut result: Vec<f32> = Vec::with_capacity(new_width * new_height);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let sum = taps(y).map(|(i, weight)| weight * rows[clamped(i, height) * new_width + x]);
+            result.push(sum.sum());
+        }
+    }
+    result
+}
+
+fn reduce_planes(planes: &Planes) -> Planes {
+    let (width, height) = (planes.width.div_ceil(2), planes.height.div_ceil(2));
+    let mut result = planes.with_size(width, height);
+    for (channel, values) in result.channels.iter_mut().zip(planes.channels.iter()) {
+        *channel = reduce(values, planes.width, planes.height).0;
+    }
+    result
+}
+
+/// `planes` grown to the size of `like`.
+fn expand_planes(planes: &Planes, like: &Planes) -> Planes {
+    let mut result = like.like();
+    for (channel, values) in result.channels.iter_mut().zip(planes.channels.iter()) {
+        *channel = expand(values, planes.width, planes.height, like.width, like.height);
+    }
+    result
+}
+
+impl Canvas {
+    /// Blends `other` into the canvas where `mask` is set with Laplacian pyramids of `levels`
+    /// levels. Coarse detail is mixed over a wide area and fine detail over a narrow one, so
+    /// the seam disappears without blurring either image. Fails if `other` or `mask` differ in
+    /// size from the canvas.
+    pub fn laplacian_blend(
+        &self,
+        other: &Canvas,
+        mask: &Mask,
+        levels: usize,
+    ) -> Result<Canvas, ImageError> {
+        check_dimensions(self, other)?;
+        let (dimensions, mask_dimensions) = (self.dimensions(), mask.dimensions());
+        if dimensions != mask_dimensions {
+            return Err(ImageError::Parameter(format!(
+                "Mask dimensions differ: {}x{} and {}x{}",
+                dimensions.width, dimensions.height, mask_dimensions.width, mask_dimensions.height
+            )));
+        }
+
+        let mut result = Pyramid::laplacian(self, levels);
+        let other = Pyramid::laplacian(other, levels);
+        let mut weights = mask.values().to_vec();
+        let (mut width, mut height) = (dimensions.width as usize, dimensions.height as usize);
+        for (level, other) in result.levels.iter_mut().zip(other.levels.iter()) {
+            for (channel, other) in level.channels.iter_mut().zip(other.channels.iter()) {
+                for ((value, other), weight) in channel.iter_mut().zip(other).zip(&weights) {
+                    *value += (other - *value) * weight;
+                }
+            }
+            (weights, width, height) = reduce(&weights, width, height);
+        }
+        Ok(result.reconstruct())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+
+    #[test]
+    fn levels_halve_in_size() {
+        let pyramid = Pyramid::gaussian(&Canvas::new_random(33, 20, 1), 4);
+        let sizes: Vec<(u32, u32)> = (0..pyramid.levels())
+            .map(|i| {
+                let size = pyramid.level(i).dimensions();
+                (size.width, size.height)
+            })
+            .collect();
+        assert_eq!(sizes, vec![(33, 20), (17, 10), (9, 5), (5, 3)]);
+        let small = Pyramid::gaussian(&Canvas::new_random(3, 2, 1), 10);
+        assert_eq!(small.levels(), 3);
+    }
+
+    #[test]
+    fn gaussian_levels_keep_flat_colors() {
+        let canvas = Canvas::new_with_background(20, 12, Pixel::new(40, 120, 200, 255));
+        let pyramid = Pyramid::gaussian(&canvas, 3);
+        assert_eq!(
+            pyramid.level(2).get_pixel(1, 1),
+            Pixel::new(40, 120, 200, 255)
+        );
+        assert_eq!(pyramid.reconstruct(), canvas);
+    }
+
+    #[test]
+    fn laplacian_pyramids_reconstruct_the_canvas() {
+        let canvas = Canvas::new_random(37, 21, 5);
+        let pyramid = Pyramid::laplacian(&canvas, 5);
+        assert!(pyramid.is_laplacian());
+        assert_eq!(pyramid.reconstruct(), canvas);
+        let flat = Pyramid::laplacian(&Canvas::new_with_background(8, 8, Colors::WHITE), 3);
+        assert_eq!(
+            flat.level(0).get_pixel(3, 3),
+            Pixel::new(128, 128, 128, 255)
+        );
+        assert_eq!(flat.level(2).get_pixel(1, 1), Colors::WHITE);
+    }
+
+    #[test]
+    fn blends_without_a_hard_edge() {
+        let red = Canvas::new_with_background(128, 16, Colors::RED);
+        let blue = Canvas::new_with_background(128, 16, Colors::BLUE);
+        let mut mask = Mask::new(128, 16);
+        for y in 0..16 {
+            for x in 64..128 {
+                mask.set(x, y, 1.0);
+            }
+        }
+        let blended = red.laplacian_blend(&blue, &mask, 4).unwrap();
+        assert_eq!(blended.get_pixel(0, 8), Colors::RED);
+        assert_eq!(blended.get_pixel(127, 8), Colors::BLUE);
+        let (left, right) = (blended.get_pixel(62, 8), blended.get_pixel(65, 8));
+        assert!(left.r > 0 && left.r < 255 && left.b > 0);
+        assert!(right.r > 0 && right.b < 255 && right.b > left.b);
+
+        let one_level = red.laplacian_blend(&blue, &mask, 1).unwrap();
+        assert_eq!(one_level, red.merge_masked(&blue, &mask));
+        assert!(red.laplacian_blend(&blue, &Mask::new(10, 10), 3).is_err());
+        assert!(red.laplacian_blend(&Canvas::new(10, 10), &mask, 3).is_err());
+    }
+}
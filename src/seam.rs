@@ -0,0 +1,351 @@
+use crate::canvas::Canvas;
+use crate::mask::Mask;
+use crate::pixels::Pixel;
+use crate::utility::to_grey_lumiosity;
+
+/// Energy added for fully protected pixels and subtracted for pixels that should be removed,
+/// far above the largest gradient magnitude.
+const BIAS_ENERGY: f32 = 100_000.0;
+
+/// Options for `seam_carve_with`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SeamCarveOptions {
+    /// Pixels that seams avoid, so they are kept as they are. Defaults to `None`.
+    pub protect: Option<Mask>,
+    /// Pixels that seams prefer. They are carved away first, even past the target width, after
+    /// which seams are inserted again to reach the target size. Defaults to `None`.
+    pub remove: Option<Mask>,
+}
+
+impl SeamCarveOptions {
+    pub fn new() -> SeamCarveOptions {
+        SeamCarveOptions {
+            protect: None,
+            remove: None,
+        }
+    }
+
+    pub fn set_protect(mut self, protect: Mask) -> SeamCarveOptions {
+        self.protect = Some(protect);
+        self
+    }
+
+    pub fn set_remove(mut self, remove: Mask) -> SeamCarveOptions {
+        self.remove = Some(remove);
+        self
+    }
+}
+
+/// Pixels being carved with a bias per pixel between `-1.0` (remove) and `1.0` (protect).
+#[derive(Clone)]
+struct Carver {
+    width: usize,
+    height: usize,
+    pixels: Vec<Pixel>,
+    bias: Vec<f32>,
+}
+
+impl Carver {
+    fn new(canvas: &Canvas, protect: Option<&Mask>, remove: Option<&Mask>) -> Carver {
+        let dimensions = canvas.dimensions();
+        let (width, height) = (dimensions.width, dimensions.height);
+        let coverage = |mask: Option<&Mask>, x: u32, y: u32| mask.map_or(0.0, |m| m.get(x, y));
+        let bias = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| coverage(protect, x, y) - coverage(remove, x, y))
+            .collect();
+        Carver {
+            width: width as usize,
+            height: height as usize,
+            pixels: canvas.pixels().cloned().collect(),
+            bias,
+        }
+    }
+
+    fn to_canvas(&self) -> Canvas {
+        Canvas::new_with_data(self.width as u32, self.height as u32, self.pixels.clone())
+    }
+
+    /// The protected part of the bias as a mask.
+    fn protect_mask(&self) -> Mask {
+        let values = self.bias.iter().map(|b| b.max(0.0)).collect();
+        Mask::new_with_data(self.width as u32, self.height as u32, values)
+    }
+
+    /// Gradient magnitude of the grey values plus the bias of every pixel.
+    fn energy(&self) -> Vec<f32> {
+        let grey: Vec<f32> = self
+            .pixels
+            .iter()
+            .map(|p| to_grey_lumiosity(p).r as f32 * p.a as f32 / 255.0)
+            .collect();
+        let (width, height) = (self.width, self.height);
+        let at = |x: usize, y: usize| grey[y * width + x];
+        let mut energy = Vec::with_capacity(grey.len());
+        for y in 0..height {
+            for x in 0..width {
+                let dx = at((x + 1).min(width - 1), y) - at(x.saturating_sub(1), y);
+                let dy = at(x, (y + 1).min(height - 1)) - at(x, y.saturating_sub(1));
+                energy.push(dx.hypot(dy) / 2.0 + self.bias[y * width + x] * BIAS_ENERGY);
+            }
+        }
+        energy
+    }
+
+    /// The column of every row of the connected top to bottom path with the least energy.
+    fn find_seam(&self) -> Vec<usize> {
+        let (width, height) = (self.width, self.height);
+        let mut cost = self.energy();
+        for y in 1..height {
+            for x in 0..width {
+                let above = (x.saturating_sub(1)..=(x + 1).min(width - 1))
+                    .map(|px| cost[(y - 1) * width + px])
+                    .fold(f32::INFINITY, f32::min);
+                cost[y * width + x] += above;
+            }
+        }
+        // Ties go to the leftmost column
+        let cheapest = |y: usize, columns: std::ops::RangeInclusive<usize>| {
+            columns
+                .min_by(|&a, &b| cost[y * width + a].total_cmp(&cost[y * width + b]))
+                .unwrap_or(0)
+        };
+        let mut seam = vec![0; height];
+        seam[height - 1] = cheapest(height - 1, 0..=width - 1);
+        for y in (0..height - 1).rev() {
+            let x = seam[y + 1];
+            seam[y] = cheapest(y, x.saturating_sub(1)..=(x + 1).min(width - 1));
+        }
+        seam
+    }
+
+    fn remove_seam(&mut self, seam: &[usize]) {
+        let width = self.width;
+        let keep = |i: &usize| i % width != seam[i / width];
+        self.pixels = (0..self.pixels.len())
+            .filter(keep)
+            .map(|i| self.pixels[i].clone())
+            .collect();
+        self.bias = (0..self.bias.len())
+            .filter(keep)
+            .map(|i| self.bias[i])
+            .collect();
+        self.width -= 1;
+    }
+
+    /// Widens by `count` columns, at most the current width, by doubling the `count` seams
+    /// that would be removed first. New pixels are the average of their neighbours.
+    fn insert_seams(&mut self, count: usize) {
+        let (width, height) = (self.width, self.height);
+        let mut copy = self.clone();
+        let mut columns: Vec<Vec<usize>> = vec![(0..width).collect(); height];
+        let mut doubled = vec![false; width * height];
+        for _ in 0..count.min(width) {
+            let seam = copy.find_seam();
+            for (y, &x) in seam.iter().enumerate() {
+                doubled[y * width + columns[y].remove(x)] = true;
+            }
+            if copy.width > 1 {
+                copy.remove_seam(&seam);
+            }
+        }
+
+        let mut pixels = Vec::with_capacity(self.pixels.len() + count * height);
+        let mut bias = Vec::with_capacity(pixels.capacity());
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                pixels.push(self.pixels[i].clone());
+                bias.push(self.bias[i]);
+                if doubled[i] {
+                    let j = if x + 1 < width {
+                        i + 1
+                    } else {
+                        i.saturating_sub(1)
+                    };
+                    pixels.push(average(&self.pixels[i], &self.pixels[j]));
+                    bias.push((self.bias[i] + self.bias[j]) / 2.0);
+                }
+            }
+        }
+        self.width = pixels.len() / height;
+        self.pixels = pixels;
+        self.bias = bias;
+    }
+
+    /// Removes or inserts seams until the width is `target`. Pixels to remove are carved away
+    /// first.
+    fn carve_to(&mut self, target: usize) {
+        let target = target.max(1);
+        // Seams go through pixels to remove first, so stop at the first one that misses them
+        while self.width > 1 {
+            let seam = self.find_seam();
+            let removes = seam
+                .iter()
+                .enumerate()
+                .any(|(y, &x)| self.bias[y * self.width + x] < 0.0);
+            if !removes {
+                break;
+            }
+            self.remove_seam(&seam);
+        }
+        while self.width > target {
+            let seam = self.find_seam();
+            self.remove_seam(&seam);
+        }
+        while self.width < target {
+            self.insert_seams(target - self.width);
+        }
+    }
+}
+
+fn average(p1: &Pixel, p2: &Pixel) -> Pixel {
+    let mean = |a: u8, b: u8| ((a as f32 + b as f32) / 2.0).round();
+    Pixel::from(
+        mean(p1.r, p2.r),
+        mean(p1.g, p2.g),
+        mean(p1.b, p2.b),
+        mean(p1.a, p2.a),
+    )
+}
+
+impl Canvas {
+    /// Content-aware resize to `target_width x target_height`. Instead of scaling, connected
+    /// paths of pixels with little detail (seams) are removed or doubled, so the objects in the
+    /// image keep their shape. See `seam_carve_with`.
+    pub fn seam_carve(self, target_width: u32, target_height: u32) -> Canvas {
+        self.seam_carve_with(target_width, target_height, &SeamCarveOptions::new())
+    }
+
+    pub fn seam_carve_mut(&mut self, target_width: u32, target_height: u32) {
+        *self = self.clone().seam_carve(target_width, target_height);
+    }
+
+    /// Content-aware resize to `target_width x target_height` where the energy of a pixel is
+    /// its gradient magnitude. Vertical seams are carved first, then the canvas is rotated to
+    /// carve the horizontal ones. Seams avoid the pixels of `options.protect` and go through
+    /// those of `options.remove`. A target of `0` is treated as `1`, as a canvas keeps at least
+    /// one row and column.
+    pub fn seam_carve_with(
+        self,
+        target_width: u32,
+        target_height: u32,
+        options: &SeamCarveOptions,
+    ) -> Canvas {
+        let mut carver = Carver::new(&self, options.protect.as_ref(), options.remove.as_ref());
+        carver.carve_to(target_width as usize);
+        if carver.height == target_height.max(1) as usize {
+            return carver.to_canvas();
+        }
+
+        let protect = Mask::from_canvas(&carver.protect_mask().to_canvas().rotate90());
+        let mut carver = Carver::new(&carver.to_canvas().rotate90(), Some(&protect), None);
+        carver.carve_to(target_height as usize);
+        carver.to_canvas().rotate270()
+    }
+
+    pub fn seam_carve_with_mut(
+        &mut self,
+        target_width: u32,
+        target_height: u32,
+        options: &SeamCarveOptions,
+    ) {
+        *self = self
+            .clone()
+            .seam_carve_with(target_width, target_height, options);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::{FillRule, Path};
+    use crate::pixels::{ColorTrait, Colors};
+
+    /// A white canvas with a red square, which is the only detail.
+    fn square() -> Canvas {
+        Canvas::new_with_background(40, 30, Colors::WHITE).draw_square(15, 10, 8, 8, &Colors::RED)
+    }
+
+    fn count(canvas: &Canvas, color: Pixel) -> usize {
+        canvas.pixels().filter(|&p| *p == color).count()
+    }
+
+    #[test]
+    fn shrinks_around_detail() {
+        let carved = square().seam_carve(25, 20);
+        let dimensions = carved.dimensions();
+        assert_eq!((dimensions.width, dimensions.height), (25, 20));
+        assert_eq!(count(&carved, Colors::RED), 64);
+    }
+
+    #[test]
+    fn grows_by_doubling_seams() {
+        let carved = square().seam_carve(50, 36);
+        let dimensions = carved.dimensions();
+        assert_eq!((dimensions.width, dimensions.height), (50, 36));
+        assert_eq!(count(&carved, Colors::RED), 64);
+        let unchanged = square().seam_carve(40, 30);
+        assert_eq!(unchanged, square());
+    }
+
+    #[test]
+    fn protected_pixels_are_kept() {
+        // Carving a flat canvas would go through the left half of the stripes
+        let stripes = Canvas::new_with_background(20, 10, Colors::WHITE).draw_square(
+            0,
+            0,
+            10,
+            10,
+            &Colors::BLUE,
+        );
+        let mut protect = Mask::new(20, 10);
+        for y in 0..10 {
+            for x in 0..10 {
+                protect.set(x, y, 1.0);
+            }
+        }
+        let options = SeamCarveOptions::new().set_protect(protect);
+        let carved = stripes.clone().seam_carve_with(14, 8, &options);
+        assert_eq!(count(&carved, Colors::BLUE), 80);
+        assert_eq!(count(&carved, Colors::WHITE), 4 * 8);
+    }
+
+    #[test]
+    fn removed_pixels_are_erased() {
+        let mut remove = Mask::new(40, 30);
+        for y in 10..18 {
+            for x in 15..23 {
+                remove.set(x, y, 1.0);
+            }
+        }
+        let options = SeamCarveOptions::new().set_remove(remove);
+        let mut carved = square();
+        carved.seam_carve_with_mut(40, 30, &options);
+        let dimensions = carved.dimensions();
+        assert_eq!((dimensions.width, dimensions.height), (40, 30));
+        assert_eq!(count(&carved, Colors::RED), 0);
+    }
+
+    #[test]
+    fn feathered_removal_stops_when_the_region_is_gone() {
+        let remove = Mask::from_path(40, 30, &Path::rect(15.0, 10.0, 8.0, 8.0), FillRule::NonZero)
+            .feather(2.0);
+        let options = SeamCarveOptions::new().set_remove(remove);
+        let carved = square().seam_carve_with(40, 30, &options);
+        assert_eq!(count(&carved, Colors::RED), 0);
+        assert!(count(&carved, Colors::WHITE) >= 40 * 30 - 100);
+
+        // A faint pixel that seams do not cross leaves the rest of the canvas alone
+        let noisy = Canvas::new_with_background(30, 20, Colors::WHITE).draw_subimage(
+            5,
+            0,
+            &Canvas::new_random(20, 20, 3),
+        );
+        let mut faint = Mask::new(30, 20);
+        faint.set(15, 10, 0.001);
+        let options = SeamCarveOptions::new().set_remove(faint);
+        let carved = noisy.clone().seam_carve_with(30, 20, &options);
+        assert!(count(&carved, Colors::WHITE) >= count(&noisy, Colors::WHITE) - 20);
+    }
+}
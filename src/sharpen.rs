@@ -0,0 +1,335 @@
+use crate::blend::BlendMode;
+use crate::blur::{gaussian_values, BlurOptions};
+use crate::canvas::Canvas;
+use crate::colorspace::{oklab_to_pixel, pixel_to_oklab};
+use crate::denoise::Planes;
+use crate::pixels::Pixel;
+
+impl Canvas {
+    /// Differences between the premultiplied color channels and their gaussian blur, unscaled
+    /// by alpha so they are in channel values between `-255` and `255`.
+    fn high_frequencies(&self, radius: f32, options: &BlurOptions) -> (Planes, [Vec<f32>; 3]) {
+        let planes = Planes::from_canvas_with(self, options.linear_light);
+        let (width, height) = (planes.width, planes.height);
+        let mut details: [Vec<f32>; 3] = Default::default();
+        for (detail, channel) in details.iter_mut().zip(planes.channels.iter()) {
+            let blurred = gaussian_values(channel, width, height, radius, options.edge_mode);
+            *detail = channel
+                .iter()
+                .zip(blurred)
+                .zip(planes.channels[3].iter())
+                .map(|((c, b), a)| if *a > 0.0 { (c - b) * 255.0 / a } else { 0.0 })
+                .collect();
+        }
+        (planes, details)
+    }
+
+    /// Sharpens by adding `amount` times the difference between the canvas and its gaussian
+    /// blur with standard deviation `radius`. Pixels whose largest channel difference is below
+    /// `threshold` are left alone, which keeps noise and smooth areas from being sharpened.
+    /// Alpha is kept and channels saturate instead of overflowing.
+    pub fn unsharp_mask(mut self, radius: f32, amount: f32, threshold: u8) -> Canvas {
+        self.unsharp_mask_mut(radius, amount, threshold);
+        self
+    }
+
+    /// Sharpens by adding `amount` times the difference between the canvas and its gaussian
+    /// blur with standard deviation `radius`. See `unsharp_mask`.
+    pub fn unsharp_mask_mut(&mut self, radius: f32, amount: f32, threshold: u8) {
+        self.unsharp_mask_with_mut(radius, amount, threshold, &BlurOptions::new());
+    }
+
+    /// Unsharp mask with custom border handling and blending for the blur. See `unsharp_mask`.
+    pub fn unsharp_mask_with(
+        mut self,
+        radius: f32,
+        amount: f32,
+        threshold: u8,
+        options: &BlurOptions,
+    ) -> Canvas {
+        self.unsharp_mask_with_mut(radius, amount, threshold, options);
+        self
+    }
+
+    /// Unsharp mask with custom border handling and blending for the blur. See `unsharp_mask`.
+    pub fn unsharp_mask_with_mut(
+        &mut self,
+        radius: f32,
+        amount: f32,
+        threshold: u8,
+        options: &BlurOptions,
+    ) {
+        if radius <= 0.0 {
+            return;
+        }
+        let (mut planes, details) = self.high_frequencies(radius, options);
+        for i in 0..planes.width * planes.height {
+            let alpha = planes.channels[3][i];
+            let largest = details.iter().map(|d| d[i].abs()).fold(0.0, f32::max);
+            if largest < threshold as f32 || alpha <= 0.0 {
+                continue;
+            }
+            for (channel, detail) in planes.channels.iter_mut().zip(details.iter()) {
+                let sharpened = channel[i] + amount * detail[i] * alpha / 255.0;
+                channel[i] = sharpened.clamp(0.0, alpha);
+            }
+        }
+        *self = planes.to_canvas();
+    }
+
+    /// Keeps only the details smaller than roughly `radius` pixels. Flat areas become middle
+    /// grey `(128, 128, 128)` and alpha is kept.
+    pub fn high_pass(mut self, radius: f32) -> Canvas {
+        self.high_pass_mut(radius);
+        self
+    }
+
+    /// Keeps only the details smaller than roughly `radius` pixels. See `high_pass`.
+    pub fn high_pass_mut(&mut self, radius: f32) {
+        self.high_pass_with_mut(radius, &BlurOptions::new());
+    }
+
+    /// High pass with custom border handling and blending for the blur. See `high_pass`.
+    pub fn high_pass_with(mut self, radius: f32, options: &BlurOptions) -> Canvas {
+        self.high_pass_with_mut(radius, options);
+        self
+    }
+
+    /// High pass with custom border handling and blending for the blur. See `high_pass`.
+    pub fn high_pass_with_mut(&mut self, radius: f32, options: &BlurOptions) {
+        let (_, details) = self.high_frequencies(radius, options);
+        let pixels = self
+            .pixels()
+            .enumerate()
+            .map(|(i, p)| {
+                let grey = |d: &Vec<f32>| (128.0 + d[i]).round();
+                Pixel::from(
+                    grey(&details[0]),
+                    grey(&details[1]),
+                    grey(&details[2]),
+                    p.a as f32,
+                )
+            })
+            .collect();
+        let dimensions = self.dimensions();
+        *self = Canvas::new_with_data(dimensions.width, dimensions.height, pixels);
+    }
+
+    /// Sharpens by overlaying the `high_pass` of the canvas on itself, with the details scaled
+    /// by `amount`. Gives a softer result than `unsharp_mask` that does not clip highlights.
+    pub fn high_pass_sharpen(mut self, radius: f32, amount: f32) -> Canvas {
+        self.high_pass_sharpen_mut(radius, amount);
+        self
+    }
+
+    /// Sharpens by overlaying the `high_pass` of the canvas on itself. See
+    /// `high_pass_sharpen`.
+    pub fn high_pass_sharpen_mut(&mut self, radius: f32, amount: f32) {
+        self.high_pass_sharpen_with_mut(radius, amount, &BlurOptions::new());
+    }
+
+    /// High pass sharpening with custom border handling and blending for the blur. See
+    /// `high_pass_sharpen`.
+    pub fn high_pass_sharpen_with(
+        mut self,
+        radius: f32,
+        amount: f32,
+        options: &BlurOptions,
+    ) -> Canvas {
+        self.high_pass_sharpen_with_mut(radius, amount, options);
+        self
+    }
+
+    /// High pass sharpening with custom border handling and blending for the blur. See
+    /// `high_pass_sharpen`.
+    pub fn high_pass_sharpen_with_mut(&mut self, radius: f32, amount: f32, options: &BlurOptions) {
+        let (_, details) = self.high_frequencies(radius, options);
+        let pixels = self
+            .pixels()
+            .enumerate()
+            .map(|(i, p)| {
+                let detail = |d: &Vec<f32>| (0.5 + amount * d[i] / 255.0).clamp(0.0, 1.0);
+                let backdrop = (p.r as f32 / 255.0, p.g as f32 / 255.0, p.b as f32 / 255.0);
+                let source = (
+                    detail(&details[0]),
+                    detail(&details[1]),
+                    detail(&details[2]),
+                );
+                let (r, g, b) = BlendMode::Overlay.blend(backdrop, source);
+                Pixel::from(
+                    (r * 255.0).round(),
+                    (g * 255.0).round(),
+                    (b * 255.0).round(),
+                    p.a as f32,
+                )
+            })
+            .collect();
+        let dimensions = self.dimensions();
+        *self = Canvas::new_with_data(dimensions.width, dimensions.height, pixels);
+    }
+
+    /// Increases local contrast of the midtones, like the clarity slider of photo editors.
+    /// Lightness is compared in Oklab to a gaussian blur with standard deviation `radius`,
+    /// which should be large, e.g. `20.0`. `amount` around `0.5` is subtle and negative values
+    /// soften the image instead. Shadows, highlights and colors are kept.
+    pub fn clarity(mut self, radius: f32, amount: f32) -> Canvas {
+        self.clarity_mut(radius, amount);
+        self
+    }
+
+    /// Increases local contrast of the midtones. See `clarity`.
+    pub fn clarity_mut(&mut self, radius: f32, amount: f32) {
+        self.clarity_with_mut(radius, amount, &BlurOptions::new());
+    }
+
+    /// Clarity with custom border handling for the blur. Lightness is always compared in
+    /// Oklab, so `linear_light` is ignored. See `clarity`.
+    pub fn clarity_with(mut self, radius: f32, amount: f32, options: &BlurOptions) -> Canvas {
+        self.clarity_with_mut(radius, amount, options);
+        self
+    }
+
+    /// Clarity with custom border handling for the blur. See `clarity_with`.
+    pub fn clarity_with_mut(&mut self, radius: f32, amount: f32, options: &BlurOptions) {
+        let dimensions = self.dimensions();
+        let (width, height) = (dimensions.width as usize, dimensions.height as usize);
+        let labs: Vec<(f32, f32, f32)> = self.pixels().map(pixel_to_oklab).collect();
+        let alphas: Vec<f32> = self.pixels().map(|p| p.a as f32 / 255.0).collect();
+        let weighted: Vec<f32> = labs.iter().zip(&alphas).map(|(lab, a)| lab.0 * a).collect();
+
+        // Weighing by alpha keeps transparent pixels out of the local average
+        let blurred = gaussian_values(&weighted, width, height, radius, options.edge_mode);
+        let coverage = gaussian_values(&alphas, width, height, radius, options.edge_mode);
+
+        let pixels = self
+            .pixels()
+            .enumerate()
+            .map(|(i, p)| {
+                let (l, a, b) = labs[i];
+                if coverage[i] <= 0.0 || p.a == 0 {
+                    return p.clone();
+                }
+                let detail = l - blurred[i] / coverage[i];
+                let midtones = (1.0 - (2.0 * l - 1.0).powi(2)).max(0.0);
+                oklab_to_pixel(l + amount * detail * midtones, a, b, p.a)
+            })
+            .collect();
+        *self = Canvas::new_with_data(dimensions.width, dimensions.height, pixels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blur::EdgeMode;
+    use crate::pixels::{ColorTrait, Colors};
+
+    fn grey(value: u8) -> Pixel {
+        Pixel::new(value, value, value, 255)
+    }
+
+    /// A vertical edge between `left` and `right` in the middle of the canvas.
+    fn edge(left: u8, right: u8) -> Canvas {
+        Canvas::new_with_background(20, 8, grey(right)).draw_square(0, 0, 10, 8, &grey(left))
+    }
+
+    #[test]
+    fn flat_canvases_stay_flat() {
+        let canvas = Canvas::new_with_background(12, 12, Pixel::new(40, 90, 200, 180));
+        assert_eq!(canvas.clone().unsharp_mask(2.0, 1.5, 0), canvas);
+        assert_eq!(canvas.clone().high_pass_sharpen(2.0, 1.0), canvas);
+        assert_eq!(canvas.clone().clarity(5.0, 1.0), canvas);
+        let grey_canvas = Canvas::new_with_background(12, 12, Pixel::new(128, 128, 128, 180));
+        assert_eq!(canvas.high_pass(2.0), grey_canvas);
+    }
+
+    #[test]
+    fn unsharp_mask_increases_edge_contrast() {
+        let sharpened = edge(100, 150).unsharp_mask(1.5, 1.0, 0);
+        assert!(sharpened.get_pixel(9, 4).r < 100);
+        assert!(sharpened.get_pixel(10, 4).r > 150);
+        assert_eq!(sharpened.get_pixel(0, 4).r, 100);
+        assert_eq!(sharpened.get_pixel(19, 4).r, 150);
+    }
+
+    #[test]
+    fn unsharp_mask_threshold_skips_small_differences() {
+        let canvas = edge(100, 104);
+        assert_eq!(canvas.clone().unsharp_mask(1.5, 2.0, 10), canvas);
+        assert_ne!(canvas.clone().unsharp_mask(1.5, 2.0, 0), canvas);
+    }
+
+    #[test]
+    fn sharpening_saturates_instead_of_overflowing() {
+        let canvas = edge(5, 250);
+        let sharpened = canvas.clone().unsharp_mask(2.0, 5.0, 0);
+        assert_eq!(sharpened.get_pixel(9, 4), grey(0));
+        assert_eq!(sharpened.get_pixel(10, 4), grey(255));
+        let sharpened = canvas.high_pass_sharpen(2.0, 5.0);
+        assert!(sharpened.get_pixel(9, 4).r <= 5);
+        assert!(sharpened.get_pixel(10, 4).r >= 250);
+    }
+
+    #[test]
+    fn high_pass_shows_details() {
+        let high_pass = edge(100, 150).high_pass(1.5);
+        assert_eq!(high_pass.get_pixel(0, 4), grey(128));
+        assert!(high_pass.get_pixel(9, 4).r < 128);
+        assert!(high_pass.get_pixel(10, 4).r > 128);
+    }
+
+    #[test]
+    fn clarity_boosts_midtone_contrast() {
+        let canvas = edge(110, 140);
+        let clearer = canvas.clone().clarity(4.0, 1.0);
+        assert!(clearer.get_pixel(9, 4).r < 110);
+        assert!(clearer.get_pixel(10, 4).r > 140);
+        let softer = canvas.clarity(4.0, -1.0);
+        assert!(softer.get_pixel(9, 4).r > 110);
+    }
+
+    #[test]
+    fn blur_options_change_the_border() {
+        let canvas = edge(100, 150);
+        let default = BlurOptions::new();
+        assert_eq!(
+            canvas.clone().high_pass_with(1.5, &default),
+            canvas.clone().high_pass(1.5)
+        );
+        assert_eq!(
+            canvas.clone().clarity_with(4.0, 1.0, &default),
+            canvas.clone().clarity(4.0, 1.0)
+        );
+
+        // Wrapping puts the bright right side next to the dark left border
+        let wrap = BlurOptions::new().set_edge_mode(EdgeMode::Wrap);
+        assert!(canvas.clone().high_pass_with(1.5, &wrap).get_pixel(0, 4).r < 128);
+        assert!(
+            canvas
+                .clone()
+                .high_pass_sharpen_with(1.5, 2.0, &wrap)
+                .get_pixel(0, 4)
+                .r
+                < 100
+        );
+        assert!(canvas.clarity_with(4.0, 1.0, &wrap).get_pixel(0, 4).r < 100);
+    }
+
+    #[test]
+    fn transparent_pixels_are_ignored() {
+        let canvas = Canvas::new_with_background(10, 10, Pixel::new(255, 255, 255, 0)).draw_square(
+            0,
+            0,
+            5,
+            10,
+            &Colors::BLUE,
+        );
+        for result in [
+            canvas.clone().unsharp_mask(1.5, 2.0, 0),
+            canvas.clone().clarity(3.0, 1.0),
+        ] {
+            assert_eq!(result.get_pixel(4, 5), Colors::BLUE);
+            assert_eq!(result.get_pixel(5, 5).a, 0);
+        }
+    }
+}
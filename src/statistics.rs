@@ -0,0 +1,222 @@
+use crate::canvas::{Canvas, Rect};
+use crate::mask::Mask;
+use crate::pixels::Pixel;
+use crate::utility::to_grey_lumiosity;
+
+/// A single channel of a pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    /// The grey value as computed by `Canvas::to_grey`
+    Luma,
+}
+
+impl Channel {
+    /// Returns the value of this channel for pixel `p`
+    pub fn value(&self, p: &Pixel) -> u8 {
+        match self {
+            Channel::Red => p.r,
+            Channel::Green => p.g,
+            Channel::Blue => p.b,
+            Channel::Alpha => p.a,
+            Channel::Luma => to_grey_lumiosity(p).r,
+        }
+    }
+}
+
+/// Summary statistics of the values of a single channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statistics {
+    pub count: u64,
+    pub min: u8,
+    pub max: u8,
+    pub mean: f64,
+    pub median: u8,
+    pub stddev: f64,
+    pub histogram: [u32; 256],
+}
+
+impl Statistics {
+    /// Computes statistics from a histogram. Returns `None` if the histogram is empty.
+    pub fn from_histogram(histogram: &[u32; 256]) -> Option<Statistics> {
+        let count: u64 = histogram.iter().map(|&c| c as u64).sum();
+        if count == 0 {
+            return None;
+        }
+        let min = histogram.iter().position(|&c| c > 0)? as u8;
+        let max = histogram.iter().rposition(|&c| c > 0)? as u8;
+        let mean = histogram
+            .iter()
+            .enumerate()
+            .map(|(value, &c)| value as f64 * c as f64)
+            .sum::<f64>()
+            / count as f64;
+        let variance = histogram
+            .iter()
+            .enumerate()
+            .map(|(value, &c)| (value as f64 - mean).powi(2) * c as f64)
+            .sum::<f64>()
+            / count as f64;
+        let mut statistics = Statistics {
+            count,
+            min,
+            max,
+            mean,
+            median: 0,
+            stddev: variance.sqrt(),
+            histogram: *histogram,
+        };
+        statistics.median = statistics.percentile(50.0);
+        Some(statistics)
+    }
+
+    /// Returns the smallest value such that at least `percent` percent of all values are less
+    /// than or equal to it. `percent` is clamped to `0.0..=100.0`.
+    pub fn percentile(&self, percent: f64) -> u8 {
+        let target = ((percent.clamp(0.0, 100.0) / 100.0) * self.count as f64).ceil() as u64;
+        let target = target.max(1);
+        let mut seen = 0u64;
+        for (value, &c) in self.histogram.iter().enumerate() {
+            seen += c as u64;
+            if seen >= target {
+                return value as u8;
+            }
+        }
+        self.max
+    }
+}
+
+fn histogram_of<'a, I>(pixels: I, channel: Channel) -> [u32; 256]
+where
+    I: Iterator<Item = &'a Pixel>,
+{
+    let mut histogram = [0u32; 256];
+    for p in pixels {
+        histogram[channel.value(p) as usize] += 1;
+    }
+    histogram
+}
+
+impl Canvas {
+    /// Counts how many pixels have each value of `channel`
+    pub fn histogram(&self, channel: Channel) -> [u32; 256] {
+        histogram_of(self.pixels(), channel)
+    }
+
+    /// Counts how many pixels inside `rect` have each value of `channel`
+    pub fn histogram_rect(&self, channel: Channel, rect: &Rect) -> [u32; 256] {
+        let dimensions = self.dimensions();
+        let mut histogram = [0u32; 256];
+        let end_x = rect
+            .start
+            .x
+            .saturating_add(rect.size.width)
+            .min(dimensions.width);
+        let end_y = rect
+            .start
+            .y
+            .saturating_add(rect.size.height)
+            .min(dimensions.height);
+        for y in rect.start.y..end_y {
+            for x in rect.start.x..end_x {
+                histogram[channel.value(&self.get_pixel(x, y)) as usize] += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Counts how many pixels have each value of `channel` where `mask` is at least half set
+    pub fn histogram_masked(&self, channel: Channel, mask: &Mask) -> [u32; 256] {
+        let mut histogram = [0u32; 256];
+        for p in self.iter_with_coordinates() {
+            if mask.get(p.coordinate.x, p.coordinate.y) >= 0.5 {
+                histogram[channel.value(&p.pixel) as usize] += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Computes statistics of `channel`. Returns `None` if the canvas holds no pixels.
+    pub fn statistics(&self, channel: Channel) -> Option<Statistics> {
+        Statistics::from_histogram(&self.histogram(channel))
+    }
+
+    /// Computes statistics of `channel` inside `rect`. Returns `None` if `rect` is empty.
+    pub fn statistics_rect(&self, channel: Channel, rect: &Rect) -> Option<Statistics> {
+        Statistics::from_histogram(&self.histogram_rect(channel, rect))
+    }
+
+    /// Computes statistics of `channel` where `mask` is set. Returns `None` if the mask is empty.
+    pub fn statistics_masked(&self, channel: Channel, mask: &Mask) -> Option<Statistics> {
+        Statistics::from_histogram(&self.histogram_masked(channel, mask))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{Point, Size};
+    use crate::pixels::{ColorTrait, Colors};
+    use crate::utility::find_center_and_size;
+
+    fn ramp() -> Canvas {
+        let pixels = (0..=255u8)
+            .map(|v| Pixel::new(v, 0, 255 - v, 255))
+            .collect();
+        Canvas::new_with_data(16, 16, pixels)
+    }
+
+    #[test]
+    fn histogram_counts_every_pixel() {
+        let canvas = ramp();
+        assert!(canvas.histogram(Channel::Red).iter().all(|&c| c == 1));
+        assert_eq!(canvas.histogram(Channel::Alpha)[255], 256);
+        assert_eq!(canvas.histogram(Channel::Luma).iter().sum::<u32>(), 256);
+    }
+
+    #[test]
+    fn statistics_of_ramp() {
+        let statistics = ramp().statistics(Channel::Red).unwrap();
+        assert_eq!((statistics.min, statistics.max), (0, 255));
+        assert_eq!(statistics.mean, 127.5);
+        assert_eq!(statistics.median, 127);
+        assert_eq!(statistics.percentile(100.0), 255);
+        assert_eq!(statistics.percentile(0.0), 0);
+        assert_eq!(statistics.percentile(10.0), 25);
+        assert!((statistics.stddev - 73.9).abs() < 0.1);
+    }
+
+    #[test]
+    fn statistics_in_rect_and_mask() {
+        let canvas = Canvas::new(10, 10).draw_square(0, 0, 5, 10, &Colors::BLACK);
+        let rect = Rect {
+            start: Point { x: 5, y: 0 },
+            size: Size {
+                width: 100,
+                height: 100,
+            },
+        };
+        let statistics = canvas.statistics_rect(Channel::Luma, &rect).unwrap();
+        assert_eq!((statistics.count, statistics.min), (50, 255));
+
+        assert!(canvas
+            .statistics_masked(Channel::Red, &Mask::new(10, 10))
+            .is_none());
+        let mask = Mask::new_with_value(10, 10, 1.0);
+        let statistics = canvas.statistics_masked(Channel::Red, &mask).unwrap();
+        assert_eq!(statistics.median, 0);
+        assert_eq!(statistics.stddev, 127.5);
+    }
+
+    #[test]
+    fn center_of_empty_canvas_does_not_panic() {
+        let empty = Canvas::new_with_data(0, 0, vec![]);
+        assert!(empty.statistics(Channel::Red).is_none());
+        assert_eq!(find_center_and_size(&empty), (Pixel::new(0, 0, 0, 0), 0.0));
+        let (center, _) = find_center_and_size(&ramp());
+        assert_eq!(center, Pixel::new(127, 0, 127, 255));
+    }
+}
@@ -0,0 +1,298 @@
+//! Helpers for comparing rendered canvases against golden images in tests.
+//!
+//! On failure the expected, actual and diff images are written to the directory named by
+//! `PIXTRA_TEST_OUTPUT`, or `target/pixtra-test-output` if it is unset. Setting
+//! `PIXTRA_UPDATE_GOLDENS=1` overwrites golden files with the actual canvas instead of failing.
+
+use crate::canvas::Canvas;
+use crate::utility::{diff_with, DiffOptions};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the directory failure images are written to.
+pub const OUTPUT_DIR_VAR: &str = "PIXTRA_TEST_OUTPUT";
+/// Environment variable that makes the assertions regenerate golden files.
+pub const UPDATE_GOLDENS_VAR: &str = "PIXTRA_UPDATE_GOLDENS";
+
+/// What a canvas is compared against: another canvas or a golden image file.
+pub enum Expected<'a> {
+    Canvas(&'a Canvas),
+    File(PathBuf),
+}
+
+impl<'a> From<&'a Canvas> for Expected<'a> {
+    fn from(canvas: &'a Canvas) -> Self {
+        Expected::Canvas(canvas)
+    }
+}
+
+impl From<&Path> for Expected<'_> {
+    fn from(path: &Path) -> Self {
+        Expected::File(path.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for Expected<'_> {
+    fn from(path: PathBuf) -> Self {
+        Expected::File(path)
+    }
+}
+
+impl From<&str> for Expected<'_> {
+    fn from(path: &str) -> Self {
+        Expected::File(PathBuf::from(path))
+    }
+}
+
+/// Returns whether `PIXTRA_UPDATE_GOLDENS` is set to something other than `0` or `false`
+pub fn update_goldens() -> bool {
+    env::var(UPDATE_GOLDENS_VAR)
+        .map(|v| !v.is_empty() && v != "0" && v != "false")
+        .unwrap_or(false)
+}
+
+/// Returns the directory failure images are written to
+pub fn output_dir() -> PathBuf {
+    env::var_os(OUTPUT_DIR_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("target").join("pixtra-test-output"))
+}
+
+/// Turns `name` into something that can be used as a file name.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Writes the expected, actual and optional diff canvases to `dir` and returns where they went.
+fn write_failure(
+    dir: &Path,
+    name: &str,
+    expected: &Canvas,
+    actual: &Canvas,
+    diff: Option<&Canvas>,
+) -> String {
+    if let Err(e) = fs::create_dir_all(dir) {
+        return format!("could not create {}: {}", dir.display(), e);
+    }
+    let name = file_name(name);
+    let mut written = Vec::new();
+    let mut outputs = vec![("expected", expected), ("actual", actual)];
+    if let Some(diff) = diff {
+        outputs.push(("diff", diff));
+    }
+    for (kind, canvas) in outputs {
+        let path = dir.join(format!("{}-{}.png", name, kind));
+        match canvas.save(&path) {
+            Ok(()) => written.push(path.display().to_string()),
+            Err(e) => written.push(format!("{} (failed: {:?})", path.display(), e)),
+        }
+    }
+    format!("wrote {}", written.join(", "))
+}
+
+/// Compares `actual` with `expected`. Returns `Ok` if no pixel differs by more than
+/// `tolerance`, see `DiffOptions::tolerance`. A tolerance of `None` requires exact equality.
+/// Golden files are regenerated instead when `PIXTRA_UPDATE_GOLDENS` is set. On failure the
+/// images are written to `output_dir()` under `name` and a summary is returned.
+pub fn compare_canvas<'a>(
+    actual: &Canvas,
+    expected: impl Into<Expected<'a>>,
+    tolerance: Option<f32>,
+    name: &str,
+) -> Result<(), String> {
+    compare_canvas_in(
+        &output_dir(),
+        update_goldens(),
+        actual,
+        expected,
+        tolerance,
+        name,
+    )
+}
+
+/// `compare_canvas` writing failure images to `dir` and regenerating golden files if `update`
+/// is set, instead of reading both from the environment.
+fn compare_canvas_in<'a>(
+    dir: &Path,
+    update: bool,
+    actual: &Canvas,
+    expected: impl Into<Expected<'a>>,
+    tolerance: Option<f32>,
+    name: &str,
+) -> Result<(), String> {
+    let loaded;
+    let (expected, name) = match expected.into() {
+        Expected::Canvas(canvas) => (canvas, name.to_string()),
+        Expected::File(path) => {
+            if update {
+                return actual
+                    .save(&path)
+                    .map_err(|e| format!("could not update {}: {:?}", path.display(), e));
+            }
+            loaded = Canvas::load(&path)
+                .map_err(|e| format!("could not load {}: {:?}", path.display(), e))?;
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| name.to_string());
+            (&loaded, stem)
+        }
+    };
+
+    let (a, e) = (actual.dimensions(), expected.dimensions());
+    if a.width != e.width || a.height != e.height {
+        return Err(format!(
+            "canvas is {}x{} but expected {}x{}; {}",
+            a.width,
+            a.height,
+            e.width,
+            e.height,
+            write_failure(dir, &name, expected, actual, None)
+        ));
+    }
+
+    let options = match tolerance {
+        Some(tolerance) => DiffOptions::new().set_tolerance(tolerance),
+        None => DiffOptions::new().set_exact(true),
+    };
+    let diff = diff_with(expected, actual, &options).map_err(|e| format!("{:?}", e))?;
+    if diff.count == 0 {
+        return Ok(());
+    }
+    let total = a.width as usize * a.height as usize;
+    let largest = diff
+        .regions
+        .iter()
+        .max_by_key(|r| r.size.width * r.size.height)
+        .map(|r| {
+            format!(
+                ", largest at ({}, {}) size {}x{}",
+                r.start.x, r.start.y, r.size.width, r.size.height
+            )
+        })
+        .unwrap_or_default();
+    Err(format!(
+        "{} of {} pixels differ ({:.2}%) in {} regions{}; {}",
+        diff.count,
+        total,
+        diff.count as f64 * 100.0 / total as f64,
+        diff.regions.len(),
+        largest,
+        write_failure(dir, &name, expected, actual, Some(&diff.canvas))
+    ))
+}
+
+/// Asserts that a canvas is identical to an expected canvas or golden image file.
+///
+/// ```no_run
+/// # use pixtra::{assert_canvas_eq, canvas::Canvas};
+/// let canvas = Canvas::new(10, 10);
+/// assert_canvas_eq!(canvas, "assets/white.png");
+/// ```
+#[macro_export]
+macro_rules! assert_canvas_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        if let Err(message) = $crate::testing::compare_canvas(
+            &$actual,
+            $expected,
+            None,
+            &format!("{}-{}", file!(), line!()),
+        ) {
+            panic!("assertion `canvas == expected` failed: {}", message);
+        }
+    };
+}
+
+/// Asserts that no pixel of a canvas differs from an expected canvas or golden image file by
+/// more than `tolerance`, ignoring anti-aliasing. See `DiffOptions::tolerance`.
+#[macro_export]
+macro_rules! assert_canvas_similar {
+    ($actual:expr, $expected:expr, $tolerance:expr $(,)?) => {
+        if let Err(message) = $crate::testing::compare_canvas(
+            &$actual,
+            $expected,
+            Some($tolerance),
+            &format!("{}-{}", file!(), line!()),
+        ) {
+            panic!("assertion `canvas ~= expected` failed: {}", message);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors, Pixel};
+
+    #[test]
+    fn equal_canvases_pass() {
+        let canvas = Canvas::new(4, 4).draw_square(1, 1, 2, 2, &Colors::RED);
+        assert_canvas_eq!(canvas, &canvas.clone());
+        let close = canvas
+            .clone()
+            .set_pixel(0, 0, &Pixel::new(254, 255, 255, 255));
+        assert_canvas_similar!(close, &canvas, 0.05);
+        let dir = env::temp_dir().join(format!("pixtra-testing-close-{}", std::process::id()));
+        assert!(compare_canvas_in(&dir, false, &close, &canvas, None, "close").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failures_write_images() {
+        let dir = env::temp_dir().join(format!("pixtra-testing-{}", std::process::id()));
+        let expected = Canvas::new(4, 4);
+        let actual = expected.clone().set_pixel(2, 1, &Colors::BLACK);
+        let message = compare_canvas_in(&dir, false, &actual, &expected, Some(0.1), "some/test:12")
+            .unwrap_err();
+        let size_message =
+            compare_canvas_in(&dir, false, &Canvas::new(2, 2), &expected, None, "size")
+                .unwrap_err();
+        assert!(message.starts_with("1 of 16 pixels differ"));
+        assert!(message.contains("largest at (2, 1) size 1x1"));
+        for kind in ["expected", "actual", "diff"] {
+            assert!(dir.join(format!("some_test_12-{}.png", kind)).exists());
+        }
+        assert!(size_message.starts_with("canvas is 2x2 but expected 4x4"));
+        assert!(dir.join("size-actual.png").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invisible_changes_fail_exact_comparison() {
+        let dir = env::temp_dir().join(format!("pixtra-testing-exact-{}", std::process::id()));
+        let expected = Canvas::new_with_background(4, 4, Pixel::new(0, 0, 0, 0));
+        let actual = expected.clone().set_pixel(1, 2, &Pixel::new(255, 0, 0, 0));
+        assert!(compare_canvas_in(&dir, false, &actual, &expected, Some(0.1), "exact").is_ok());
+        let message =
+            compare_canvas_in(&dir, false, &actual, &expected, None, "exact").unwrap_err();
+        assert!(message.starts_with("1 of 16 pixels differ"));
+        assert!(message.contains("in 1 regions, largest at (1, 2) size 1x1"));
+        let diff = Canvas::load(&dir.join("exact-diff.png")).unwrap();
+        assert_eq!(diff.get_pixel(1, 2), DiffOptions::new().diff_color);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_golden_fails() {
+        let dir = env::temp_dir().join(format!("pixtra-testing-golden-{}", std::process::id()));
+        let golden = dir.join("golden.png");
+        let canvas = Canvas::new(1, 1);
+        let result = compare_canvas_in(&dir, false, &canvas, golden.as_path(), None, "missing");
+        assert!(result.unwrap_err().starts_with("could not load"));
+        assert!(!golden.exists());
+
+        fs::create_dir_all(&dir).unwrap();
+        assert!(compare_canvas_in(&dir, true, &canvas, golden.as_path(), None, "update").is_ok());
+        assert!(compare_canvas_in(&dir, false, &canvas, golden.as_path(), None, "load").is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,662 @@
+use crate::canvas::{Canvas, ImageError, Point, Size};
+use crate::pixels::{ColorTrait, Colors, Pixel};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The embedded 5x7 font, stored column by column for the characters `' '..='~'`. The least
+/// significant bit of each column is the top row.
+const DEFAULT_FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x08, 0x2a, 0x1c, 0x2a, 0x08], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // backslash
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+/// A single character of a `BitmapFont`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Glyph {
+    pub width: u32,
+    pub height: u32,
+    /// Horizontal offset from the pen position to the left edge of the bitmap
+    pub left: i32,
+    /// Vertical offset from the top of the line to the top edge of the bitmap
+    pub top: i32,
+    /// How far the pen moves after drawing this glyph
+    pub advance: u32,
+    /// Row by row, `true` where the glyph is set
+    pub bitmap: Vec<bool>,
+}
+
+impl Glyph {
+    fn is_set(&self, x: u32, y: u32) -> bool {
+        self.bitmap[(y * self.width + x) as usize]
+    }
+}
+
+/// Horizontal alignment of text relative to the position it is drawn at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// Describes how text is drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    pub color: Pixel,
+    pub scale: u32,
+    pub align: TextAlign,
+}
+
+impl TextStyle {
+    /// Creates a new `TextStyle` with color `color`, scale `1` and left alignment.
+    pub fn new(color: &Pixel) -> TextStyle {
+        TextStyle {
+            color: color.clone(),
+            scale: 1,
+            align: TextAlign::Left,
+        }
+    }
+
+    pub fn set_scale(mut self, scale: u32) -> TextStyle {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn set_align(mut self, align: TextAlign) -> TextStyle {
+        self.align = align;
+        self
+    }
+}
+
+impl Default for TextStyle {
+    fn default() -> TextStyle {
+        TextStyle::new(&Colors::BLACK)
+    }
+}
+
+/// A monochrome bitmap font. Use `BitmapFont::default()` for the embedded 5x7 font or load
+/// BDF and PSF fonts from disk.
+///
+/// # Examples
+///
+/// ```
+/// use pixtra::canvas::{Canvas, Point};
+/// use pixtra::text::{BitmapFont, TextStyle};
+/// use pixtra::pixels::{ColorTrait, Colors};
+///
+/// let font = BitmapFont::default();
+/// let style = TextStyle::new(&Colors::BLACK).set_scale(2);
+/// let canvas = Canvas::new(100, 20).draw_text("Island 1", &Point { x: 2, y: 2 }, &font, &style);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitmapFont {
+    glyphs: HashMap<char, Glyph>,
+    line_height: u32,
+    default_advance: u32,
+}
+
+impl Default for BitmapFont {
+    fn default() -> BitmapFont {
+        let glyphs = DEFAULT_FONT
+            .iter()
+            .enumerate()
+            .map(|(i, columns)| {
+                let mut bitmap = vec![false; 5 * 7];
+                for (x, column) in columns.iter().enumerate() {
+                    for y in 0..7 {
+                        bitmap[y * 5 + x] = column & (1 << y) != 0;
+                    }
+                }
+                let glyph = Glyph {
+                    width: 5,
+                    height: 7,
+                    left: 0,
+                    top: 0,
+                    advance: 6,
+                    bitmap,
+                };
+                (char::from(b' ' + i as u8), glyph)
+            })
+            .collect();
+        BitmapFont {
+            glyphs,
+            line_height: 8,
+            default_advance: 6,
+        }
+    }
+}
+
+impl BitmapFont {
+    /// Creates a font from a set of glyphs. `line_height` is the distance between two lines of
+    /// text.
+    pub fn new(glyphs: HashMap<char, Glyph>, line_height: u32) -> BitmapFont {
+        let default_advance = glyphs
+            .get(&' ')
+            .map(|g| g.advance)
+            .unwrap_or(line_height / 2);
+        BitmapFont {
+            glyphs,
+            line_height,
+            default_advance,
+        }
+    }
+
+    /// Returns the glyph for `c` if the font contains it
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c)
+    }
+
+    /// Distance between two lines of text, unscaled
+    pub fn line_height(&self) -> u32 {
+        self.line_height
+    }
+
+    fn advance(&self, c: char) -> u32 {
+        self.glyphs
+            .get(&c)
+            .map(|g| g.advance)
+            .unwrap_or(self.default_advance)
+    }
+
+    fn line_width(&self, line: &str) -> u32 {
+        line.chars().map(|c| self.advance(c)).sum()
+    }
+
+    /// Returns the size of the area covered by `text` when drawn with scale `scale`. Lines are
+    /// separated by `'\n'`.
+    pub fn measure_text(&self, text: &str, scale: u32) -> Size {
+        let scale = scale.max(1);
+        let width = text
+            .split('\n')
+            .map(|line| self.line_width(line))
+            .max()
+            .unwrap_or(0);
+        let lines = text.split('\n').count() as u32;
+        Size {
+            width: width * scale,
+            height: lines * self.line_height * scale,
+        }
+    }
+
+    /// Loads a font in the Glyph Bitmap Distribution Format (BDF)
+    pub fn load_bdf(filename: &Path) -> Result<BitmapFont, ImageError> {
+        let content =
+            fs::read_to_string(filename).map_err(|e| ImageError::IoError(e.to_string()))?;
+        BitmapFont::parse_bdf(&content)
+    }
+
+    /// Parses a font in the Glyph Bitmap Distribution Format (BDF)
+    pub fn parse_bdf(content: &str) -> Result<BitmapFont, ImageError> {
+        let invalid =
+            |message: &str| ImageError::Decoding(format!("Invalid BDF font: {}", message));
+        let numbers = |values: &[&str]| -> Result<Vec<i32>, ImageError> {
+            values
+                .iter()
+                .map(|v| v.parse::<i32>().map_err(|_| invalid(v)))
+                .collect()
+        };
+
+        let mut glyphs = HashMap::new();
+        let mut bounding_box: Option<Vec<i32>> = None;
+        let mut ascent: Option<i32> = None;
+        let mut descent: Option<i32> = None;
+
+        let mut lines = content.lines();
+        while let Some(line) = lines.next() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.first() {
+                Some(&"FONTBOUNDINGBOX") if parts.len() >= 5 => {
+                    bounding_box = Some(numbers(&parts[1..5])?);
+                }
+                Some(&"FONT_ASCENT") if parts.len() >= 2 => {
+                    ascent = Some(numbers(&parts[1..2])?[0])
+                }
+                Some(&"FONT_DESCENT") if parts.len() >= 2 => {
+                    descent = Some(numbers(&parts[1..2])?[0])
+                }
+                Some(&"STARTCHAR") => {
+                    let font_box = bounding_box
+                        .clone()
+                        .ok_or_else(|| invalid("missing FONTBOUNDINGBOX"))?;
+                    let font_ascent = ascent.unwrap_or(font_box[1] + font_box[3]);
+                    let mut encoding = None;
+                    let mut advance = font_box[0];
+                    let mut glyph_box = font_box.clone();
+                    let mut rows: Vec<u64> = vec![];
+                    let mut in_bitmap = false;
+                    for line in lines.by_ref() {
+                        let parts: Vec<&str> = line.split_whitespace().collect();
+                        match parts.first() {
+                            Some(&"ENDCHAR") => break,
+                            Some(&"ENCODING") if parts.len() >= 2 => {
+                                encoding = Some(numbers(&parts[1..2])?[0])
+                            }
+                            Some(&"DWIDTH") if parts.len() >= 2 => {
+                                advance = numbers(&parts[1..2])?[0]
+                            }
+                            Some(&"BBX") if parts.len() >= 5 => glyph_box = numbers(&parts[1..5])?,
+                            Some(&"BITMAP") => in_bitmap = true,
+                            Some(hex) if in_bitmap => {
+                                rows.push(u64::from_str_radix(hex, 16).map_err(|_| invalid(hex))?);
+                            }
+                            _ => {}
+                        }
+                    }
+                    let (width, height) = (glyph_box[0].max(0) as u32, glyph_box[1].max(0) as u32);
+                    // Rows are read into a `u64`, and checking the rows first bounds the bitmap
+                    if width > 64 {
+                        return Err(invalid("glyph wider than 64 pixels"));
+                    }
+                    if rows.len() < height as usize {
+                        return Err(invalid("bitmap has too few rows"));
+                    }
+                    // Rows are padded to whole bytes with the first pixel in the highest bit
+                    let bits = width.div_ceil(8) * 8;
+                    let mut bitmap = vec![false; width as usize * height as usize];
+                    for y in 0..height {
+                        for x in 0..width {
+                            bitmap[y as usize * width as usize + x as usize] =
+                                rows[y as usize] >> (bits - 1 - x) & 1 == 1;
+                        }
+                    }
+                    let glyph = Glyph {
+                        width,
+                        height,
+                        left: glyph_box[2],
+                        top: font_ascent - glyph_box[3] - height as i32,
+                        advance: advance.max(0) as u32,
+                        bitmap,
+                    };
+                    if let Some(c) = encoding.and_then(|e| char::from_u32(e as u32)) {
+                        glyphs.insert(c, glyph);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let font_box = bounding_box.ok_or_else(|| invalid("missing FONTBOUNDINGBOX"))?;
+        let line_height = match (ascent, descent) {
+            (Some(a), Some(d)) => a + d,
+            _ => font_box[1],
+        };
+        Ok(BitmapFont::new(glyphs, line_height.max(1) as u32))
+    }
+
+    /// Loads a font in the PC Screen Font format (PSF version 1 or 2)
+    pub fn load_psf(filename: &Path) -> Result<BitmapFont, ImageError> {
+        let data = fs::read(filename).map_err(|e| ImageError::IoError(e.to_string()))?;
+        BitmapFont::parse_psf(&data)
+    }
+
+    /// Parses a font in the PC Screen Font format (PSF version 1 or 2)
+    pub fn parse_psf(data: &[u8]) -> Result<BitmapFont, ImageError> {
+        let invalid =
+            |message: &str| ImageError::Decoding(format!("Invalid PSF font: {}", message));
+        let read_u32 = |offset: usize| -> Result<u32, ImageError> {
+            data.get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or_else(|| invalid("truncated header"))
+        };
+
+        let (header_size, count, glyph_size, height, width, has_table, psf2) =
+            if data.starts_with(&[0x36, 0x04]) {
+                let mode = *data.get(2).ok_or_else(|| invalid("truncated header"))?;
+                let size = *data.get(3).ok_or_else(|| invalid("truncated header"))? as u32;
+                let count = if mode & 0x01 != 0 { 512 } else { 256 };
+                (4, count, size, size, 8, mode & 0x06 != 0, false)
+            } else if data.starts_with(&[0x72, 0xb5, 0x4a, 0x86]) {
+                (
+                    read_u32(8)? as usize,
+                    read_u32(16)?,
+                    read_u32(20)?,
+                    read_u32(24)?,
+                    read_u32(28)?,
+                    read_u32(12)? & 0x01 != 0,
+                    true,
+                )
+            } else {
+                return Err(invalid("unknown magic number"));
+            };
+
+        if width == 0 || height == 0 || glyph_size == 0 {
+            return Err(invalid("empty glyphs"));
+        }
+        let row_bytes = width.div_ceil(8) as usize;
+        if (glyph_size as usize) < row_bytes * height as usize {
+            return Err(invalid("glyph size too small"));
+        }
+        let glyph_data = (count as usize)
+            .checked_mul(glyph_size as usize)
+            .and_then(|length| header_size.checked_add(length))
+            .and_then(|end| data.get(header_size..end))
+            .ok_or_else(|| invalid("truncated glyph data"))?;
+
+        let glyph_list: Vec<Glyph> = glyph_data
+            .chunks(glyph_size as usize)
+            .map(|bytes| {
+                let mut bitmap = vec![false; width as usize * height as usize];
+                for y in 0..height as usize {
+                    for x in 0..width as usize {
+                        let byte = bytes[y * row_bytes + x / 8];
+                        bitmap[y * width as usize + x] = byte & (0x80 >> (x % 8)) != 0;
+                    }
+                }
+                Glyph {
+                    width,
+                    height,
+                    left: 0,
+                    top: 0,
+                    advance: width,
+                    bitmap,
+                }
+            })
+            .collect();
+
+        let mut glyphs = HashMap::new();
+        if has_table {
+            let table = &data[header_size + glyph_data.len()..];
+            let mut index = 0;
+            if psf2 {
+                // UTF-8 sequences separated by 0xff, combining sequences start with 0xfe
+                for entry in table.split(|&b| b == 0xff).take(glyph_list.len()) {
+                    let singles = entry.split(|&b| b == 0xfe).next().unwrap_or(&[]);
+                    if let Ok(text) = std::str::from_utf8(singles) {
+                        for c in text.chars() {
+                            glyphs.entry(c).or_insert_with(|| glyph_list[index].clone());
+                        }
+                    }
+                    index += 1;
+                }
+            } else {
+                // UCS-2 little endian values separated by 0xffff, sequences start with 0xfffe
+                let values: Vec<u16> = table
+                    .chunks_exact(2)
+                    .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                    .collect();
+                for entry in values.split(|&v| v == 0xffff).take(glyph_list.len()) {
+                    for &value in entry.iter().take_while(|&&v| v != 0xfffe) {
+                        if let Some(c) = char::from_u32(value as u32) {
+                            glyphs.entry(c).or_insert_with(|| glyph_list[index].clone());
+                        }
+                    }
+                    index += 1;
+                }
+            }
+        } else {
+            for (i, glyph) in glyph_list.into_iter().enumerate() {
+                if let Some(c) = char::from_u32(i as u32) {
+                    glyphs.insert(c, glyph);
+                }
+            }
+        }
+
+        Ok(BitmapFont::new(glyphs, height))
+    }
+}
+
+impl Canvas {
+    /// Draws `text` with its top edge at `position`. `style.align` decides whether `position` is
+    /// the left edge, the center or the right edge of each line. Lines are separated by `'\n'`.
+    pub fn draw_text_mut(
+        &mut self,
+        text: &str,
+        position: &Point,
+        font: &BitmapFont,
+        style: &TextStyle,
+    ) {
+        let scale = style.scale.max(1) as i64;
+        for (row, line) in text.split('\n').enumerate() {
+            let line_width = font.line_width(line) as i64 * scale;
+            let mut pen_x = match style.align {
+                TextAlign::Left => position.x as i64,
+                TextAlign::Center => position.x as i64 - line_width / 2,
+                TextAlign::Right => position.x as i64 - line_width,
+            };
+            let pen_y = position.y as i64 + row as i64 * font.line_height as i64 * scale;
+            for c in line.chars() {
+                if let Some(glyph) = font.glyph(c) {
+                    for y in 0..glyph.height {
+                        for x in 0..glyph.width {
+                            if !glyph.is_set(x, y) {
+                                continue;
+                            }
+                            let left = pen_x + (glyph.left as i64 + x as i64) * scale;
+                            let top = pen_y + (glyph.top as i64 + y as i64) * scale;
+                            for dy in 0..scale {
+                                for dx in 0..scale {
+                                    self.set_pixel_mut_signed(left + dx, top + dy, &style.color);
+                                }
+                            }
+                        }
+                    }
+                }
+                pen_x += font.advance(c) as i64 * scale;
+            }
+        }
+    }
+
+    /// Draws `text` with its top edge at `position`. `style.align` decides whether `position` is
+    /// the left edge, the center or the right edge of each line. Lines are separated by `'\n'`.
+    pub fn draw_text(
+        mut self,
+        text: &str,
+        position: &Point,
+        font: &BitmapFont,
+        style: &TextStyle,
+    ) -> Canvas {
+        self.draw_text_mut(text, position, font, style);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure_default_font() {
+        let font = BitmapFont::default();
+        assert_eq!(
+            font.measure_text("abc", 1),
+            Size {
+                width: 18,
+                height: 8
+            }
+        );
+        assert_eq!(
+            font.measure_text("abc\nab", 2),
+            Size {
+                width: 36,
+                height: 32
+            }
+        );
+        assert_eq!(
+            font.measure_text("", 1),
+            Size {
+                width: 0,
+                height: 8
+            }
+        );
+    }
+
+    #[test]
+    fn draw_scaled_text() {
+        let font = BitmapFont::default();
+        let style = TextStyle::new(&Colors::BLACK).set_scale(3);
+        let canvas = Canvas::new(20, 30).draw_text("|", &Point { x: 0, y: 0 }, &font, &style);
+        // '|' is a single column of seven pixels
+        assert_eq!(canvas.count_pixels(&Colors::BLACK), 7 * 9);
+        assert_eq!(canvas.get_pixel(6, 0), Colors::BLACK);
+    }
+
+    #[test]
+    fn alignment_moves_text() {
+        let font = BitmapFont::default();
+        let style = TextStyle::new(&Colors::BLACK).set_align(TextAlign::Right);
+        let canvas = Canvas::new(20, 10).draw_text("|", &Point { x: 20, y: 0 }, &font, &style);
+        assert_eq!(canvas.get_pixel(16, 3), Colors::BLACK);
+    }
+
+    #[test]
+    fn parse_bdf_font() {
+        let bdf = "STARTFONT 2.1\n\
+                   FONTBOUNDINGBOX 4 4 0 -1\n\
+                   FONT_ASCENT 3\n\
+                   FONT_DESCENT 1\n\
+                   CHARS 1\n\
+                   STARTCHAR A\n\
+                   ENCODING 65\n\
+                   DWIDTH 5 0\n\
+                   BBX 3 2 1 0\n\
+                   BITMAP\n\
+                   A0\n\
+                   40\n\
+                   ENDCHAR\n\
+                   ENDFONT\n";
+        let font = BitmapFont::parse_bdf(bdf).unwrap();
+        let glyph = font.glyph('A').unwrap();
+        assert_eq!(glyph.bitmap, vec![true, false, true, false, true, false]);
+        assert_eq!((glyph.left, glyph.top, glyph.advance), (1, 1, 5));
+        assert_eq!(font.line_height(), 4);
+    }
+
+    #[test]
+    fn parse_psf1_font() {
+        let mut data = vec![0x36, 0x04, 0x00, 0x02];
+        data.extend(vec![0u8; 256 * 2]);
+        data[4 + 65 * 2] = 0b1000_0001;
+        let font = BitmapFont::parse_psf(&data).unwrap();
+        let glyph = font.glyph('A').unwrap();
+        assert_eq!((glyph.width, glyph.height), (8, 2));
+        assert!(glyph.bitmap[0] && glyph.bitmap[7] && !glyph.bitmap[8]);
+        assert!(BitmapFont::parse_psf(&[0, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn malformed_fonts_are_rejected() {
+        let bdf = "FONTBOUNDINGBOX 100 1 0 0\n\
+                   STARTCHAR A\n\
+                   ENCODING 65\n\
+                   BBX 100 1 0 0\n\
+                   BITMAP\n\
+                   FF\n\
+                   ENDCHAR\n";
+        assert!(matches!(
+            BitmapFont::parse_bdf(bdf),
+            Err(ImageError::Decoding(_))
+        ));
+
+        let psf2 = |count: u32, glyph_size: u32, height: u32, width: u32| {
+            let mut data = vec![0x72, 0xb5, 0x4a, 0x86];
+            for value in [0, 32, 0, count, glyph_size, height, width] {
+                data.extend(u32::to_le_bytes(value));
+            }
+            BitmapFont::parse_psf(&data)
+        };
+        let cases = [
+            BitmapFont::parse_psf(&[0x36, 0x04, 0x00, 0x00]),
+            psf2(1, 0, 1, 1),
+            psf2(1, 1, 0, 1),
+            psf2(1, 1, 1, 0),
+            psf2(0x1000_0000, 0x1000_0000, 1, 1),
+        ];
+        for result in cases {
+            assert!(matches!(result, Err(ImageError::Decoding(_))));
+        }
+    }
+}
@@ -1,5 +1,7 @@
-use crate::canvas::Canvas;
+use crate::canvas::{Canvas, ImageError, Point, Rect, Size};
+use crate::metrics::check_dimensions;
 use crate::pixels::Pixel;
+use crate::statistics::Channel;
 use std::cmp;
 use std::collections::HashMap;
 
@@ -53,9 +55,9 @@ where
 }
 
 /// Clamp value `value` to be between `min` and `max`.
-/// `min` needs to be less than `max`
+/// `min` must not be greater than `max`
 pub fn clamp<T: PartialOrd>(min: T, max: T, val: T) -> T {
-    assert!(min < max);
+    assert!(min <= max);
     if val > max {
         return max;
     }
@@ -65,11 +67,13 @@ pub fn clamp<T: PartialOrd>(min: T, max: T, val: T) -> T {
     val
 }
 
+/// Finds the center of the color range of `canvas` and the distance from that center to the
+/// highest value of every channel. An empty canvas has its center at `(0, 0, 0, 0)`.
 pub fn find_center_and_size(canvas: &Canvas) -> (Pixel, f32) {
-    let (center_r, max_r) = center(&canvas, |p| p.r);
-    let (center_g, max_g) = center(&canvas, |p| p.g);
-    let (center_b, max_b) = center(&canvas, |p| p.b);
-    let (center_a, max_a) = center(&canvas, |p| p.a);
+    let (center_r, max_r) = center(canvas, Channel::Red);
+    let (center_g, max_g) = center(canvas, Channel::Green);
+    let (center_b, max_b) = center(canvas, Channel::Blue);
+    let (center_a, max_a) = center(canvas, Channel::Alpha);
     let center = Pixel::new(center_r, center_g, center_b, center_a);
     let corner = Pixel::new(max_r, max_g, max_b, max_a);
     let distance = center.distance(&corner);
@@ -77,19 +81,24 @@ pub fn find_center_and_size(canvas: &Canvas) -> (Pixel, f32) {
     (center, distance)
 }
 
-fn center<F>(canvas: &Canvas, map_color: F) -> (u8, u8) 
-where F: Fn(Pixel) -> u8{
-    let min = canvas.iter().map(|p| map_color(p)).min().unwrap();
-    let max = canvas.iter().map(|p| map_color(p)).max().unwrap();
-    ((max - min) / 2 + min, max)
+fn center(canvas: &Canvas, channel: Channel) -> (u8, u8) {
+    match canvas.statistics(channel) {
+        Some(statistics) => (
+            (statistics.max - statistics.min) / 2 + statistics.min,
+            statistics.max,
+        ),
+        None => (0, 0),
+    }
 }
 
+/// Squared difference of every channel of `p1` and `p2`
 pub fn diff_squared(p1: &Pixel, p2: &Pixel) -> (u32, u32, u32, u32) {
+    let squared = |v1: u8, v2: u8| (v1 as i32 - v2 as i32).pow(2) as u32;
     (
-        (p1.r - p2.r).pow(2).into(),
-        (p1.g - p2.g).pow(2).into(),
-        (p1.b - p2.b).pow(2).into(),
-        (p1.a - p2.a).pow(2).into(),
+        squared(p1.r, p2.r),
+        squared(p1.g, p2.g),
+        squared(p1.b, p2.b),
+        squared(p1.a, p2.a),
     )
 }
 
@@ -110,28 +119,287 @@ pub fn counted_colors_to_html(map: &HashMap<Pixel, usize>) -> String {
     output
 }
 
-pub fn diff(_c1: &Canvas, _c2: &Canvas) -> Canvas {
-    let c = Canvas::new(1, 1);
-    c
+/// Settings for `diff_with`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffOptions {
+    /// Smallest perceptual difference, between `0.0` and `1.0`, that counts as a change
+    pub tolerance: f32,
+    /// Whether changes that look like anti-aliasing are reported separately
+    pub detect_anti_aliasing: bool,
+    /// Counts every pixel whose channels differ, even invisible changes such as a different
+    /// color under zero alpha. Tolerance and anti-aliasing detection are ignored.
+    pub exact: bool,
+    /// Opacity of the grey original drawn under the highlighted changes
+    pub fade: f32,
+    pub diff_color: Pixel,
+    pub anti_aliased_color: Pixel,
 }
 
-pub fn diff_debug(c1: &Canvas, c2: &Canvas) -> Canvas {
-    let c1_dim = c1.dimensions();
-    let c2_dim = c2.dimensions();
-    let res = if c1_dim.width == c2_dim.width && c1_dim.height == c2_dim.height {
-        let pixels = c1
-            .pixels()
-            .zip(c2.pixels())
-            .map(|(p1, p2)| p1.diff(p2))
-            .collect();
-        Canvas::new_with_data(c1_dim.width, c1_dim.height, pixels)
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions::new()
+    }
+}
+
+impl DiffOptions {
+    /// Creates the default options: a tolerance of `0.1` with anti-aliasing detection
+    pub fn new() -> DiffOptions {
+        DiffOptions {
+            tolerance: 0.1,
+            detect_anti_aliasing: true,
+            exact: false,
+            fade: 0.1,
+            diff_color: Pixel::new(255, 0, 0, 255),
+            anti_aliased_color: Pixel::new(255, 255, 0, 255),
+        }
+    }
+
+    pub fn set_tolerance(mut self, tolerance: f32) -> DiffOptions {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn set_detect_anti_aliasing(mut self, detect: bool) -> DiffOptions {
+        self.detect_anti_aliasing = detect;
+        self
+    }
+
+    pub fn set_exact(mut self, exact: bool) -> DiffOptions {
+        self.exact = exact;
+        self
+    }
+
+    pub fn set_fade(mut self, fade: f32) -> DiffOptions {
+        self.fade = fade;
+        self
+    }
+
+    pub fn set_diff_color(mut self, color: &Pixel) -> DiffOptions {
+        self.diff_color = color.clone();
+        self
+    }
+
+    pub fn set_anti_aliased_color(mut self, color: &Pixel) -> DiffOptions {
+        self.anti_aliased_color = color.clone();
+        self
+    }
+}
+
+/// The result of comparing two canvases with `diff`.
+#[derive(Clone, Debug)]
+pub struct Diff {
+    /// The faded original with changed pixels highlighted
+    pub canvas: Canvas,
+    /// Number of changed pixels, not counting anti-aliasing
+    pub count: usize,
+    /// Number of changed pixels that look like anti-aliasing
+    pub anti_aliased: usize,
+    /// Bounding boxes of the connected regions of changed pixels
+    pub regions: Vec<Rect>,
+}
+
+/// Largest possible value of `color_delta`.
+const MAX_COLOR_DELTA: f32 = 35215.0;
+
+/// Blends `p` onto white and returns its YIQ components.
+fn to_yiq(p: &Pixel) -> (f32, f32, f32) {
+    let a = p.a as f32 / 255.0;
+    let blend = |c: u8| 255.0 + (c as f32 - 255.0) * a;
+    let (r, g, b) = (blend(p.r), blend(p.g), blend(p.b));
+    (
+        r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_23,
+        r * 0.595_978 - g * 0.274_176_1 - b * 0.321_801_9,
+        r * 0.211_470_17 - g * 0.522_617_1 + b * 0.311_146_94,
+    )
+}
+
+/// Perceptual difference of two pixels in YIQ. Negative if `p1` is brighter than `p2`.
+fn color_delta(p1: &Pixel, p2: &Pixel) -> f32 {
+    if p1 == p2 {
+        return 0.0;
+    }
+    let (y1, i1, q1) = to_yiq(p1);
+    let (y2, i2, q2) = to_yiq(p2);
+    let (y, i, q) = (y1 - y2, i1 - i2, q1 - q2);
+    let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+    if y1 > y2 {
+        -delta
     } else {
-        c1.clone()
-    };
-    res
+        delta
+    }
+}
+
+/// The in-bounds 3x3 neighbourhood of `(x, y)` without the center, and whether it touches the
+/// border of `canvas`.
+fn neighbours(canvas: &Canvas, x: u32, y: u32) -> (Vec<Point>, bool) {
+    let dimensions = canvas.dimensions();
+    let (x0, y0) = (x.saturating_sub(1), y.saturating_sub(1));
+    let x1 = (x + 1).min(dimensions.width - 1);
+    let y1 = (y + 1).min(dimensions.height - 1);
+    let mut points = Vec::with_capacity(8);
+    for ny in y0..=y1 {
+        for nx in x0..=x1 {
+            if nx != x || ny != y {
+                points.push(Point { x: nx, y: ny });
+            }
+        }
+    }
+    (points, x == x0 || x == x1 || y == y0 || y == y1)
+}
+
+/// Whether at least three neighbours of `(x, y)` have exactly its color.
+fn has_many_siblings(canvas: &Canvas, x: u32, y: u32) -> bool {
+    let center = canvas.get_pixel(x, y);
+    let (points, on_border) = neighbours(canvas, x, y);
+    let same = points
+        .iter()
+        .filter(|p| canvas.get_pixel(p.x, p.y) == center)
+        .count();
+    same + on_border as usize > 2
+}
+
+/// Whether the pixel at `(x, y)` of `canvas` looks like anti-aliasing, based on the approach of
+/// the pixelmatch library.
+fn is_anti_aliased(canvas: &Canvas, other: &Canvas, x: u32, y: u32) -> bool {
+    let center = canvas.get_pixel(x, y);
+    let (points, on_border) = neighbours(canvas, x, y);
+    let mut zeroes = on_border as usize;
+    let (mut min, mut max) = (0f32, 0f32);
+    let (mut darkest, mut brightest) = (Point { x, y }, Point { x, y });
+    for point in points {
+        let (brightness, _, _) = to_yiq(&canvas.get_pixel(point.x, point.y));
+        let delta = to_yiq(&center).0 - brightness;
+        if delta == 0.0 {
+            zeroes += 1;
+            if zeroes > 2 {
+                return false;
+            }
+        } else if delta < min {
+            min = delta;
+            darkest = point;
+        } else if delta > max {
+            max = delta;
+            brightest = point;
+        }
+    }
+    if min == 0.0 || max == 0.0 {
+        return false;
+    }
+    (has_many_siblings(canvas, darkest.x, darkest.y)
+        && has_many_siblings(other, darkest.x, darkest.y))
+        || (has_many_siblings(canvas, brightest.x, brightest.y)
+            && has_many_siblings(other, brightest.x, brightest.y))
+}
+
+/// Bounding boxes of the 8-connected regions set in `changed`.
+fn changed_regions(changed: &[bool], width: u32, height: u32) -> Vec<Rect> {
+    let mut seen = vec![false; changed.len()];
+    let mut regions = Vec::new();
+    for start in 0..changed.len() {
+        if !changed[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut stack = vec![start];
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index as u32 % width, index as u32 / width);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    let neighbour = (ny * width + nx) as usize;
+                    if changed[neighbour] && !seen[neighbour] {
+                        seen[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+        }
+        regions.push(Rect {
+            start: Point { x: min_x, y: min_y },
+            size: Size {
+                width: max_x - min_x + 1,
+                height: max_y - min_y + 1,
+            },
+        });
+    }
+    regions
+}
+
+/// Compares `c1` and `c2` with the default `DiffOptions`. Fails if the dimensions differ.
+pub fn diff(c1: &Canvas, c2: &Canvas) -> Result<Diff, ImageError> {
+    diff_with(c1, c2, &DiffOptions::new())
+}
+
+/// Compares `c1` and `c2` pixel by pixel. Changed pixels are drawn in `options.diff_color` over
+/// a faded grey copy of `c1`, pixels that only differ by anti-aliasing in
+/// `options.anti_aliased_color`. Fails if the dimensions differ.
+pub fn diff_with(c1: &Canvas, c2: &Canvas, options: &DiffOptions) -> Result<Diff, ImageError> {
+    check_dimensions(c1, c2)?;
+    let dimensions = c1.dimensions();
+    let max_delta = MAX_COLOR_DELTA * options.tolerance * options.tolerance;
+    let mut canvas = Canvas::new(dimensions.width, dimensions.height);
+    let mut changed = vec![false; (dimensions.width * dimensions.height) as usize];
+    let (mut count, mut anti_aliased) = (0, 0);
+    for y in 0..dimensions.height {
+        for x in 0..dimensions.width {
+            let (p1, p2) = (c1.get_pixel(x, y), c2.get_pixel(x, y));
+            let differs = if options.exact {
+                p1 != p2
+            } else {
+                color_delta(&p1, &p2).abs() > max_delta
+            };
+            let color = if differs {
+                if !options.exact
+                    && options.detect_anti_aliasing
+                    && (is_anti_aliased(c1, c2, x, y) || is_anti_aliased(c2, c1, x, y))
+                {
+                    anti_aliased += 1;
+                    options.anti_aliased_color.clone()
+                } else {
+                    count += 1;
+                    changed[(y * dimensions.width + x) as usize] = true;
+                    options.diff_color.clone()
+                }
+            } else {
+                let (brightness, _, _) = to_yiq(&p1);
+                let grey = 255.0 + (brightness - 255.0) * options.fade.clamp(0.0, 1.0);
+                from_grey(grey.round().clamp(0.0, 255.0) as u8)
+            };
+            canvas.set_pixel_mut(x, y, &color);
+        }
+    }
+    let regions = changed_regions(&changed, dimensions.width, dimensions.height);
+    Ok(Diff {
+        canvas,
+        count,
+        anti_aliased,
+        regions,
+    })
+}
+
+/// Absolute difference of every channel of `c1` and `c2`. Fails if the dimensions differ.
+pub fn diff_debug(c1: &Canvas, c2: &Canvas) -> Result<Canvas, ImageError> {
+    check_dimensions(c1, c2)?;
+    let dimensions = c1.dimensions();
+    let pixels = c1
+        .pixels()
+        .zip(c2.pixels())
+        .map(|(p1, p2)| p1.diff(p2))
+        .collect();
+    Ok(Canvas::new_with_data(
+        dimensions.width,
+        dimensions.height,
+        pixels,
+    ))
 }
 
 // https://stackoverflow.com/questions/20271479/what-does-it-mean-to-get-the-mse-mean-error-squared-for-2-images
+/// Integer mean squared error of the region where `c1` and `c2` overlap.
+#[deprecated(note = "use `metrics::mse`, which does not truncate and rejects mismatched sizes")]
 pub fn error(c1: &Canvas, c2: &Canvas) -> u128 {
     let c1_dim = c1.dimensions();
     let c2_dim = c2.dimensions();
@@ -154,49 +422,54 @@ pub fn error(c1: &Canvas, c2: &Canvas) -> u128 {
 }
 
 // TODO: Should these exist here or only in the Canvas as private members?
+/// Porter-Duff source-over for a single channel. Returns the premultiplied result, divide it by
+/// the resulting alpha to get the color.
 pub fn apply_alpha_color(
     source_color: f32,
     source_alpha: f32,
     destination_color: f32,
-    _destination_alpha: f32,
+    destination_alpha: f32,
 ) -> f32 {
-    source_color * source_alpha + destination_color * (1.0f32 - source_alpha)
+    source_color * source_alpha + destination_color * destination_alpha * (1.0f32 - source_alpha)
 }
 
 //TODO: RENAME
 // We draw source onto destination
 pub fn overlap_colors(destination: &Pixel, source: &Pixel) -> Pixel {
-    /*if source.a == 255 {
+    if source.a == 255 {
         return source.clone();
-    }*/
+    }
     let source_normalized = source.normalize();
     let destination_normalized = destination.normalize();
+    let new_a = source_normalized.3 + destination_normalized.3 * (1.0f32 - source_normalized.3);
+    if new_a <= 0.0 {
+        return Pixel::new(0, 0, 0, 0);
+    }
     let new_r = apply_alpha_color(
         source_normalized.0,
         source_normalized.3,
         destination_normalized.0,
         destination_normalized.3,
-    );
+    ) / new_a;
     let new_g = apply_alpha_color(
         source_normalized.1,
         source_normalized.3,
         destination_normalized.1,
         destination_normalized.3,
-    );
+    ) / new_a;
     let new_b = apply_alpha_color(
         source_normalized.2,
         source_normalized.3,
         destination_normalized.2,
         destination_normalized.3,
-    );
-    let new_a = apply_alpha_color(
-        source_normalized.3,
-        source_normalized.3,
-        destination_normalized.3,
-        destination_normalized.3,
-    );
+    ) / new_a;
 
-    Pixel::denormalize(new_r, new_g, new_b, new_a)
+    Pixel::from(
+        (new_r * 255.0).round(),
+        (new_g * 255.0).round(),
+        (new_b * 255.0).round(),
+        (new_a * 255.0).round(),
+    )
 }
 
 pub fn merge_chunks_vertically(chunks: &Vec<Canvas>) -> Canvas {
@@ -224,3 +497,93 @@ pub fn merge_chunks_horizontally(chunks: &Vec<Canvas>) -> Canvas {
     }
     canvas
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixels::{ColorTrait, Colors};
+
+    fn edge() -> Canvas {
+        Canvas::new(10, 10).draw_square(0, 0, 5, 10, &Colors::BLACK)
+    }
+
+    #[test]
+    fn diff_squared_does_not_overflow() {
+        let (r, g, _, a) = diff_squared(&Pixel::new(0, 255, 3, 9), &Pixel::new(255, 0, 3, 9));
+        assert_eq!((r, g, a), (65025, 65025, 0));
+    }
+
+    #[test]
+    fn clamp_accepts_equal_bounds() {
+        assert_eq!(clamp(0, 0, 7), 0);
+        assert_eq!(clamp(2, 5, 7), 5);
+        assert_eq!(
+            Canvas::new_with_background(1, 1, Colors::RED).get_pixel(3, 0),
+            Colors::RED
+        );
+    }
+
+    #[test]
+    fn diff_of_identical_canvases() {
+        let result = diff(&edge(), &edge()).unwrap();
+        assert_eq!((result.count, result.anti_aliased), (0, 0));
+        assert!(result.regions.is_empty());
+        // The original is faded towards white
+        assert_eq!(result.canvas.get_pixel(0, 0), from_grey(230));
+        assert_eq!(result.canvas.get_pixel(9, 0), Colors::WHITE);
+    }
+
+    #[test]
+    fn diff_finds_changed_regions() {
+        let changed = edge()
+            .draw_square(6, 3, 2, 2, &Colors::BLUE)
+            .set_pixel(0, 9, &Colors::WHITE);
+        let result = diff(&edge(), &changed).unwrap();
+        assert_eq!(result.count, 5);
+        assert_eq!(result.canvas.get_pixel(6, 3), Pixel::new(255, 0, 0, 255));
+        assert_eq!(
+            result.regions,
+            vec![
+                Rect {
+                    start: Point { x: 6, y: 3 },
+                    size: Size {
+                        width: 2,
+                        height: 2
+                    }
+                },
+                Rect {
+                    start: Point { x: 0, y: 9 },
+                    size: Size {
+                        width: 1,
+                        height: 1
+                    }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_tolerance_and_anti_aliasing() {
+        let grey = Canvas::new_with_background(4, 4, from_grey(100));
+        let slightly = grey.clone().set_pixel(1, 1, &from_grey(103));
+        assert_eq!(diff(&grey, &slightly).unwrap().count, 0);
+        let strict = DiffOptions::new().set_tolerance(0.0);
+        assert_eq!(diff_with(&grey, &slightly, &strict).unwrap().count, 1);
+
+        // A grey pixel on a black and white edge looks like anti-aliasing
+        let smoothed = edge().set_pixel(5, 4, &from_grey(128));
+        let result = diff(&edge(), &smoothed).unwrap();
+        assert_eq!((result.count, result.anti_aliased), (0, 1));
+        assert_eq!(result.canvas.get_pixel(5, 4), Pixel::new(255, 255, 0, 255));
+        let options = DiffOptions::default().set_detect_anti_aliasing(false);
+        assert_eq!(diff_with(&edge(), &smoothed, &options).unwrap().count, 1);
+    }
+
+    #[test]
+    fn diff_rejects_mismatched_sizes() {
+        assert!(diff(&edge(), &Canvas::new(10, 11)).is_err());
+        assert!(diff_debug(&edge(), &Canvas::new(11, 10)).is_err());
+        let debug = diff_debug(&edge(), &Canvas::new(10, 10)).unwrap();
+        assert_eq!(debug.get_pixel(0, 0), Pixel::new(255, 255, 255, 0));
+    }
+}